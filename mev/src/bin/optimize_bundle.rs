@@ -0,0 +1,64 @@
+use alloy::eips::Encodable2718;
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::node_bindings::Anvil;
+use alloy::primitives::{Bytes, utils::parse_ether};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::rpc::types::mev::EthSendBundle;
+use alloy::signers::local::PrivateKeySigner;
+use alloy_flashbots::optimizer::{BundleOptimizer, Objective};
+use eyre::Result;
+
+/// 在 anvil 上模拟 bundle 中交易的所有合法顺序, 选出 coinbase 收益最高的顺序
+#[tokio::main]
+async fn main() -> Result<()> {
+    // anvil 默认按 gas price 打包, 使用 fifo 保证按发送顺序执行
+    // 实际使用时可以 .fork(rpc_url) 在主网状态上模拟
+    let anvil = Anvil::new().args(["--order", "fifo"]).try_spawn()?;
+    let funder: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let funder_address = funder.address();
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::new(funder))
+        .on_http(anvil.endpoint_url());
+
+    // 一个没有余额的新账户, 它的交易需要先由 funder 转账才能执行
+    let fresh = PrivateKeySigner::random();
+    let fresh_address = fresh.address();
+
+    let fund_tx = TransactionRequest::default()
+        .with_to(fresh_address)
+        .with_value(parse_ether("1")?);
+    let fund_envelope = provider.fill(fund_tx).await?;
+    let fund_raw: Bytes = fund_envelope.as_envelope().unwrap().encoded_2718().into();
+
+    let fresh_tx = TransactionRequest::default()
+        .with_to(funder_address)
+        .with_value(parse_ether("0.1")?)
+        .with_nonce(0)
+        .with_chain_id(provider.get_chain_id().await?)
+        .with_gas_limit(21_000)
+        .with_max_fee_per_gas(20_000_000_000u128)
+        .with_max_priority_fee_per_gas(2_000_000_000u128)
+        .build(&EthereumWallet::new(fresh))
+        .await?;
+    let fresh_raw: Bytes = fresh_tx.encoded_2718().into();
+
+    // 故意把顺序写反, 新账户的交易在转账之前执行会因为余额不足被拒绝
+    let mut bundle = EthSendBundle::default();
+    bundle.txs.push(fresh_raw);
+    bundle.txs.push(fund_raw);
+    bundle.block_number = provider.get_block_number().await? + 1;
+
+    let optimizer =
+        BundleOptimizer::new(provider.clone(), Objective::CoinbaseDelta).max_permutations(24);
+    let (optimized, simulated) = optimizer.optimize(&bundle).await?;
+
+    println!("best order: {:?}", simulated.order);
+    println!(
+        "coinbase delta: {}, gas used: {}",
+        simulated.coinbase_delta, simulated.gas_used
+    );
+    println!("optimized bundle: {:#?}", optimized);
+
+    Ok(())
+}
@@ -4,42 +4,20 @@
 use alloy::consensus::TxEnvelope;
 use alloy::eips::Encodable2718;
 use alloy::network::EthereumWallet;
-use alloy::primitives::private::serde::{Deserialize, Serialize};
-use alloy::primitives::utils::{eip191_message, parse_units};
-use alloy::primitives::{
-    B256, Bytes, TxHash, TxKind, U256, address, eip191_hash_message, keccak256,
-};
+use alloy::primitives::utils::parse_units;
+use alloy::primitives::{Bytes, TxHash, TxKind, U256, address};
 use alloy::providers::{Provider, ProviderBuilder, WsConnect};
-use alloy::rpc::client::{RpcCall, RpcClient};
-use alloy::rpc::types::mev::{BundleStats, EthBundleHash, EthSendBundle};
-use alloy::signers::Signer;
+use alloy::rpc::types::mev::{BundleStats, EthSendBundle};
 use alloy::signers::local::{LocalSigner, PrivateKeySigner};
-// use alloy::transports::http::Client;
-// use alloy::transports::http::Http;
-use alloy::transports::http::reqwest::header::HeaderValue;
-use alloy::transports::http::{
-    Http,
-    HyperClient,
-    HyperResponse,
-    HyperResponseFut,
-    hyper,
-    // hyper::body::Body,
-    hyper_util::{
-        client::legacy::{Client, Error},
-        rt::TokioExecutor,
-    },
-};
-use hyper_tls::HttpsConnector;
+use alloy_flashbots::eth::{BundleEvent, BundleTracker, FlashbotsEthExt, GetBundleStatsParam};
+use alloy_flashbots::signature::flashbots_provider;
 
 // use alloy::rlp::Buf;
 use alloy::{hex, sol};
 use eyre::Result;
 // use futures_util::FutureExt;
 use futures_util::StreamExt;
-use http_body_util::{BodyExt, Full};
 use std::path::PathBuf;
-use std::thread::sleep;
-use tower::{Layer, Service};
 
 // 使用abi, 合约地址, provider创建contract instance 和合约交互
 sol! {
@@ -225,28 +203,9 @@ async fn main() -> Result<()> {
     //     .wallet(wallet.clone())
     //     .on_http(flashbots_url);
 
-    // Create a new Hyper client.
-    // let hyper_client =
-    //     Client::builder(TokioExecutor::new()).build_http::<Full<hyper::body::Bytes>>();
-
-    // support tls
-    let https = HttpsConnector::new();
-    let hyper_client =
-        Client::builder(TokioExecutor::new()).build::<_, Full<hyper::body::Bytes>>(https);
-
-    // Use tower::ServiceBuilder to stack layers on top of the Hyper client.
-    let service = tower::ServiceBuilder::new()
-        .layer(FlashbotsSignatureLayer::new(private_signer))
-        .service(hyper_client);
-
-    // Instantiate the HyperClient with the stacked layers.
+    // 请求带上 X-Flashbots-Signature header
     let flashbots_http_url = "https://relay-sepolia.flashbots.net".parse()?;
-    let layer_transport = HyperClient::<Full<hyper::body::Bytes>, _>::with_service(service);
-    let http = Http::with_client(layer_transport, flashbots_http_url);
-
-    // Create a new RPC client with the Hyper transport.
-    let flashbots_rpc_client = RpcClient::new(http, true);
-    let flashbots_provider = ProviderBuilder::new().on_client(flashbots_rpc_client);
+    let flashbots_provider = flashbots_provider(private_signer, flashbots_http_url);
 
     // 3. 构造自己的交易
     let nft_instance = OpenSpaceNFT::new(nft_contract_address, flashbots_provider.clone());
@@ -363,217 +322,46 @@ async fn main() -> Result<()> {
     // 5. 发送 bundle
     // let request = flashbots_provider.client().make_request("eth_sendBundle", [bundle]);
     // let resp = flashbots_provider.client().request::<RpcCall<(EthSendBundle,), EthBundleHash>>("eth_sendBundle", (bundle,));
-    let bundle_hash = flashbots_provider.send_bundle(bundle).await?;
+    let bundle_hash = flashbots_provider.send_bundle(bundle.clone()).await?;
     println!("Bundle Hash: {:?}", bundle_hash);
 
-    let hexed_block_number = format!("0x{:x}", target_block_number + 10);
-
     // 6. 查询 bundle 状态
-    let param = GetBundleStatsParam {
-        bundle_hash: bundle_hash.bundle_hash,
-        block_number: hexed_block_number,
-    };
-
-    loop {
-        // TODO: param是否会有底层没存释放不及时导致内存泄漏的风险？内存分配器（malloc），或者 jemalloc 带来的OOM风险?
-        // 蚂蚁集团：如何在生产环境排查Rust内存占用过高问题
-        // https://rustmagazine.github.io/rust_magazine_2021/chapter_5/rust-memory-troubleshootting.html
-        let bundle_stats = flashbots_provider
-            .get_bundle_stats_v2(param.clone())
-            .await?;
-        match bundle_stats {
-            BundleStats::Unknown => {
-                println!("Bundle status: Unknown");
-            }
-            BundleStats::Seen(stats) => {
-                println!("Bundle status Seen: {:?}", stats);
-            }
-            BundleStats::Simulated(stats) => {
-                println!("Bundle status Simulated: {:#?}", stats);
-                // return Ok(());
-            }
+    let param = GetBundleStatsParam::new(bundle_hash.bundle_hash, target_block_number + 10);
+    let bundle_stats = flashbots_provider.get_bundle_stats_v2(param).await?;
+    match bundle_stats {
+        BundleStats::Unknown => {
+            println!("Bundle status: Unknown");
         }
-
-        sleep(std::time::Duration::from_secs(1));
-    }
-}
-
-// 为 Provider 扩展 send_bundle 方法
-trait FlashbotsProviderExt: Provider {
-    fn send_bundle(
-        &self,
-        eth_send_bundle: EthSendBundle,
-    ) -> RpcCall<(EthSendBundle,), EthBundleHash> {
-        self.client()
-            .request("eth_sendBundle", (eth_send_bundle,))
-            .into()
-    }
-    //   fn get_raw_transaction_by_hash(&self, hash: TxHash) -> ProviderCall<(TxHash,), Option<Bytes>> {
-    //         self.client().request("eth_getRawTransactionByHash", (hash,)).into()
-    //     }
-
-    fn get_bundle_stats_v2(
-        &self,
-        param: GetBundleStatsParam,
-    ) -> RpcCall<(GetBundleStatsParam,), BundleStats> {
-        self.client()
-            .request("flashbots_getBundleStatsV2", (param,))
-            .into()
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct GetBundleStatsParam {
-    bundle_hash: B256,
-    // block_number: BlockNumber,
-    block_number: String,
-}
-
-// 为所有 Provider 实现扩展 trait
-impl<P: Provider> FlashbotsProviderExt for P {}
-
-/// === modify layer
-// 自定义 Layer, 用于添加flashbots header X-Flashbots-Signature
-#[derive(Clone)]
-struct FlashbotsSignatureLayer {
-    // wallet: EthereumWallet,
-    wallet: PrivateKeySigner,
-}
-
-impl FlashbotsSignatureLayer {
-    fn new(wallet: PrivateKeySigner) -> Self {
-        Self { wallet }
-    }
-}
-
-impl<S> Layer<S> for FlashbotsSignatureLayer {
-    type Service = FlashbotsSignatureService<S>;
-    fn layer(&self, inner: S) -> Self::Service {
-        FlashbotsSignatureService {
-            inner,
-            wallet: self.wallet.clone(),
+        BundleStats::Seen(stats) => {
+            println!("Bundle status Seen: {:?}", stats);
+        }
+        BundleStats::Simulated(stats) => {
+            println!("Bundle status Simulated: {:#?}", stats);
         }
-    }
-}
-
-/// === 实现flashbots signature service 来添加header
-// 自定义 Service
-#[derive(Clone)]
-struct FlashbotsSignatureService<S> {
-    inner: S,
-    // wallet: EthereumWallet,
-    wallet: PrivateKeySigner,
-}
-
-impl<S, B> Service<hyper::Request<B>> for FlashbotsSignatureService<S>
-where
-    S: Service<hyper::Request<B>, Response = HyperResponse, Error = Error>
-        + Clone
-        + Send
-        + Sync
-        + 'static,
-    S::Future: Send,
-    S::Error: std::error::Error + Send + Sync + 'static,
-    // B: From<Vec<u8>> + Send + 'static + Clone + Sync + std::fmt::Debug,
-    B: hyper::body::Body<Data = hyper::body::Bytes> + Send + 'static + Clone + Sync,
-    <B as hyper::body::Body>::Error: std::fmt::Debug,
-{
-    type Response = HyperResponse;
-    type Error = Error;
-    type Future = HyperResponseFut;
-
-    fn poll_ready(
-        &mut self,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Result<(), Self::Error>> {
-        self.inner.poll_ready(cx)
     }
 
-    #[allow(unused_mut)]
-    fn call(&mut self, mut req: hyper::Request<B>) -> Self::Future {
-        // async move {
-        let mut cloned_req = req.clone();
-        let wallet = self.wallet.clone();
-        // 获取请求体
-        // let mut req_body: Vec<u8> = req.into_body().clone().into();
-        // let rt = tokio::runtime::Runtime::new().unwrap();
-
-        // let whole_body_c = rt.block_on(req.collect()).unwrap();
-        let whole_body_c = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(req.collect())
-        })
-        .unwrap();
-        let whole_body = whole_body_c.to_bytes();
-        println!("Body: {}", String::from_utf8_lossy(&whole_body));
-        // let body_bytes = hyper::body::to_bytes(req.body_mut()).await?;
-        // let body_bytes = whole_body.iter().collect::<Vec<u8>>();
-        let hashed_body = keccak256(whole_body.as_ref());
-        println!("Hashed Body: {:?}", hex::encode(hashed_body));
-        // bug fix
-        // 参考: https://github.com/onbjerg/ethers-flashbots/blob/64a0ac980702037dc7499ffdd46cb6bf406442f3/src/relay.rs#L84
-        // 给body签名的步骤是:
-        // 1. hash(body)
-        // 2. hex hashed_body
-        // 3. add 0x prefix to hexed_hashed_body
-        // 4. sign_message(0xhexed_hashed_body) 到这里走eth eip191 sign_message的流程就可以了
-        let hexed_body = hex::encode(hashed_body);
-        let hexed_body_with_perfix = format!("0x{}", hexed_body);
-
-        // 生成签名
-        let signature = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current()
-                .block_on(wallet.sign_hash(&eip191_hash_message(&hexed_body_with_perfix)))
-            //sign_message  ==  self.sign_hash(&eip191_hash_message(message)).await
+    // 7. 追踪 bundle 是否上链
+    // relay 的状态只能说明 bundle 被模拟/提交给了 builder, 上链之后区块还可能被 reorg 掉
+    // 这里上链之后继续观察 6 个区块, 交易从 canonical chain 上消失时重新发送, 最多 3 次
+    let outcome = BundleTracker::new(provider.clone(), flashbots_provider.clone(), bundle)
+        .confirmation_depth(6)
+        .max_resubmits(3)
+        .track(|event| match event {
+            BundleEvent::Included {
+                block_number,
+                block_hash,
+            } => println!("Bundle included in block {block_number}, hash: {block_hash:?}"),
+            BundleEvent::Reorged {
+                block_number,
+                block_hash,
+            } => println!("Bundle block {block_number} reorged, hash: {block_hash:?}"),
+            BundleEvent::Resubmitted {
+                bundle_hash,
+                block_number,
+            } => println!("Bundle resubmitted for block {block_number}, hash: {bundle_hash:?}"),
         })
-        .unwrap();
-        println!("EIP-191 Message: {:?}", eip191_message(hashed_body));
-        println!(
-            "hexed EIP-191 Message: {:?}",
-            hex::encode(eip191_message(hashed_body))
-        );
-
-        println!("Signature: {:?}", hex::encode(signature.as_bytes()));
-        // let signature = rt.block_on(wallet.sign_hash(&eip191_hash_message(hashed_body))).unwrap();
-        // let signature = wallet.sign_hash(&eip191_hash_message(hashed_body)).await.unwrap();
-        let header_value = format!(
-            "{}:0x{}",
-            wallet.address(),
-            hex::encode(signature.as_bytes())
-        );
-        println!("Header: {}", header_value);
-
-        // === 验证一下签名，看是否正常
-        let recover_addr = signature
-            .recover_address_from_msg(&hexed_body_with_perfix)
-            .unwrap();
-        println!("Recover address from msg: {:?}", recover_addr);
-        let recover_addr_from_hash = signature
-            .recover_address_from_prehash(&eip191_hash_message(&hexed_body_with_perfix))
-            .unwrap();
-        println!(
-            "Recover address from final hash: {:?}",
-            recover_addr_from_hash
-        );
-
-        // 修改请求头
-        cloned_req.headers_mut().insert(
-            "X-Flashbots-Signature",
-            HeaderValue::from_str(&header_value).unwrap(),
-        );
-
-        // 重新设置 body
-        // TODO:?
-        // *req.body_mut() = Body::(body_bytes);
-
-        // 调用底层服务
-        // let response = self.inner.call(cloned_req).await.unwrap();
-        // Ok(response)
-
-        let fut = self.inner.call(cloned_req);
-
-        Box::pin(fut)
-        // }
-        // .boxed()
-    }
+        .await?;
+    println!("Bundle outcome: {:?}", outcome);
+
+    Ok(())
 }
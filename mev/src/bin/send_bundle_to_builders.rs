@@ -0,0 +1,65 @@
+use alloy::eips::Encodable2718;
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{Bytes, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::rpc::types::mev::EthSendBundle;
+use alloy::signers::local::{LocalSigner, PrivateKeySigner};
+use alloy_flashbots::fanout::{BundleFanout, adapt_eth_bundle};
+use alloy_flashbots::registry::{BuilderRegistry, ETH_SEND_BUNDLE};
+use eyre::Result;
+use std::path::PathBuf;
+
+/// 根据 builders.json 中每个 builder 的能力, 把 bundle 发送给链上所有支持的 builder
+#[tokio::main]
+async fn main() -> Result<()> {
+    let registry = BuilderRegistry::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/config/builders.json"
+    ))?;
+
+    // 读取 本地 keystore文件, 解锁keystore 创建signer
+    let keystore_file_path = PathBuf::from(std::env::var("KEYSTORE_PATH")?);
+    let keystore_signer =
+        LocalSigner::decrypt_keystore(keystore_file_path, std::env::var("KEYSTORE_PWD")?)?;
+    let private_signer = PrivateKeySigner::from(keystore_signer.clone());
+    let wallet = EthereumWallet::from(keystore_signer.clone());
+
+    let http_url = "https://ethereum-sepolia-rpc.publicnode.com".parse()?;
+    let provider = ProviderBuilder::new().wallet(wallet).on_http(http_url);
+    let chain_id = provider.get_chain_id().await?;
+
+    // 给自己转账 0 ETH
+    let tx_req = TransactionRequest::default()
+        .with_to(keystore_signer.address())
+        .with_value(U256::ZERO);
+    let sendable = provider.fill(tx_req).await?;
+    let tx_encoded: Bytes = sendable.as_envelope().unwrap().encoded_2718().into();
+
+    let mut bundle = EthSendBundle::default();
+    bundle.txs.push(tx_encoded);
+    bundle.block_number = provider.get_block_number().await? + 1;
+    bundle.replacement_uuid = Some("2d1c2f4e-7a7b-4a8e-9c4a-0f7c6c1e2b3d".to_string());
+
+    // 主网上不同 builder 收到的 bundle 字段不同
+    for builder in registry.supporting(1, ETH_SEND_BUNDLE) {
+        println!(
+            "mainnet builder {} receives: {:?}",
+            builder.name,
+            adapt_eth_bundle(builder, &bundle)
+        );
+    }
+
+    let fanout = BundleFanout::new(&registry, chain_id, private_signer)?;
+    for (builder, reason) in fanout.skipped() {
+        println!("warning: skip {builder}, {reason}");
+    }
+    for result in fanout.send_bundle(&bundle).await {
+        match result.result {
+            Ok(bundle_hash) => println!("{}: bundle hash {:?}", result.builder, bundle_hash),
+            Err(e) => println!("{}: send bundle failed, {e:?}", result.builder),
+        }
+    }
+
+    Ok(())
+}
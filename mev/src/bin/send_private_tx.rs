@@ -0,0 +1,71 @@
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::U256;
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::rpc::types::mev::{PrivateTransactionPreferences, PrivateTransactionRequest};
+use alloy::signers::local::{LocalSigner, PrivateKeySigner};
+use alloy_flashbots::eth::{PrivateTxOutcome, PrivateTxTracker};
+use alloy_flashbots::signature::flashbots_provider;
+use eyre::Result;
+use std::path::PathBuf;
+
+/// 不需要 bundle, 但不想进入公开 mempool 的单笔交易, 使用 eth_sendPrivateTransaction 发送
+#[tokio::main]
+async fn main() -> Result<()> {
+    // 读取 本地 keystore文件, 解锁keystore 创建signer
+    let keystore_file_path = PathBuf::from(std::env::var("KEYSTORE_PATH")?);
+    let keystore_signer =
+        LocalSigner::decrypt_keystore(keystore_file_path, std::env::var("KEYSTORE_PWD")?)?;
+    let private_signer = PrivateKeySigner::from(keystore_signer.clone());
+    let wallet = EthereumWallet::from(keystore_signer.clone());
+
+    let http_url = "https://ethereum-sepolia-rpc.publicnode.com".parse()?;
+    let provider = ProviderBuilder::new()
+        .wallet(wallet.clone())
+        .on_http(http_url);
+
+    let flashbots_url = "https://relay-sepolia.flashbots.net".parse()?;
+    let relay = flashbots_provider(private_signer, flashbots_url);
+
+    // 构造交易, 这里给自己转账 0 ETH
+    let tx_req = TransactionRequest::default()
+        .with_to(keystore_signer.address())
+        .with_value(U256::ZERO);
+    let sendable = provider.fill(tx_req).await?;
+    let envelope = sendable.as_envelope().unwrap();
+    println!("private tx hash: {:?}", envelope.tx_hash());
+
+    // 最多在之后的 5 个区块内打包, 并开启 fast mode 把交易分享给所有注册的 builders
+    let max_block_number = provider.get_block_number().await? + 5;
+    let request = PrivateTransactionRequest::new(envelope)
+        .max_block_number(max_block_number)
+        .with_preferences(PrivateTransactionPreferences::default().into_fast());
+
+    let tracker = PrivateTxTracker::send(provider.clone(), relay, request).await?;
+    println!(
+        "eth_sendPrivateTransaction: {:?}, max block number: {}",
+        tracker.tx_hash(),
+        tracker.max_block_number()
+    );
+
+    match tracker.track().await? {
+        PrivateTxOutcome::Included(receipt) => {
+            println!(
+                "private tx included in block {:?}, status: {}",
+                receipt.block_number,
+                receipt.status()
+            );
+        }
+        PrivateTxOutcome::Expired {
+            cancelled,
+            cancel_error,
+        } => {
+            println!("private tx expired, eth_cancelPrivateTransaction: {cancelled}");
+            if let Some(error) = cancel_error {
+                println!("  cancel failed: {error}");
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
+use alloy::rpc::types::mev::PrivacyHint;
+use alloy::signers::local::LocalSigner;
+use alloy_flashbots::protect::{ProtectPreferences, protect_provider};
+use eyre::Result;
+use std::path::PathBuf;
+
+/// 读请求走普通 rpc, eth_sendRawTransaction 走 Flashbots Protect
+/// 发送交易的代码和普通 wallet provider 完全一样
+#[tokio::main]
+async fn main() -> Result<()> {
+    // 读取 本地 keystore文件, 解锁keystore 创建signer
+    let keystore_file_path = PathBuf::from(std::env::var("KEYSTORE_PATH")?);
+    let keystore_signer =
+        LocalSigner::decrypt_keystore(keystore_file_path, std::env::var("KEYSTORE_PWD")?)?;
+    let from = keystore_signer.address();
+    let wallet = EthereumWallet::from(keystore_signer);
+
+    let rpc_url = "https://ethereum-sepolia-rpc.publicnode.com".parse()?;
+    let protect_url = "https://rpc-sepolia.flashbots.net".parse()?;
+    // 只分享 hash 和 logs, 只允许 flashbots builder 打包
+    let preferences = ProtectPreferences::default()
+        .with_hints(PrivacyHint::default().with_hash().with_logs())
+        .with_builder("flashbots");
+    println!(
+        "protect endpoint: {}",
+        preferences.endpoint("https://rpc-sepolia.flashbots.net".parse()?)
+    );
+
+    let provider = protect_provider(wallet, rpc_url, protect_url, &preferences);
+
+    // 给自己转账 0 ETH
+    let tx = TransactionRequest::default()
+        .with_to(from)
+        .with_value(U256::ZERO);
+    let pending_tx = provider.send_transaction(tx).await?;
+    println!(
+        "Pending tx sent to protect, tx hash: {:?}",
+        pending_tx.tx_hash()
+    );
+
+    let receipt = pending_tx.get_receipt().await?;
+    println!(
+        "protect tx included in block {:?}, status: {}",
+        receipt.block_number,
+        receipt.status()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,245 @@
+use std::time::Duration;
+
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{B256, BlockHash, TxHash, keccak256};
+use alloy::providers::Provider;
+use alloy::rpc::types::mev::EthSendBundle;
+use eyre::Result;
+
+use super::FlashbotsEthExt;
+
+/// 默认在上链之后继续观察的区块数量
+pub const DEFAULT_CONFIRMATION_DEPTH: u64 = 6;
+
+/// 追踪过程中的事件, 通过 [`BundleTracker::track`] 的回调通知
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleEvent {
+    /// bundle 中的交易出现在了 canonical chain 上
+    Included {
+        block_number: u64,
+        block_hash: BlockHash,
+    },
+    /// 包含 bundle 的区块被 reorg 掉了, 交易已经不在 canonical chain 上
+    Reorged {
+        block_number: u64,
+        block_hash: BlockHash,
+    },
+    /// 目标区块没有打包或者被 reorg 之后, 重新发送到了新的目标区块
+    Resubmitted {
+        bundle_hash: B256,
+        block_number: u64,
+    },
+}
+
+/// bundle 的最终结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleOutcome {
+    /// 上链后经过了 confirmation_depth 个区块仍然在 canonical chain 上
+    Confirmed {
+        block_number: u64,
+        block_hash: BlockHash,
+    },
+    /// 被 reorg 掉, 并且没有开启(或者用完了)自动重新发送
+    Reorged {
+        block_number: u64,
+        block_hash: BlockHash,
+    },
+    /// 目标区块已经过去, bundle 没有被打包
+    NotIncluded { block_number: u64 },
+}
+
+enum State {
+    Pending {
+        target_block: u64,
+    },
+    Included {
+        block_number: u64,
+        block_hash: BlockHash,
+    },
+}
+
+/// 追踪 bundle 的上链情况, 上链后在 canonical chain 上继续观察 confirmation_depth 个区块,
+/// 检测到交易从 canonical chain 上消失时报告 reorg, 或者自动重新发送
+///
+/// - provider: 普通 rpc, 用来查询区块和交易回执
+/// - relay: flashbots relay, 用来重新发送 bundle
+pub struct BundleTracker<P, R> {
+    provider: P,
+    relay: R,
+    bundle: EthSendBundle,
+    tx_hashes: Vec<TxHash>,
+    confirmation_depth: u64,
+    max_resubmits: usize,
+    poll_interval: Duration,
+}
+
+impl<P: Provider, R: Provider> BundleTracker<P, R> {
+    /// bundle 需要已经通过 eth_sendBundle 发送过
+    pub fn new(provider: P, relay: R, bundle: EthSendBundle) -> Self {
+        let tx_hashes = bundle.txs.iter().map(keccak256).collect();
+        Self {
+            provider,
+            relay,
+            bundle,
+            tx_hashes,
+            confirmation_depth: DEFAULT_CONFIRMATION_DEPTH,
+            max_resubmits: 0,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// 上链之后继续观察的区块数量, 默认 [`DEFAULT_CONFIRMATION_DEPTH`]
+    pub fn confirmation_depth(mut self, depth: u64) -> Self {
+        self.confirmation_depth = depth;
+        self
+    }
+
+    /// 没有打包或者被 reorg 之后自动重新发送的最大次数, 默认 0 即只报告不重发
+    pub fn max_resubmits(mut self, max_resubmits: usize) -> Self {
+        self.max_resubmits = max_resubmits;
+        self
+    }
+
+    /// 设置轮询区块的间隔, 默认 1s
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// 追踪直到确认, 被 reorg 或者没有打包, 过程中的事件通过 on_event 通知
+    pub async fn track(mut self, mut on_event: impl FnMut(&BundleEvent)) -> Result<BundleOutcome> {
+        let mut resubmits = 0;
+        let mut state = State::Pending {
+            target_block: self.bundle.block_number,
+        };
+
+        loop {
+            let head = self.provider.get_block_number().await?;
+
+            state = match state {
+                State::Pending { target_block } => match self.find_inclusion().await? {
+                    Some((block_number, block_hash)) => {
+                        on_event(&BundleEvent::Included {
+                            block_number,
+                            block_hash,
+                        });
+                        State::Included {
+                            block_number,
+                            block_hash,
+                        }
+                    }
+                    None if head >= target_block => {
+                        if resubmits >= self.max_resubmits {
+                            return Ok(BundleOutcome::NotIncluded {
+                                block_number: target_block,
+                            });
+                        }
+                        resubmits += 1;
+                        let event = self.resubmit(head + 1).await?;
+                        on_event(&event);
+                        State::Pending {
+                            target_block: head + 1,
+                        }
+                    }
+                    None => State::Pending { target_block },
+                },
+                State::Included {
+                    block_number,
+                    block_hash,
+                } => {
+                    let canonical = self
+                        .provider
+                        .get_block_by_number(BlockNumberOrTag::Number(block_number))
+                        .await?
+                        .map(|block| block.header.hash);
+
+                    if canonical == Some(block_hash) {
+                        if head >= block_number + self.confirmation_depth {
+                            return Ok(BundleOutcome::Confirmed {
+                                block_number,
+                                block_hash,
+                            });
+                        }
+                        State::Included {
+                            block_number,
+                            block_hash,
+                        }
+                    } else {
+                        on_event(&BundleEvent::Reorged {
+                            block_number,
+                            block_hash,
+                        });
+                        // 交易可能已经被打包进了新的 canonical block,
+                        // 节点可能还会短暂返回旧区块的回执, 这种情况不算重新上链
+                        match self
+                            .find_inclusion()
+                            .await?
+                            .filter(|inclusion| *inclusion != (block_number, block_hash))
+                        {
+                            Some((block_number, block_hash)) => {
+                                on_event(&BundleEvent::Included {
+                                    block_number,
+                                    block_hash,
+                                });
+                                State::Included {
+                                    block_number,
+                                    block_hash,
+                                }
+                            }
+                            None if resubmits < self.max_resubmits => {
+                                resubmits += 1;
+                                let event = self.resubmit(head + 1).await?;
+                                on_event(&event);
+                                State::Pending {
+                                    target_block: head + 1,
+                                }
+                            }
+                            None => {
+                                return Ok(BundleOutcome::Reorged {
+                                    block_number,
+                                    block_hash,
+                                });
+                            }
+                        }
+                    }
+                }
+            };
+
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// bundle 中的交易是原子的, 所有交易都在同一个区块中才算上链
+    async fn find_inclusion(&self) -> Result<Option<(u64, BlockHash)>> {
+        let mut inclusion = None;
+        for tx_hash in &self.tx_hashes {
+            let Some(receipt) = self.provider.get_transaction_receipt(*tx_hash).await? else {
+                return Ok(None);
+            };
+            let (Some(block_number), Some(block_hash)) = (receipt.block_number, receipt.block_hash)
+            else {
+                return Ok(None);
+            };
+            match inclusion {
+                None => inclusion = Some((block_number, block_hash)),
+                Some(included) if included == (block_number, block_hash) => {}
+                // 部分交易在别的区块中(例如目标交易被单独打包了), 不算 bundle 上链
+                Some(_) => return Ok(None),
+            }
+        }
+        Ok(inclusion)
+    }
+
+    async fn resubmit(&mut self, block_number: u64) -> Result<BundleEvent> {
+        self.bundle.block_number = block_number;
+        let bundle_hash = self
+            .relay
+            .send_bundle(self.bundle.clone())
+            .await?
+            .bundle_hash;
+        Ok(BundleEvent::Resubmitted {
+            bundle_hash,
+            block_number,
+        })
+    }
+}
@@ -1 +1,74 @@
+mod bundle_tracker;
+mod private_tx;
 
+pub use bundle_tracker::{BundleEvent, BundleOutcome, BundleTracker};
+pub use private_tx::{PrivateTxOutcome, PrivateTxTracker};
+
+use alloy::primitives::{B256, TxHash};
+use alloy::providers::Provider;
+use alloy::rpc::client::RpcCall;
+use alloy::rpc::types::mev::{
+    BundleStats, CancelPrivateTransactionRequest, EthBundleHash, EthSendBundle,
+    PrivateTransactionRequest,
+};
+use serde::{Deserialize, Serialize};
+
+/// 为 Provider 扩展 flashbots relay 的 eth_ 命名空间方法
+///
+/// 这些请求都需要带 X-Flashbots-Signature header, provider 需要使用
+/// [`crate::signature::flashbots_provider`] 创建
+pub trait FlashbotsEthExt: Provider {
+    /// eth_sendBundle, 发送 bundle 到 relay
+    fn send_bundle(&self, bundle: EthSendBundle) -> RpcCall<(EthSendBundle,), EthBundleHash> {
+        self.client().request("eth_sendBundle", (bundle,))
+    }
+
+    /// flashbots_getBundleStatsV2, 查询 bundle 在 relay 中的状态
+    fn get_bundle_stats_v2(
+        &self,
+        param: GetBundleStatsParam,
+    ) -> RpcCall<(GetBundleStatsParam,), BundleStats> {
+        self.client()
+            .request("flashbots_getBundleStatsV2", (param,))
+    }
+
+    /// eth_sendPrivateTransaction, 单笔交易不进入公开 mempool, 直接发给 builders
+    fn send_private_transaction(
+        &self,
+        request: PrivateTransactionRequest,
+    ) -> RpcCall<(PrivateTransactionRequest,), TxHash> {
+        self.client()
+            .request("eth_sendPrivateTransaction", (request,))
+    }
+
+    /// eth_cancelPrivateTransaction, 停止继续提交该私有交易, 返回是否取消成功
+    fn cancel_private_transaction(
+        &self,
+        tx_hash: TxHash,
+    ) -> RpcCall<(CancelPrivateTransactionRequest,), bool> {
+        self.client().request(
+            "eth_cancelPrivateTransaction",
+            (CancelPrivateTransactionRequest { tx_hash },),
+        )
+    }
+}
+
+// 为所有 Provider 实现扩展 trait
+impl<P: Provider> FlashbotsEthExt for P {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetBundleStatsParam {
+    pub bundle_hash: B256,
+    /// hex 编码的区块号, 例如 0x10
+    pub block_number: String,
+}
+
+impl GetBundleStatsParam {
+    pub fn new(bundle_hash: B256, block_number: u64) -> Self {
+        Self {
+            bundle_hash,
+            block_number: format!("0x{:x}", block_number),
+        }
+    }
+}
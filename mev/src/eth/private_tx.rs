@@ -0,0 +1,280 @@
+use std::time::Duration;
+
+use alloy::primitives::TxHash;
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionReceipt;
+use alloy::rpc::types::mev::PrivateTransactionRequest;
+use eyre::Result;
+
+use super::FlashbotsEthExt;
+
+/// flashbots 没有指定 maxBlockNumber 时, 默认在之后的 25 个区块内尝试打包
+pub const DEFAULT_MAX_BLOCK_RANGE: u64 = 25;
+
+/// 私有交易的最终结果
+#[derive(Debug, Clone)]
+pub enum PrivateTxOutcome {
+    /// 在 maxBlockNumber 之前上链
+    Included(Box<TransactionReceipt>),
+    /// 超过 maxBlockNumber 还没有上链, cancelled 表示 eth_cancelPrivateTransaction 是否成功,
+    /// 请求失败时 cancelled 为 false, cancel_error 为错误信息
+    Expired {
+        cancelled: bool,
+        cancel_error: Option<String>,
+    },
+}
+
+/// 通过 eth_sendPrivateTransaction 发送单笔交易, 并追踪到上链或过期
+///
+/// - provider: 普通 rpc, 用来查询区块高度和交易回执
+/// - relay: flashbots relay, 用来发送和取消私有交易
+pub struct PrivateTxTracker<P, R> {
+    provider: P,
+    relay: R,
+    tx_hash: TxHash,
+    max_block_number: u64,
+    poll_interval: Duration,
+}
+
+impl<P: Provider, R: Provider> PrivateTxTracker<P, R> {
+    /// 发送私有交易, request 中没有设置 maxBlockNumber 时使用
+    /// 当前区块 + [`DEFAULT_MAX_BLOCK_RANGE`]
+    pub async fn send(
+        provider: P,
+        relay: R,
+        mut request: PrivateTransactionRequest,
+    ) -> Result<Self> {
+        let max_block_number = match request.max_block_number {
+            Some(max_block_number) => max_block_number,
+            None => provider.get_block_number().await? + DEFAULT_MAX_BLOCK_RANGE,
+        };
+        request.max_block_number = Some(max_block_number);
+
+        let tx_hash = relay.send_private_transaction(request).await?;
+
+        Ok(Self {
+            provider,
+            relay,
+            tx_hash,
+            max_block_number,
+            poll_interval: Duration::from_secs(1),
+        })
+    }
+
+    /// 设置轮询回执的间隔, 默认 1s
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn tx_hash(&self) -> TxHash {
+        self.tx_hash
+    }
+
+    pub fn max_block_number(&self) -> u64 {
+        self.max_block_number
+    }
+
+    /// 轮询交易回执直到上链, 超过 maxBlockNumber 后自动取消
+    pub async fn track(self) -> Result<PrivateTxOutcome> {
+        loop {
+            if let Some(receipt) = self.provider.get_transaction_receipt(self.tx_hash).await? {
+                return Ok(PrivateTxOutcome::Included(Box::new(receipt)));
+            }
+
+            let block_number = self.provider.get_block_number().await?;
+            if block_number > self.max_block_number {
+                // 过期前最后一个区块可能刚好打包了, 取消前再确认一次
+                if let Some(receipt) = self.provider.get_transaction_receipt(self.tx_hash).await? {
+                    return Ok(PrivateTxOutcome::Included(Box::new(receipt)));
+                }
+                // 交易已经过期, 取消失败也要返回观察到的结果
+                let outcome = match self.relay.cancel_private_transaction(self.tx_hash).await {
+                    Ok(cancelled) => PrivateTxOutcome::Expired {
+                        cancelled,
+                        cancel_error: None,
+                    },
+                    Err(e) => PrivateTxOutcome::Expired {
+                        cancelled: false,
+                        cancel_error: Some(e.to_string()),
+                    },
+                };
+                return Ok(outcome);
+            }
+
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Bytes, b256};
+    use alloy::providers::ProviderBuilder;
+    use alloy::transports::mock::Asserter;
+    use serde_json::json;
+
+    use super::*;
+
+    const TX_HASH: TxHash =
+        b256!("0x1111111111111111111111111111111111111111111111111111111111111111");
+
+    fn request(max_block_number: Option<u64>) -> PrivateTransactionRequest {
+        PrivateTransactionRequest {
+            tx: Bytes::from_static(&[0x02]),
+            max_block_number,
+            preferences: Default::default(),
+        }
+    }
+
+    fn receipt(block_number: u64) -> TransactionReceipt {
+        serde_json::from_value(json!({
+            "type": "0x2",
+            "status": "0x1",
+            "cumulativeGasUsed": "0x5208",
+            "logs": [],
+            "logsBloom": format!("0x{}", "0".repeat(512)),
+            "transactionHash": TX_HASH,
+            "transactionIndex": "0x0",
+            "blockHash": b256!("0x2222222222222222222222222222222222222222222222222222222222222222"),
+            "blockNumber": format!("{block_number:#x}"),
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x3b9aca00",
+            "from": "0x00000000000000000000000000000000000000aa",
+            "to": "0x00000000000000000000000000000000000000bb",
+            "contractAddress": null
+        }))
+        .unwrap()
+    }
+
+    /// provider 和 relay 分别按顺序返回 rpc 的响应
+    async fn send_tracker(
+        rpc: &Asserter,
+        relay: &Asserter,
+        max_block_number: Option<u64>,
+    ) -> PrivateTxTracker<impl Provider, impl Provider> {
+        relay.push_success(&TX_HASH);
+        let tracker = PrivateTxTracker::send(
+            ProviderBuilder::new().on_mocked_client(rpc.clone()),
+            ProviderBuilder::new().on_mocked_client(relay.clone()),
+            request(max_block_number),
+        )
+        .await
+        .unwrap();
+        tracker.poll_interval(Duration::from_millis(1))
+    }
+
+    /// 没有 receipt 的区块
+    fn push_pending(rpc: &Asserter, block_number: u64) {
+        rpc.push_success(&Option::<TransactionReceipt>::None);
+        rpc.push_success(&format!("{block_number:#x}"));
+    }
+
+    /// 超过 maxBlockNumber 时再确认一次 receipt
+    fn push_expired(rpc: &Asserter, block_number: u64) {
+        push_pending(rpc, block_number);
+        rpc.push_success(&Option::<TransactionReceipt>::None);
+    }
+
+    #[tokio::test]
+    async fn default_max_block_number() {
+        let (rpc, relay) = (Asserter::new(), Asserter::new());
+        rpc.push_success(&"0x64");
+        let tracker = send_tracker(&rpc, &relay, None).await;
+        assert_eq!(tracker.tx_hash(), TX_HASH);
+        assert_eq!(tracker.max_block_number(), 100 + DEFAULT_MAX_BLOCK_RANGE);
+        assert!(rpc.read_q().is_empty());
+        assert!(relay.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn included_before_expiry() {
+        let (rpc, relay) = (Asserter::new(), Asserter::new());
+        let tracker = send_tracker(&rpc, &relay, Some(10)).await;
+        push_pending(&rpc, 9);
+        push_pending(&rpc, 10);
+        rpc.push_success(&receipt(11));
+
+        let outcome = tracker.track().await.unwrap();
+        assert!(
+            matches!(outcome, PrivateTxOutcome::Included(receipt) if receipt.block_number == Some(11))
+        );
+        assert!(rpc.read_q().is_empty());
+        assert!(relay.read_q().is_empty());
+    }
+
+    /// 过期前最后一个区块打包了交易, 不需要取消
+    #[tokio::test]
+    async fn included_in_last_block() {
+        let (rpc, relay) = (Asserter::new(), Asserter::new());
+        let tracker = send_tracker(&rpc, &relay, Some(10)).await;
+        push_pending(&rpc, 11);
+        rpc.push_success(&receipt(10));
+
+        let outcome = tracker.track().await.unwrap();
+        assert!(
+            matches!(outcome, PrivateTxOutcome::Included(receipt) if receipt.block_number == Some(10))
+        );
+        assert!(rpc.read_q().is_empty());
+    }
+
+    #[tokio::test]
+    async fn expired_and_cancelled() {
+        let (rpc, relay) = (Asserter::new(), Asserter::new());
+        let tracker = send_tracker(&rpc, &relay, Some(10)).await;
+        push_pending(&rpc, 10);
+        push_expired(&rpc, 11);
+        relay.push_success(&true);
+
+        let outcome = tracker.track().await.unwrap();
+        assert!(matches!(
+            outcome,
+            PrivateTxOutcome::Expired {
+                cancelled: true,
+                cancel_error: None
+            }
+        ));
+        assert!(rpc.read_q().is_empty());
+        assert!(relay.read_q().is_empty());
+
+        // relay 返回 false, 交易可能已经发给了 builder
+        let (rpc, relay) = (Asserter::new(), Asserter::new());
+        let tracker = send_tracker(&rpc, &relay, Some(10)).await;
+        push_expired(&rpc, 12);
+        relay.push_success(&false);
+        let outcome = tracker.track().await.unwrap();
+        assert!(matches!(
+            outcome,
+            PrivateTxOutcome::Expired {
+                cancelled: false,
+                cancel_error: None
+            }
+        ));
+    }
+
+    /// 取消请求失败时仍然返回 Expired, 带上错误信息
+    #[tokio::test]
+    async fn expired_cancel_failed() {
+        let (rpc, relay) = (Asserter::new(), Asserter::new());
+        let tracker = send_tracker(&rpc, &relay, Some(10)).await;
+        push_expired(&rpc, 11);
+        relay.push_failure_msg("private transaction not found");
+
+        let outcome = tracker.track().await.unwrap();
+        let PrivateTxOutcome::Expired {
+            cancelled,
+            cancel_error,
+        } = outcome
+        else {
+            panic!("expected expired outcome, got {outcome:?}");
+        };
+        assert!(!cancelled);
+        assert!(
+            cancel_error
+                .unwrap()
+                .contains("private transaction not found")
+        );
+        assert!(rpc.read_q().is_empty());
+        assert!(relay.read_q().is_empty());
+    }
+}
@@ -0,0 +1,326 @@
+use std::time::Duration;
+
+use alloy::primitives::B256;
+use alloy::providers::RootProvider;
+use alloy::rpc::client::RpcClient;
+use alloy::rpc::types::mev::{EthSendBundle, SendBundleRequest};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::transports::http::Http;
+use alloy::transports::http::reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use alloy::transports::http::reqwest::{self, Url};
+use eyre::{Result, eyre};
+use futures_util::future::join_all;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::eth::FlashbotsEthExt;
+use crate::mev::FlashbotsMevExt;
+use crate::registry::{
+    BuilderAuth, BuilderEndpoint, BuilderRegistry, ETH_SEND_BUNDLE, MEV_SEND_BUNDLE,
+};
+use crate::signature::flashbots_client;
+
+/// 一个 builder 的发送结果
+#[derive(Debug)]
+pub struct FanoutResult {
+    pub builder: String,
+    pub result: Result<B256>,
+}
+
+/// 根据 [`BuilderRegistry`] 把同一个 bundle 发送给一条链上的所有 builder
+///
+/// 只发送给支持对应方法的 builder, 并按每个 builder 的能力去掉不支持的字段,
+/// 每个 builder 单独限速
+pub struct BundleFanout {
+    builders: Vec<BuilderClient>,
+    skipped: Vec<(String, String)>,
+}
+
+struct BuilderClient {
+    endpoint: BuilderEndpoint,
+    provider: RootProvider,
+    limiter: RateLimiter,
+}
+
+impl BundleFanout {
+    /// signer 用于 X-Flashbots-Signature 认证的 builder
+    ///
+    /// 没有设置 API key 环境变量的 builder 会被跳过, 见 [`BundleFanout::skipped`]
+    pub fn new(
+        registry: &BuilderRegistry,
+        chain_id: u64,
+        signer: PrivateKeySigner,
+    ) -> Result<Self> {
+        let mut builders = Vec::new();
+        let mut skipped = Vec::new();
+        for endpoint in registry.builders(chain_id) {
+            if let BuilderAuth::ApiKey { env, .. } = &endpoint.auth
+                && std::env::var(env).is_err()
+            {
+                skipped.push((endpoint.name.clone(), format!("missing api key env {env}")));
+                continue;
+            }
+
+            let client = builder_client(endpoint, &signer)?;
+            let interval = endpoint.rate_limit.and_then(|rate_limit| {
+                (rate_limit.requests_per_second > 0)
+                    .then(|| Duration::from_secs(1) / rate_limit.requests_per_second)
+            });
+            builders.push(BuilderClient {
+                endpoint: endpoint.clone(),
+                provider: RootProvider::new(client),
+                limiter: RateLimiter::new(interval),
+            });
+        }
+
+        if builders.is_empty() {
+            return Err(eyre!(
+                "no usable builder for chain {chain_id}, skipped {skipped:?}"
+            ));
+        }
+        Ok(Self { builders, skipped })
+    }
+
+    /// 没有启用的 builder 和原因, 调用方可以作为警告输出
+    pub fn skipped(&self) -> &[(String, String)] {
+        &self.skipped
+    }
+
+    /// eth_sendBundle 并发发送给所有支持的 builder
+    pub async fn send_bundle(&self, bundle: &EthSendBundle) -> Vec<FanoutResult> {
+        let sends = self
+            .builders
+            .iter()
+            .filter(|builder| builder.endpoint.supports(ETH_SEND_BUNDLE))
+            .map(|builder| async move {
+                let bundle = adapt_eth_bundle(&builder.endpoint, bundle);
+                builder.limiter.acquire().await;
+                let result = builder
+                    .provider
+                    .send_bundle(bundle)
+                    .await
+                    .map(|resp| resp.bundle_hash)
+                    .map_err(Into::into);
+                FanoutResult {
+                    builder: builder.endpoint.name.clone(),
+                    result,
+                }
+            });
+        join_all(sends).await
+    }
+
+    /// mev_sendBundle 并发发送给所有支持的 builder
+    pub async fn send_mev_bundle(&self, bundle: &SendBundleRequest) -> Vec<FanoutResult> {
+        let sends = self
+            .builders
+            .iter()
+            .filter(|builder| builder.endpoint.supports(MEV_SEND_BUNDLE))
+            .map(|builder| async move {
+                let bundle = adapt_mev_bundle(&builder.endpoint, bundle);
+                builder.limiter.acquire().await;
+                let result = builder
+                    .provider
+                    .send_mev_bundle(bundle)
+                    .await
+                    .map(|resp| resp.bundle_hash)
+                    .map_err(Into::into);
+                FanoutResult {
+                    builder: builder.endpoint.name.clone(),
+                    result,
+                }
+            });
+        join_all(sends).await
+    }
+}
+
+/// 去掉 builder 不支持的 eth_sendBundle 字段
+pub fn adapt_eth_bundle(endpoint: &BuilderEndpoint, bundle: &EthSendBundle) -> EthSendBundle {
+    let fields = endpoint.bundle_fields;
+    let mut bundle = bundle.clone();
+    if !fields.min_timestamp {
+        bundle.min_timestamp = None;
+    }
+    if !fields.max_timestamp {
+        bundle.max_timestamp = None;
+    }
+    if !fields.reverting_tx_hashes {
+        bundle.reverting_tx_hashes.clear();
+    }
+    if !fields.replacement_uuid {
+        bundle.replacement_uuid = None;
+    }
+    bundle
+}
+
+/// 去掉 builder 不支持的 mev_sendBundle validity.refund, 保留 refundConfig
+pub fn adapt_mev_bundle(
+    endpoint: &BuilderEndpoint,
+    bundle: &SendBundleRequest,
+) -> SendBundleRequest {
+    let mut bundle = bundle.clone();
+    if !endpoint.refunds
+        && let Some(validity) = &mut bundle.validity
+    {
+        validity.refund = None;
+        if validity.refund_config.is_none() {
+            bundle.validity = None;
+        }
+    }
+    bundle
+}
+
+fn builder_client(endpoint: &BuilderEndpoint, signer: &PrivateKeySigner) -> Result<RpcClient> {
+    let url: Url = endpoint.url.parse()?;
+    let client = match &endpoint.auth {
+        BuilderAuth::None => RpcClient::new_http(url),
+        BuilderAuth::FlashbotsSignature => flashbots_client(signer.clone(), url),
+        BuilderAuth::ApiKey { header, env } => {
+            let api_key = std::env::var(env)
+                .map_err(|_| eyre!("missing api key env {env} for builder {}", endpoint.name))?;
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                HeaderName::from_bytes(header.as_bytes())?,
+                HeaderValue::from_str(&api_key)?,
+            );
+            let http_client = reqwest::Client::builder()
+                .default_headers(headers)
+                .build()?;
+            RpcClient::new(Http::with_client(http_client, url), false)
+        }
+    };
+    Ok(client)
+}
+
+/// 简单的限速器, 保证两次请求之间至少间隔 interval
+struct RateLimiter {
+    interval: Option<Duration>,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(interval: Option<Duration>) -> Self {
+        Self {
+            interval,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn acquire(&self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let mut next = self.next.lock().await;
+        let now = Instant::now();
+        if *next > now {
+            tokio::time::sleep_until(*next).await;
+        }
+        *next = (*next).max(now) + interval;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::Address;
+    use alloy::rpc::types::mev::{Refund, RefundConfig, Validity};
+
+    use super::*;
+
+    fn endpoint(refunds: bool) -> BuilderEndpoint {
+        BuilderEndpoint {
+            name: "builder".to_string(),
+            url: "https://builder.example".to_string(),
+            methods: vec![MEV_SEND_BUNDLE.to_string()],
+            bundle_fields: Default::default(),
+            refunds,
+            auth: BuilderAuth::None,
+            rate_limit: None,
+        }
+    }
+
+    #[test]
+    fn mev_bundle_refunds() {
+        let refund = vec![Refund {
+            body_idx: 0,
+            percent: 90,
+        }];
+        let refund_config = vec![RefundConfig {
+            address: Address::with_last_byte(0xaa),
+            percent: 100,
+        }];
+        let bundle = SendBundleRequest {
+            validity: Some(Validity {
+                refund: Some(refund),
+                refund_config: Some(refund_config.clone()),
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(adapt_mev_bundle(&endpoint(true), &bundle), bundle);
+        // 只去掉 refund, 保留 refundConfig
+        let adapted = adapt_mev_bundle(&endpoint(false), &bundle);
+        assert_eq!(
+            adapted.validity,
+            Some(Validity {
+                refund: None,
+                refund_config: Some(refund_config),
+            })
+        );
+        assert_eq!(adapted.inclusion, bundle.inclusion);
+
+        // 去掉 refund 之后 validity 为空
+        let only_refund = SendBundleRequest {
+            validity: Some(Validity {
+                refund: bundle.validity.as_ref().unwrap().refund.clone(),
+                refund_config: None,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            adapt_mev_bundle(&endpoint(false), &only_refund).validity,
+            None
+        );
+    }
+
+    #[test]
+    fn skip_builders_without_api_key() {
+        let registry = BuilderRegistry::from_json(
+            r#"{
+                "chains": {
+                    "1": [
+                        { "name": "open", "url": "https://open.example", "methods": ["eth_sendBundle"] },
+                        {
+                            "name": "keyed",
+                            "url": "https://keyed.example",
+                            "methods": ["eth_sendBundle"],
+                            "auth": { "type": "apiKey", "header": "X-Api-Key", "env": "FANOUT_TEST_MISSING_API_KEY" }
+                        }
+                    ],
+                    "2": [
+                        {
+                            "name": "keyed",
+                            "url": "https://keyed.example",
+                            "methods": ["eth_sendBundle"],
+                            "auth": { "type": "apiKey", "header": "X-Api-Key", "env": "FANOUT_TEST_MISSING_API_KEY" }
+                        }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let signer = PrivateKeySigner::random();
+
+        let fanout = BundleFanout::new(&registry, 1, signer.clone()).unwrap();
+        assert_eq!(fanout.builders.len(), 1);
+        assert_eq!(fanout.builders[0].endpoint.name, "open");
+        assert_eq!(
+            fanout.skipped(),
+            [(
+                "keyed".to_string(),
+                "missing api key env FANOUT_TEST_MISSING_API_KEY".to_string()
+            )]
+        );
+
+        // 所有 builder 都被跳过时报错
+        assert!(BundleFanout::new(&registry, 2, signer).is_err());
+    }
+}
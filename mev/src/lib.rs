@@ -1,2 +1,7 @@
 pub mod eth;
+pub mod fanout;
 pub mod mev;
+pub mod optimizer;
+pub mod protect;
+pub mod registry;
+pub mod signature;
@@ -1 +1,17 @@
+use alloy::providers::Provider;
+use alloy::rpc::client::RpcCall;
+use alloy::rpc::types::mev::{SendBundleRequest, SendBundleResponse};
 
+/// 为 Provider 扩展 mev_ 命名空间方法(MEV-Share)
+pub trait FlashbotsMevExt: Provider {
+    /// mev_sendBundle
+    fn send_mev_bundle(
+        &self,
+        bundle: SendBundleRequest,
+    ) -> RpcCall<(SendBundleRequest,), SendBundleResponse> {
+        self.client().request("mev_sendBundle", (bundle,))
+    }
+}
+
+// 为所有 Provider 实现扩展 trait
+impl<P: Provider> FlashbotsMevExt for P {}
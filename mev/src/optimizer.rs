@@ -0,0 +1,420 @@
+use std::collections::{HashMap, HashSet};
+
+use alloy::consensus::{Transaction, TxEnvelope};
+use alloy::eips::Decodable2718;
+use alloy::primitives::{Address, Bytes, I256, TxHash, U256, address, keccak256};
+use alloy::providers::Provider;
+use alloy::providers::ext::AnvilApi;
+use alloy::rpc::json_rpc::RpcError;
+use alloy::rpc::types::mev::EthSendBundle;
+use eyre::{Result, eyre};
+
+/// 模拟时使用的 coinbase, 用一个没有余额变化的地址方便计算 coinbase delta
+const SIMULATION_COINBASE: Address = address!("0x00000000000000000000000000000000c014ba5e");
+
+/// 默认最多尝试的排列数量
+pub const DEFAULT_MAX_PERMUTATIONS: usize = 120;
+
+/// 选择最优顺序的目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    /// builder 收到的 coinbase 余额变化最大
+    CoinbaseDelta,
+    /// 指定地址的余额变化(扣掉 gas 之后的利润)最大
+    Profit(Address),
+}
+
+/// 一种交易顺序的模拟结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedOrder {
+    /// 原始 bundle 中交易的下标, 按执行顺序排列
+    pub order: Vec<usize>,
+    pub coinbase_delta: U256,
+    /// 只有 [`Objective::Profit`] 时才会计算
+    pub profit: Option<I256>,
+    pub gas_used: u64,
+}
+
+/// bundle 内交易顺序优化器
+///
+/// 在满足 nonce 和依赖约束的前提下枚举交易顺序, 在本地 anvil(一般是 fork 出来的)上逐个模拟,
+/// 按 [`Objective`] 选出最优顺序.
+///
+/// 注意: anvil 默认按 gas price 排序打包, provider 连接的 anvil 需要使用 `--order fifo` 启动,
+/// 否则模拟的顺序和发送的顺序不一致
+pub struct BundleOptimizer<P> {
+    provider: P,
+    objective: Objective,
+    max_permutations: usize,
+    dependencies: Vec<(usize, usize)>,
+}
+
+impl<P: Provider> BundleOptimizer<P> {
+    pub fn new(provider: P, objective: Objective) -> Self {
+        Self {
+            provider,
+            objective,
+            max_permutations: DEFAULT_MAX_PERMUTATIONS,
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// 设置最多尝试的排列数量
+    pub fn max_permutations(mut self, max_permutations: usize) -> Self {
+        self.max_permutations = max_permutations;
+        self
+    }
+
+    /// 下标为 before 的交易必须在下标为 after 的交易之前执行
+    /// 例如 backrun 的交易必须在目标交易之后
+    pub fn dependency(mut self, before: usize, after: usize) -> Self {
+        self.dependencies.push((before, after));
+        self
+    }
+
+    /// 模拟所有满足约束的顺序, 返回按最优顺序重新排列的 bundle 和它的模拟结果
+    pub async fn optimize(
+        &self,
+        bundle: &EthSendBundle,
+    ) -> Result<(EthSendBundle, SimulatedOrder)> {
+        let senders = senders(&bundle.txs)?;
+        self.check_nonce_gaps(&senders).await?;
+        let constraints = self.constraints(&senders)?;
+        let orderings = valid_orderings(bundle.txs.len(), &constraints, self.max_permutations);
+        if orderings.is_empty() {
+            return Err(eyre!("no ordering satisfies the bundle constraints"));
+        }
+
+        // 模拟结束后, 无论是否出错都要恢复 auto-mine 和 coinbase
+        let auto_mine = self.provider.anvil_get_auto_mine().await?;
+        let coinbase: Address = self
+            .provider
+            .client()
+            .request_noparams("eth_coinbase")
+            .await?;
+        let result = self.simulate_all(bundle, orderings).await;
+        let restored = self.restore(auto_mine, coinbase).await;
+        let best = result?;
+        restored?;
+
+        let best = best.ok_or_else(|| eyre!("every ordering failed in simulation"))?;
+        let mut optimized = bundle.clone();
+        optimized.txs = best.order.iter().map(|i| bundle.txs[*i].clone()).collect();
+        Ok((optimized, best))
+    }
+
+    /// 关闭 auto-mine, 切换到模拟用的 coinbase, 逐个模拟 orderings, 返回最优的顺序
+    async fn simulate_all(
+        &self,
+        bundle: &EthSendBundle,
+        orderings: Vec<Vec<usize>>,
+    ) -> Result<Option<SimulatedOrder>> {
+        self.provider.anvil_set_auto_mine(false).await?;
+        self.provider
+            .anvil_set_coinbase(SIMULATION_COINBASE)
+            .await?;
+
+        let mut best: Option<SimulatedOrder> = None;
+        for order in orderings {
+            // 返回 None 时该顺序下有交易失败, 跳过
+            if let Some(simulated) = self.simulate(bundle, order).await?
+                && best
+                    .as_ref()
+                    .is_none_or(|best| self.is_better(&simulated, best))
+            {
+                best = Some(simulated);
+            }
+        }
+        Ok(best)
+    }
+
+    /// 恢复模拟之前的 auto-mine 和 coinbase, 两个都会尝试, 返回第一个错误
+    async fn restore(&self, auto_mine: bool, coinbase: Address) -> Result<()> {
+        let auto_mine = self.provider.anvil_set_auto_mine(auto_mine).await;
+        let coinbase = self.provider.anvil_set_coinbase(coinbase).await;
+        auto_mine?;
+        coinbase?;
+        Ok(())
+    }
+
+    fn is_better(&self, candidate: &SimulatedOrder, best: &SimulatedOrder) -> bool {
+        match self.objective {
+            Objective::CoinbaseDelta => candidate.coinbase_delta > best.coinbase_delta,
+            Objective::Profit(_) => candidate.profit > best.profit,
+        }
+    }
+
+    /// nonce 不连续的交易会被 anvil 放进 queued, 出块时不会打包, 任何顺序的模拟都没有意义
+    async fn check_nonce_gaps(&self, senders: &[(Address, u64)]) -> Result<()> {
+        let mut next_nonces = HashMap::new();
+        for (sender, _) in senders {
+            if !next_nonces.contains_key(sender) {
+                let nonce = self.provider.get_transaction_count(*sender).await?;
+                next_nonces.insert(*sender, nonce);
+            }
+        }
+        if let Some((i, expected)) = nonce_gap(senders, &next_nonces) {
+            let (sender, nonce) = senders[i];
+            return Err(eyre!(
+                "tx {i} from {sender} has nonce {nonce} but the next nonce is {expected}, \
+                 anvil would queue it instead of mining it"
+            ));
+        }
+        Ok(())
+    }
+
+    /// 同一个 sender 的交易按 nonce 排序, 再加上用户指定的依赖
+    fn constraints(&self, senders: &[(Address, u64)]) -> Result<Vec<(usize, usize)>> {
+        let mut constraints = self.dependencies.clone();
+        for (i, (sender_i, nonce_i)) in senders.iter().enumerate() {
+            for (j, (sender_j, nonce_j)) in senders.iter().enumerate() {
+                if sender_i == sender_j && nonce_i < nonce_j {
+                    constraints.push((i, j));
+                }
+            }
+        }
+
+        if let Some((before, after)) = constraints
+            .iter()
+            .find(|(before, after)| *before >= senders.len() || *after >= senders.len())
+        {
+            return Err(eyre!("dependency ({before}, {after}) out of bundle range"));
+        }
+        Ok(constraints)
+    }
+
+    /// 在 snapshot 上按 order 发送交易并出一个块, 结束后(包括出错时) revert
+    /// 有交易被拒绝或者执行失败(且不在 reverting_tx_hashes 中)时返回 None, 被接受但没有打包时报错
+    async fn simulate(
+        &self,
+        bundle: &EthSendBundle,
+        order: Vec<usize>,
+    ) -> Result<Option<SimulatedOrder>> {
+        let snapshot = self.provider.anvil_snapshot().await?;
+        let result = self.execute(bundle, order).await;
+        let reverted = self.provider.anvil_revert(snapshot).await;
+        let simulated = result?;
+        if !reverted? {
+            return Err(eyre!("failed to revert simulation snapshot {snapshot}"));
+        }
+        Ok(simulated)
+    }
+
+    /// 按 order 发送交易并出一个块, 计算余额变化
+    async fn execute(
+        &self,
+        bundle: &EthSendBundle,
+        order: Vec<usize>,
+    ) -> Result<Option<SimulatedOrder>> {
+        let coinbase_before = self.provider.get_balance(SIMULATION_COINBASE).await?;
+        let profit_before = match self.objective {
+            Objective::Profit(account) => Some(self.provider.get_balance(account).await?),
+            Objective::CoinbaseDelta => None,
+        };
+
+        let mut tx_hashes: Vec<TxHash> = Vec::with_capacity(order.len());
+        let mut valid = true;
+        for i in &order {
+            let raw_tx = &bundle.txs[*i];
+            match self.provider.send_raw_transaction(raw_tx).await {
+                Ok(_) => tx_hashes.push(keccak256(raw_tx)),
+                // 节点拒绝了交易(余额不足, nonce 错误等), 该顺序不可行
+                Err(RpcError::ErrorResp(_)) => {
+                    valid = false;
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.provider.evm_mine(None).await?;
+
+        let reverting: HashSet<&TxHash> = bundle.reverting_tx_hashes.iter().collect();
+        let mut gas_used = 0;
+        if valid {
+            for tx_hash in &tx_hashes {
+                match self.provider.get_transaction_receipt(*tx_hash).await? {
+                    Some(receipt) if receipt.status() || reverting.contains(tx_hash) => {
+                        gas_used += receipt.gas_used;
+                    }
+                    Some(_) => {
+                        valid = false;
+                        break;
+                    }
+                    // 节点接受了交易但没有打包, 一般是 nonce 不连续被放进了 queued
+                    None => {
+                        return Err(eyre!(
+                            "tx {tx_hash} was accepted but not mined, it may be queued behind a nonce gap"
+                        ));
+                    }
+                }
+            }
+        }
+
+        let simulated = if valid {
+            let coinbase_after = self.provider.get_balance(SIMULATION_COINBASE).await?;
+            let profit = match (self.objective, profit_before) {
+                (Objective::Profit(account), Some(before)) => {
+                    let after = self.provider.get_balance(account).await?;
+                    Some(I256::from_raw(after).wrapping_sub(I256::from_raw(before)))
+                }
+                _ => None,
+            };
+            Some(SimulatedOrder {
+                order,
+                coinbase_delta: coinbase_after.saturating_sub(coinbase_before),
+                profit,
+                gas_used,
+            })
+        } else {
+            None
+        };
+
+        Ok(simulated)
+    }
+}
+
+/// 解码 bundle 中每笔交易的 sender 和 nonce
+fn senders(txs: &[Bytes]) -> Result<Vec<(Address, u64)>> {
+    let mut senders = Vec::with_capacity(txs.len());
+    for raw_tx in txs {
+        let envelope = TxEnvelope::decode_2718(&mut raw_tx.as_ref())?;
+        senders.push((envelope.recover_signer()?, envelope.nonce()));
+    }
+    Ok(senders)
+}
+
+/// 每个 sender 的 nonce 从账户当前的 nonce 开始, 找到第一个不连续的交易和它应该使用的 nonce
+///
+/// nonce 太低的交易会被节点直接拒绝, 按该顺序不可行处理, 这里不报告
+fn nonce_gap(
+    senders: &[(Address, u64)],
+    next_nonces: &HashMap<Address, u64>,
+) -> Option<(usize, u64)> {
+    let mut sorted: Vec<_> = senders.iter().enumerate().collect();
+    sorted.sort_by_key(|(_, (sender, nonce))| (*sender, *nonce));
+    let mut expected = next_nonces.clone();
+    for (i, (sender, nonce)) in sorted {
+        let next = expected.get_mut(sender)?;
+        if *nonce > *next {
+            return Some((i, *next));
+        }
+        *next = (*next).max(nonce + 1);
+    }
+    None
+}
+
+/// 枚举满足 (before, after) 约束的所有拓扑序, 最多返回 max 个
+/// 第一个总是尽量保持原始顺序
+pub fn valid_orderings(n: usize, constraints: &[(usize, usize)], max: usize) -> Vec<Vec<usize>> {
+    fn walk(
+        n: usize,
+        constraints: &[(usize, usize)],
+        max: usize,
+        current: &mut Vec<usize>,
+        used: &mut Vec<bool>,
+        out: &mut Vec<Vec<usize>>,
+    ) {
+        if out.len() >= max {
+            return;
+        }
+        if current.len() == n {
+            out.push(current.clone());
+            return;
+        }
+        for i in 0..n {
+            // i 的所有前置交易都已经排好才能放 i
+            let ready = !used[i]
+                && constraints
+                    .iter()
+                    .all(|(before, after)| *after != i || used[*before]);
+            if ready {
+                used[i] = true;
+                current.push(i);
+                walk(n, constraints, max, current, used, out);
+                current.pop();
+                used[i] = false;
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(
+        n,
+        constraints,
+        max,
+        &mut Vec::with_capacity(n),
+        &mut vec![false; n],
+        &mut out,
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orderings_without_constraints() {
+        let orderings = valid_orderings(4, &[], DEFAULT_MAX_PERMUTATIONS);
+        assert_eq!(orderings.len(), 24);
+        assert_eq!(orderings[0], vec![0, 1, 2, 3]);
+        let unique: HashSet<_> = orderings.iter().collect();
+        assert_eq!(unique.len(), 24);
+
+        // 超过 max 时只返回前 max 个
+        let orderings = valid_orderings(5, &[], 10);
+        assert_eq!(orderings.len(), 10);
+        assert_eq!(orderings[0], vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn chained_constraints() {
+        assert_eq!(
+            valid_orderings(4, &[(0, 1), (1, 2), (2, 3)], DEFAULT_MAX_PERMUTATIONS),
+            vec![vec![0, 1, 2, 3]]
+        );
+        assert_eq!(
+            valid_orderings(4, &[(2, 1), (3, 2), (1, 0)], DEFAULT_MAX_PERMUTATIONS),
+            vec![vec![3, 2, 1, 0]]
+        );
+
+        // 只约束一对交易时, 一半的顺序满足
+        let orderings = valid_orderings(3, &[(2, 0)], DEFAULT_MAX_PERMUTATIONS);
+        assert_eq!(orderings.len(), 3);
+        assert!(orderings.iter().all(|order| {
+            order.iter().position(|i| *i == 2) < order.iter().position(|i| *i == 0)
+        }));
+    }
+
+    #[test]
+    fn cyclic_constraints() {
+        assert!(valid_orderings(2, &[(0, 1), (1, 0)], DEFAULT_MAX_PERMUTATIONS).is_empty());
+        assert!(valid_orderings(4, &[(0, 1), (1, 2), (2, 0)], DEFAULT_MAX_PERMUTATIONS).is_empty());
+    }
+
+    #[test]
+    fn nonce_gaps() {
+        let alice = Address::with_last_byte(0xaa);
+        let bob = Address::with_last_byte(0xbb);
+        let next_nonces = HashMap::from([(alice, 5), (bob, 0)]);
+
+        // 每个 sender 连续, bundle 中的顺序不影响
+        assert_eq!(
+            nonce_gap(&[(alice, 6), (bob, 0), (alice, 5)], &next_nonces),
+            None
+        );
+        // nonce 太低的交易由节点拒绝
+        assert_eq!(nonce_gap(&[(alice, 4), (alice, 5)], &next_nonces), None);
+
+        // 第一笔交易就跳过了 nonce
+        assert_eq!(
+            nonce_gap(&[(bob, 0), (alice, 6)], &next_nonces),
+            Some((1, 5))
+        );
+        // bundle 中间缺少一个 nonce
+        assert_eq!(
+            nonce_gap(&[(alice, 5), (alice, 7), (bob, 0)], &next_nonces),
+            Some((1, 6))
+        );
+    }
+}
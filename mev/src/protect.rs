@@ -0,0 +1,419 @@
+use std::task::{Context, Poll};
+
+use alloy::network::EthereumWallet;
+use alloy::providers::fillers::{BlobGasFiller, ChainIdFiller, GasFiller};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::client::RpcClient;
+use alloy::rpc::json_rpc::{Id, RequestPacket, ResponsePacket};
+use alloy::rpc::types::mev::PrivacyHint;
+use alloy::transports::http::Http;
+use alloy::transports::http::reqwest::Url;
+use alloy::transports::{BoxTransport, TransportError, TransportFut};
+use tower::Service;
+
+/// Flashbots Protect 主网地址
+pub const FLASHBOTS_PROTECT_URL: &str = "https://rpc.flashbots.net";
+
+/// 需要发送到 Protect 的方法, 其余请求全部发送到普通 rpc
+const PROTECT_METHODS: &[&str] = &["eth_sendRawTransaction"];
+
+/// Protect 的 hint 和 builder 偏好, 编码到 url query 参数中
+///
+/// 参考: https://docs.flashbots.net/flashbots-protect/settings-guide
+#[derive(Debug, Clone, Default)]
+pub struct ProtectPreferences {
+    hints: Option<PrivacyHint>,
+    builders: Vec<String>,
+    fast: bool,
+}
+
+impl ProtectPreferences {
+    /// 设置分享给 searcher 的交易数据, 不设置时使用 Protect 默认的 hints
+    pub fn with_hints(mut self, hints: PrivacyHint) -> Self {
+        self.hints = Some(hints);
+        self
+    }
+
+    /// 添加允许打包交易的 builder
+    pub fn with_builder(mut self, builder: impl Into<String>) -> Self {
+        self.builders.push(builder.into());
+        self
+    }
+
+    /// fast mode, 交易分享给所有注册的 builders
+    pub fn into_fast(mut self) -> Self {
+        self.fast = true;
+        self
+    }
+
+    /// 把偏好编码到 Protect url 上, 例如:
+    /// https://rpc.flashbots.net/fast?hint=calldata&hint=logs&builder=flashbots
+    pub fn endpoint(&self, mut protect_url: Url) -> Url {
+        if self.fast {
+            protect_url
+                .path_segments_mut()
+                .expect("protect url can not be a base")
+                .pop_if_empty()
+                .push("fast");
+        }
+
+        {
+            let mut query = protect_url.query_pairs_mut();
+            if let Some(hints) = &self.hints {
+                for (enabled, hint) in [
+                    (hints.calldata, "calldata"),
+                    (hints.contract_address, "contract_address"),
+                    (hints.function_selector, "function_selector"),
+                    (hints.logs, "logs"),
+                    (hints.hash || hints.tx_hash, "hash"),
+                ] {
+                    if enabled {
+                        query.append_pair("hint", hint);
+                    }
+                }
+            }
+            for builder in &self.builders {
+                query.append_pair("builder", builder);
+            }
+        }
+
+        // 没有任何 query 参数时去掉多余的 '?'
+        if protect_url.query() == Some("") {
+            protect_url.set_query(None);
+        }
+        protect_url
+    }
+}
+
+/// 按方法名分发请求的 transport
+///
+/// eth_sendRawTransaction 发送到 Protect, 其余读请求发送到普通 rpc,
+/// batch 请求会拆成两个 batch 分别发送, 再按 id 合并回去
+#[derive(Debug, Clone)]
+pub struct ProtectTransport {
+    rpc: BoxTransport,
+    protect: BoxTransport,
+}
+
+impl ProtectTransport {
+    pub fn new(rpc_url: Url, protect_url: Url) -> Self {
+        Self {
+            rpc: BoxTransport::new(Http::new(rpc_url)),
+            protect: BoxTransport::new(Http::new(protect_url)),
+        }
+    }
+}
+
+fn is_protect_method(method: &str) -> bool {
+    PROTECT_METHODS.contains(&method)
+}
+
+fn into_responses(packet: ResponsePacket) -> Vec<alloy::rpc::json_rpc::Response> {
+    match packet {
+        ResponsePacket::Single(response) => vec![response],
+        ResponsePacket::Batch(responses) => responses,
+    }
+}
+
+impl Service<RequestPacket> for ProtectTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // http transport 总是 ready
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let mut rpc = self.rpc.clone();
+        let mut protect = self.protect.clone();
+
+        Box::pin(async move {
+            match req {
+                RequestPacket::Single(request) => {
+                    if is_protect_method(request.method()) {
+                        protect.call(request.into()).await
+                    } else {
+                        rpc.call(request.into()).await
+                    }
+                }
+                RequestPacket::Batch(requests) => {
+                    let ids: Vec<Id> = requests
+                        .iter()
+                        .map(|request| request.id().clone())
+                        .collect();
+                    let (private, public): (Vec<_>, Vec<_>) = requests
+                        .into_iter()
+                        .partition(|request| is_protect_method(request.method()));
+
+                    if private.is_empty() {
+                        return rpc.call(RequestPacket::Batch(public)).await;
+                    }
+                    if public.is_empty() {
+                        return protect.call(RequestPacket::Batch(private)).await;
+                    }
+
+                    let (private, public) = tokio::try_join!(
+                        protect.call(RequestPacket::Batch(private)),
+                        rpc.call(RequestPacket::Batch(public))
+                    )?;
+                    // 节点返回的 batch 不一定保持请求顺序, 按原始请求的 id 排序
+                    let mut responses = into_responses(private);
+                    responses.extend(into_responses(public));
+                    responses.sort_by_key(|response| {
+                        ids.iter()
+                            .position(|id| *id == response.id)
+                            .unwrap_or(usize::MAX)
+                    });
+                    Ok(ResponsePacket::Batch(responses))
+                }
+            }
+        })
+    }
+}
+
+/// 创建读请求走 rpc_url, 发送交易走 Protect 的 rpc client
+///
+/// 可以直接传给 `ProviderBuilder::on_client`, 现有的 wallet 发送交易流程不需要改动
+pub fn protect_client(
+    rpc_url: Url,
+    protect_url: Url,
+    preferences: &ProtectPreferences,
+) -> RpcClient {
+    let transport = ProtectTransport::new(rpc_url, preferences.endpoint(protect_url));
+    RpcClient::new(transport, false)
+}
+
+/// 使用和 recommended fillers 相同的 fillers 和 wallet 创建 Protect provider
+///
+/// 注意: 交易在上链前只有 Protect 知道, 普通 rpc 查询的 pending nonce 不包含这些交易,
+/// 连续发送多笔交易时使用缓存的 nonce 管理, 避免 nonce 重复
+pub fn protect_provider(
+    wallet: EthereumWallet,
+    rpc_url: Url,
+    protect_url: Url,
+    preferences: &ProtectPreferences,
+) -> impl Provider + Clone {
+    ProviderBuilder::default()
+        .with_cached_nonce_management()
+        .filler(GasFiller)
+        .filler(BlobGasFiller)
+        .filler(ChainIdFiller::default())
+        .wallet(wallet)
+        .on_client(protect_client(rpc_url, protect_url, preferences))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use alloy::rpc::json_rpc::{Request, Response, ResponsePayload};
+    use serde_json::value::RawValue;
+
+    use super::*;
+
+    /// 记录收到的请求, 返回 "<name>:<method>"
+    #[derive(Clone)]
+    struct MockTransport {
+        name: &'static str,
+        received: Arc<Mutex<Vec<(String, Id)>>>,
+    }
+
+    impl MockTransport {
+        fn new(name: &'static str) -> Self {
+            Self {
+                name,
+                received: Arc::default(),
+            }
+        }
+
+        fn received(&self) -> Vec<(String, Id)> {
+            self.received.lock().unwrap().clone()
+        }
+    }
+
+    impl Service<RequestPacket> for MockTransport {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: RequestPacket) -> Self::Future {
+            let mut responses = Vec::new();
+            let requests = match &req {
+                RequestPacket::Single(request) => std::slice::from_ref(request),
+                RequestPacket::Batch(requests) => requests.as_slice(),
+            };
+            for request in requests {
+                let id = request.id().clone();
+                self.received
+                    .lock()
+                    .unwrap()
+                    .push((request.method().to_string(), id.clone()));
+                let result =
+                    serde_json::to_string(&format!("{}:{}", self.name, request.method())).unwrap();
+                responses.push(Response {
+                    id,
+                    payload: ResponsePayload::Success(RawValue::from_string(result).unwrap()),
+                });
+            }
+            // 倒序返回 batch, 检查合并时恢复请求顺序
+            let packet = match req {
+                RequestPacket::Single(_) => ResponsePacket::Single(responses.remove(0)),
+                RequestPacket::Batch(_) => {
+                    responses.reverse();
+                    ResponsePacket::Batch(responses)
+                }
+            };
+            Box::pin(async move { Ok(packet) })
+        }
+    }
+
+    fn request(method: &'static str, id: u64) -> RequestPacket {
+        Request::new(method, Id::Number(id), ())
+            .serialize()
+            .unwrap()
+            .into()
+    }
+
+    fn results(packet: ResponsePacket) -> Vec<(Id, String)> {
+        into_responses(packet)
+            .into_iter()
+            .map(|response| {
+                let ResponsePayload::Success(result) = response.payload else {
+                    panic!("unexpected error response");
+                };
+                (response.id, serde_json::from_str(result.get()).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn endpoint_query() {
+        let url = || Url::parse(FLASHBOTS_PROTECT_URL).unwrap();
+        assert_eq!(
+            ProtectPreferences::default().endpoint(url()).as_str(),
+            "https://rpc.flashbots.net/"
+        );
+        assert_eq!(
+            ProtectPreferences::default()
+                .into_fast()
+                .endpoint(url())
+                .as_str(),
+            "https://rpc.flashbots.net/fast"
+        );
+
+        let hints = PrivacyHint::default()
+            .with_calldata()
+            .with_contract_address()
+            .with_function_selector()
+            .with_logs()
+            .with_tx_hash();
+        let preferences = ProtectPreferences::default()
+            .with_hints(hints)
+            .with_builder("flashbots")
+            .with_builder("beaverbuild.org")
+            .into_fast();
+        assert_eq!(
+            preferences.endpoint(url()).as_str(),
+            "https://rpc.flashbots.net/fast?hint=calldata&hint=contract_address\
+             &hint=function_selector&hint=logs&hint=hash&builder=flashbots&builder=beaverbuild.org"
+        );
+
+        // 没有开启任何 hint 时不分享数据, 也不带 query 参数
+        let preferences = ProtectPreferences::default().with_hints(PrivacyHint::default());
+        assert_eq!(
+            preferences.endpoint(url()).as_str(),
+            "https://rpc.flashbots.net/"
+        );
+        let preferences = ProtectPreferences::default()
+            .with_hints(PrivacyHint::default().with_hash())
+            .with_builder("titan");
+        assert_eq!(
+            preferences.endpoint(url()).as_str(),
+            "https://rpc.flashbots.net/?hint=hash&builder=titan"
+        );
+    }
+
+    #[tokio::test]
+    async fn routes_by_method() {
+        let rpc = MockTransport::new("rpc");
+        let protect = MockTransport::new("protect");
+        let mut transport = ProtectTransport {
+            rpc: BoxTransport::new(rpc.clone()),
+            protect: BoxTransport::new(protect.clone()),
+        };
+
+        let single = transport
+            .call(request("eth_sendRawTransaction", 1))
+            .await
+            .unwrap();
+        assert_eq!(
+            results(single),
+            vec![(Id::Number(1), "protect:eth_sendRawTransaction".to_string())]
+        );
+        let single = transport.call(request("eth_call", 2)).await.unwrap();
+        assert_eq!(
+            results(single),
+            vec![(Id::Number(2), "rpc:eth_call".to_string())]
+        );
+
+        // 混合的 batch 拆成两个, 响应按请求的顺序和 id 合并
+        let methods = [
+            "eth_blockNumber",
+            "eth_sendRawTransaction",
+            "eth_getTransactionCount",
+            "eth_sendRawTransaction",
+            "eth_chainId",
+        ];
+        let batch = RequestPacket::Batch(
+            methods
+                .iter()
+                .enumerate()
+                .map(|(i, method)| {
+                    Request::new(*method, Id::Number(10 + i as u64), ())
+                        .serialize()
+                        .unwrap()
+                })
+                .collect(),
+        );
+        let responses = transport.call(batch).await.unwrap();
+        assert_eq!(
+            results(responses),
+            methods
+                .iter()
+                .enumerate()
+                .map(|(i, method)| {
+                    let name = if is_protect_method(method) {
+                        "protect"
+                    } else {
+                        "rpc"
+                    };
+                    (Id::Number(10 + i as u64), format!("{name}:{method}"))
+                })
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            protect.received(),
+            vec![
+                ("eth_sendRawTransaction".to_string(), Id::Number(1)),
+                ("eth_sendRawTransaction".to_string(), Id::Number(11)),
+                ("eth_sendRawTransaction".to_string(), Id::Number(13)),
+            ]
+        );
+        assert_eq!(
+            rpc.received(),
+            vec![
+                ("eth_call".to_string(), Id::Number(2)),
+                ("eth_blockNumber".to_string(), Id::Number(10)),
+                ("eth_getTransactionCount".to_string(), Id::Number(12)),
+                ("eth_chainId".to_string(), Id::Number(14)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+
+/// 方法名, 对应 [`BuilderEndpoint::methods`]
+pub const ETH_SEND_BUNDLE: &str = "eth_sendBundle";
+pub const MEV_SEND_BUNDLE: &str = "mev_sendBundle";
+pub const ETH_SEND_PRIVATE_TRANSACTION: &str = "eth_sendPrivateTransaction";
+
+/// 每条链的 builder endpoint 列表, 从配置文件加载
+///
+/// 配置文件格式见 `src/config/builders.json`, key 为 chain id
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuilderRegistry {
+    chains: BTreeMap<u64, Vec<BuilderEndpoint>>,
+}
+
+impl BuilderRegistry {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| eyre!("read builder registry {} failed, {e}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    /// 某条链上配置的所有 builder
+    pub fn builders(&self, chain_id: u64) -> &[BuilderEndpoint] {
+        self.chains.get(&chain_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// 某条链上支持指定方法的 builder
+    pub fn supporting<'a>(
+        &'a self,
+        chain_id: u64,
+        method: &'a str,
+    ) -> impl Iterator<Item = &'a BuilderEndpoint> + 'a {
+        self.builders(chain_id)
+            .iter()
+            .filter(move |builder| builder.supports(method))
+    }
+}
+
+/// 一个 builder 的 rpc endpoint 和它支持的能力
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderEndpoint {
+    pub name: String,
+    pub url: String,
+    /// 支持的 rpc 方法, 例如 eth_sendBundle, mev_sendBundle
+    pub methods: Vec<String>,
+    /// eth_sendBundle 支持的可选字段
+    #[serde(default)]
+    pub bundle_fields: BundleFields,
+    /// mev_sendBundle 是否支持 validity 中的 refund, 不支持时只保留 refundConfig
+    #[serde(default)]
+    pub refunds: bool,
+    #[serde(default)]
+    pub auth: BuilderAuth,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl BuilderEndpoint {
+    pub fn supports(&self, method: &str) -> bool {
+        self.methods.iter().any(|m| m == method)
+    }
+}
+
+/// eth_sendBundle 中除了 txs 和 blockNumber 之外的可选字段, 不支持的字段发送前会被去掉
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleFields {
+    #[serde(default)]
+    pub min_timestamp: bool,
+    #[serde(default)]
+    pub max_timestamp: bool,
+    #[serde(default)]
+    pub reverting_tx_hashes: bool,
+    #[serde(default)]
+    pub replacement_uuid: bool,
+}
+
+/// builder 的认证方式
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BuilderAuth {
+    #[default]
+    None,
+    /// X-Flashbots-Signature header, 使用 searcher 的签名 key
+    FlashbotsSignature,
+    /// 把环境变量 env 中的 api key 放到 header 中
+    ApiKey { header: String, env: String },
+}
+
+/// 每秒最多发送的请求数量
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimit {
+    pub requests_per_second: u32,
+}
@@ -0,0 +1,140 @@
+use alloy::hex;
+use alloy::primitives::{eip191_hash_message, keccak256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::client::RpcClient;
+use alloy::signers::Signer;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::transports::http::reqwest::Url;
+use alloy::transports::http::reqwest::header::HeaderValue;
+use alloy::transports::http::{
+    Http, HyperClient, HyperResponse, HyperResponseFut, hyper,
+    hyper_util::{
+        client::legacy::{Client, Error},
+        rt::TokioExecutor,
+    },
+};
+use http_body_util::{BodyExt, Full};
+use hyper_tls::HttpsConnector;
+use tower::{Layer, Service};
+
+/// 创建一个请求会带上 X-Flashbots-Signature header 的 provider, 用来访问 flashbots relay
+///
+/// 注意: signature service 内部使用了 `block_in_place`, 需要在 multi-thread runtime 中使用
+pub fn flashbots_provider(signer: PrivateKeySigner, relay_url: Url) -> impl Provider + Clone {
+    ProviderBuilder::new().on_client(flashbots_client(signer, relay_url))
+}
+
+/// 创建带 X-Flashbots-Signature header 的 rpc client
+pub fn flashbots_client(signer: PrivateKeySigner, relay_url: Url) -> RpcClient {
+    // support tls
+    let https = HttpsConnector::new();
+    let hyper_client =
+        Client::builder(TokioExecutor::new()).build::<_, Full<hyper::body::Bytes>>(https);
+
+    // Use tower::ServiceBuilder to stack layers on top of the Hyper client.
+    let service = tower::ServiceBuilder::new()
+        .layer(FlashbotsSignatureLayer::new(signer))
+        .service(hyper_client);
+
+    let layer_transport = HyperClient::<Full<hyper::body::Bytes>, _>::with_service(service);
+    let http = Http::with_client(layer_transport, relay_url);
+
+    RpcClient::new(http, false)
+}
+
+/// 自定义 Layer, 用于添加flashbots header X-Flashbots-Signature
+#[derive(Clone)]
+pub struct FlashbotsSignatureLayer {
+    wallet: PrivateKeySigner,
+}
+
+impl FlashbotsSignatureLayer {
+    pub fn new(wallet: PrivateKeySigner) -> Self {
+        Self { wallet }
+    }
+}
+
+impl<S> Layer<S> for FlashbotsSignatureLayer {
+    type Service = FlashbotsSignatureService<S>;
+    fn layer(&self, inner: S) -> Self::Service {
+        FlashbotsSignatureService {
+            inner,
+            wallet: self.wallet.clone(),
+        }
+    }
+}
+
+/// 实现flashbots signature service 来添加header
+#[derive(Clone)]
+pub struct FlashbotsSignatureService<S> {
+    inner: S,
+    wallet: PrivateKeySigner,
+}
+
+impl<S, B> Service<hyper::Request<B>> for FlashbotsSignatureService<S>
+where
+    S: Service<hyper::Request<B>, Response = HyperResponse, Error = Error>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send,
+    S::Error: std::error::Error + Send + Sync + 'static,
+    B: hyper::body::Body<Data = hyper::body::Bytes> + Send + 'static + Clone + Sync,
+    <B as hyper::body::Body>::Error: std::fmt::Debug,
+{
+    type Response = HyperResponse;
+    type Error = Error;
+    type Future = HyperResponseFut;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: hyper::Request<B>) -> Self::Future {
+        let mut cloned_req = req.clone();
+        let wallet = self.wallet.clone();
+
+        // 获取请求体
+        let whole_body = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(req.collect())
+        })
+        .unwrap()
+        .to_bytes();
+
+        // 给body签名的步骤是:
+        // 1. hash(body)
+        // 2. hex hashed_body
+        // 3. add 0x prefix to hexed_hashed_body
+        // 4. sign_message(0xhexed_hashed_body) 到这里走eth eip191 sign_message的流程就可以了
+        // 参考: https://github.com/onbjerg/ethers-flashbots/blob/64a0ac980702037dc7499ffdd46cb6bf406442f3/src/relay.rs#L84
+        let hashed_body = keccak256(whole_body.as_ref());
+        let hexed_body_with_perfix = hex::encode_prefixed(hashed_body);
+
+        // 生成签名
+        let signature = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current()
+                .block_on(wallet.sign_hash(&eip191_hash_message(&hexed_body_with_perfix)))
+        })
+        .unwrap();
+        let header_value = format!(
+            "{}:0x{}",
+            wallet.address(),
+            hex::encode(signature.as_bytes())
+        );
+
+        // 修改请求头
+        cloned_req.headers_mut().insert(
+            "X-Flashbots-Signature",
+            HeaderValue::from_str(&header_value).unwrap(),
+        );
+
+        // 调用底层服务
+        let fut = self.inner.call(cloned_req);
+
+        Box::pin(fut)
+    }
+}
@@ -0,0 +1,48 @@
+use alloy::providers::ProviderBuilder;
+use eyre::Result;
+use query::timestamp::BlockFinder;
+
+/// 查找某个时间点之后的第一个区块, 默认 2024-01-01 00:00:00 UTC
+///
+///   cargo run --bin block_by_timestamp -- 1704067200
+#[tokio::main]
+async fn main() -> Result<()> {
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string())
+        .parse()?;
+    let provider = ProviderBuilder::new().on_http(rpc_url);
+    let timestamp = match std::env::args().nth(1) {
+        Some(timestamp) => timestamp.parse()?,
+        None => 1704067200,
+    };
+
+    let finder = BlockFinder::new(provider);
+    let Some(first) = finder.first_block_at_or_after(timestamp).await? else {
+        println!("no block at or after {timestamp} yet");
+        return Ok(());
+    };
+    println!(
+        "first block at or after {timestamp}: {first}, fetched {} headers",
+        finder.cached()
+    );
+    let last = finder.last_block_before(timestamp).await?;
+    println!("last block before {timestamp}: {last:?}");
+
+    // 结果满足 ts(first - 1) < timestamp <= ts(first)
+    assert!(finder.header(first).await?.timestamp >= timestamp);
+    if let Some(last) = last {
+        assert_eq!(last + 1, first);
+        assert!(finder.header(last).await?.timestamp < timestamp);
+    }
+
+    // 附近的时间戳可以利用缓存的区块头缩小范围
+    let cached = finder.cached();
+    let an_hour_later = finder.first_block_at_or_after(timestamp + 3600).await?;
+    println!(
+        "first block at or after {}: {an_hour_later:?}, fetched {} more headers",
+        timestamp + 3600,
+        finder.cached() - cached
+    );
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use alloy::{
+    primitives::{Address, address},
+    providers::ProviderBuilder,
+};
+use eyre::Result;
+use query::creation::find_creation;
+use query::ens::EnsResolver;
+
+/// 主网上的合约
+const DEFAULT_ADDRESSES: [(&str, Address); 2] = [
+    // 部署交易创建
+    (
+        "WETH9",
+        address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+    ),
+    // Safe v1.3.0 GnosisSafeProxyFactory, 通过 deterministic deployment proxy 用 CREATE2 部署
+    (
+        "GnosisSafeProxyFactory",
+        address!("0xa6B71E26C5e0845f74c812102Ca7114b6a896AB2"),
+    ),
+];
+
+/// 查找合约的创建区块, 创建交易和部署者, 需要 archive 节点,
+/// 工厂合约创建的还需要节点支持 debug_traceBlockByNumber
+///
+///   cargo run --bin contract_creation -- [address or ENS name...]
+#[tokio::main]
+async fn main() -> Result<()> {
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string())
+        .parse()?;
+    let provider = ProviderBuilder::new().on_http(rpc_url);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let addresses = if args.is_empty() {
+        DEFAULT_ADDRESSES
+            .iter()
+            .map(|(name, address)| (name.to_string(), *address))
+            .collect()
+    } else {
+        let ens = EnsResolver::new(provider.clone());
+        let mut addresses = Vec::new();
+        for arg in args {
+            let address = ens.resolve_address(&arg).await?;
+            addresses.push((arg, address));
+        }
+        addresses
+    };
+
+    for (name, address) in addresses {
+        let Some(creation) = find_creation(&provider, address).await? else {
+            println!("{name} ({address}) has no code");
+            continue;
+        };
+        println!(
+            "{name} ({address}) created at block {}",
+            creation.block_number
+        );
+        match creation.tx {
+            Some(tx) => {
+                println!("  tx: {} ({})", tx.tx_hash, tx.kind);
+                println!("  deployer: {}, creator: {}", tx.deployer, tx.creator);
+            }
+            None => println!("  creation tx unknown"),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use alloy::{
+    primitives::{B256, Bytes, LogData, U256, address, b256, keccak256},
+    sol_types::SolValue,
+};
+use eyre::Result;
+use query::logs::EventRegistry;
+
+/// 用常见事件表和 ABI 文件解析 log, 包括签名相同的 ERC-20/ERC-721 Transfer,
+/// indexed 的动态类型和匿名事件
+fn main() -> Result<()> {
+    let mut registry = EventRegistry::with_common();
+    let count = registry.load_abi_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/abi/NameRegistry.json"
+    ))?;
+    println!("loaded {count} events, {} in registry", registry.len());
+
+    let from = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+    let to = address!("1f9840a85d5aF5bf1D1762F925BDADdC4201F984");
+    let transfer = keccak256("Transfer(address,address,uint256)");
+
+    // ERC-20: value 在 data 中
+    let erc20 = LogData::new_unchecked(
+        vec![transfer, from.into_word(), to.into_word()],
+        U256::from(1_000_000).abi_encode().into(),
+    );
+    let decoded = registry.decode(&erc20).expect("erc20 transfer");
+    println!("{decoded}");
+
+    // ERC-721: tokenId 是 indexed, data 为空
+    let erc721 = LogData::new_unchecked(
+        vec![
+            transfer,
+            from.into_word(),
+            to.into_word(),
+            B256::from(U256::from(42)),
+        ],
+        Bytes::new(),
+    );
+    let decoded = registry.decode(&erc721).expect("erc721 transfer");
+    println!("{decoded}");
+
+    // indexed string 在 topic 中是哈希
+    let name_registered = LogData::new_unchecked(
+        vec![
+            keccak256("NameRegistered(string,address,string)"),
+            keccak256("alice"),
+            from.into_word(),
+        ],
+        ("alice.eth".to_string(),).abi_encode_params().into(),
+    );
+    let decoded = registry.decode(&name_registered).expect("name registered");
+    println!("{decoded}");
+    println!("{}", decoded.to_json());
+
+    // 匿名事件没有签名 topic, 按 topic 数量和 data 匹配
+    let note = LogData::new_unchecked(
+        vec![
+            b256!("0xa9059cbb00000000000000000000000000000000000000000000000000000000"),
+            from.into_word(),
+        ],
+        (Bytes::from_static(b"note"),).abi_encode_params().into(),
+    );
+    let decoded = registry.decode(&note).expect("anonymous note");
+    println!("{decoded}");
+
+    // 未注册的事件
+    let unknown = LogData::new_unchecked(
+        vec![keccak256("Unknown(uint256)")],
+        U256::from(1).abi_encode().into(),
+    );
+    println!(
+        "unknown event decoded: {}",
+        registry.decode(&unknown).is_some()
+    );
+    // topic 中的地址高位不为 0, 不是合法的 ERC-20 Transfer
+    let invalid = LogData::new_unchecked(
+        vec![transfer, B256::repeat_byte(0xff), to.into_word()],
+        U256::from(1).abi_encode().into(),
+    );
+    println!(
+        "invalid transfer decoded: {}",
+        registry.decode(&invalid).is_some()
+    );
+
+    Ok(())
+}
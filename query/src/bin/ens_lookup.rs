@@ -0,0 +1,171 @@
+use alloy::{
+    node_bindings::Anvil,
+    primitives::{Address, B256, address, b256, utils::parse_ether},
+    providers::{Provider, ProviderBuilder, ext::AnvilApi},
+    sol,
+};
+use eyre::Result;
+use query::ens::{
+    ENS_REGISTRY_ADDRESS, EnsResolver, contenthash_uri, labelhash, namehash, reverse_name,
+};
+
+sol! {
+    #[sol(rpc)]
+    interface IEnsRegistry {
+        function owner(bytes32 node) external view returns (address);
+        function setSubnodeRecord(bytes32 node, bytes32 label, address owner, address resolver, uint64 ttl) external;
+    }
+
+    #[sol(rpc)]
+    interface IReverseRegistrar {
+        function defaultResolver() external view returns (address);
+        function setName(string name) external returns (bytes32);
+    }
+
+    #[sol(rpc)]
+    interface IPublicResolver {
+        function setAddr(bytes32 node, address addr) external;
+        function setText(bytes32 node, string key, string value) external;
+        function setContenthash(bytes32 node, bytes hash) external;
+    }
+}
+
+const VITALIK: Address = address!("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+const CID: &str = "QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4";
+
+/// fork 主网, 使用主网上部署的 ENS registry, reverse registrar 和 public resolver
+///
+///   cargo run --bin ens_lookup
+#[tokio::main]
+async fn main() -> Result<()> {
+    assert_eq!(namehash(""), B256::ZERO);
+    assert_eq!(
+        namehash("eth"),
+        b256!("0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae")
+    );
+    assert_eq!(namehash("Vitalik.ETH"), namehash("vitalik.eth"));
+
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string());
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().fork(rpc_url).try_spawn()?;
+    // anvil 的账户和 impersonate 的账户都可以直接 eth_sendTransaction, 不需要 wallet
+    let provider = ProviderBuilder::new().on_http(anvil.endpoint_url());
+    let ens = EnsResolver::new(provider.clone());
+
+    // 主网上已有的名称
+    assert_eq!(ens.address("vitalik.eth").await?, Some(VITALIK));
+    println!(
+        "vitalik.eth resolver: {:?}",
+        ens.resolver("vitalik.eth").await?
+    );
+    println!(
+        "vitalik.eth url: {:?}",
+        ens.text("vitalik.eth", "url").await?
+    );
+    if let Some(contenthash) = ens.contenthash("vitalik.eth").await? {
+        println!(
+            "vitalik.eth contenthash: {contenthash} {:?}",
+            contenthash_uri(&contenthash)
+        );
+    }
+    println!(
+        "{VITALIK} reverse: {:?}",
+        ens.lookup_address(VITALIK).await?
+    );
+    assert_eq!(ens.address("not-registered-name-1234.eth").await?, None);
+
+    let fork_block = provider.get_block_number().await?;
+
+    // 用 .eth registrar 的身份在 registry 中注册 learning-alloy.eth
+    let registry = IEnsRegistry::new(ENS_REGISTRY_ADDRESS, provider.clone());
+    let eth_registrar = registry.owner(namehash("eth")).call().await?._0;
+    let reverse_registrar = registry.owner(namehash("addr.reverse")).call().await?._0;
+    let reverse_registrar = IReverseRegistrar::new(reverse_registrar, provider.clone());
+    let public_resolver = reverse_registrar.defaultResolver().call().await?._0;
+
+    let name = "learning-alloy.eth";
+    let node = namehash(name);
+    let user = anvil.addresses()[0];
+    provider.anvil_impersonate_account(eth_registrar).await?;
+    provider
+        .anvil_set_balance(eth_registrar, parse_ether("1")?)
+        .await?;
+    let receipt = registry
+        .setSubnodeRecord(
+            namehash("eth"),
+            labelhash("learning-alloy"),
+            user,
+            public_resolver,
+            0,
+        )
+        .from(eth_registrar)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    assert!(receipt.status());
+    provider
+        .anvil_stop_impersonating_account(eth_registrar)
+        .await?;
+
+    // 设置 addr, text, contenthash 记录和反向解析
+    let mut contenthash = vec![0xe3, 0x01, 0x01, 0x70];
+    contenthash.extend(bs58::decode(CID).into_vec()?);
+    let resolver = IPublicResolver::new(public_resolver, provider.clone());
+    let txs = [
+        resolver.setAddr(node, user).from(user).send().await?,
+        resolver
+            .setText(node, "url".into(), "https://alloy.rs".into())
+            .from(user)
+            .send()
+            .await?,
+        resolver
+            .setContenthash(node, contenthash.into())
+            .from(user)
+            .send()
+            .await?,
+        reverse_registrar
+            .setName(name.into())
+            .from(user)
+            .send()
+            .await?,
+    ];
+    for tx in txs {
+        assert!(tx.get_receipt().await?.status());
+    }
+
+    assert_eq!(ens.resolver(name).await?, Some(public_resolver));
+    assert_eq!(ens.address(name).await?, Some(user));
+    assert_eq!(
+        ens.text(name, "url").await?.as_deref(),
+        Some("https://alloy.rs")
+    );
+    assert_eq!(ens.text(name, "avatar").await?, None);
+    let contenthash = ens.contenthash(name).await?.unwrap();
+    assert_eq!(contenthash_uri(&contenthash), Some(format!("ipfs://{CID}")));
+    assert_eq!(ens.lookup_address(user).await?.as_deref(), Some(name));
+    assert_eq!(ens.resolve_address(name).await?, user);
+    assert_eq!(ens.resolve_address(&user.to_string()).await?, user);
+    println!("{name} -> {user}, {} -> {name}", reverse_name(user));
+
+    // addr 改成其他地址后, 反向解析的结果不能通过正向验证
+    let other = anvil.addresses()[1];
+    let receipt = resolver
+        .setAddr(node, other)
+        .from(user)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    assert!(receipt.status());
+    assert_eq!(ens.lookup_address(user).await?, None);
+    assert_eq!(ens.lookup_address(other).await?, None);
+
+    // 查询注册之前的区块
+    let historical = EnsResolver::new(provider).block(fork_block.into());
+    assert_eq!(historical.address(name).await?, None);
+    assert!(historical.resolve_address(name).await.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use alloy::{
+    providers::{Provider, ProviderBuilder},
+    rpc::types::Filter,
+};
+use eyre::{Result, eyre};
+use query::ens::EnsResolver;
+use query::export::{Dataset, Exporter, Format};
+
+const USAGE: &str = "usage: export <from block> <to block> [--out <dir>] [--format csv|jsonl|parquet] \
+[--datasets logs,blocks,transactions,receipts] [--address <address or ENS name>] [--event <signature>] [--chunk-blocks <n>]";
+
+/// 导出区块范围内的 logs, 区块, 交易和 receipt, 中断后重新运行会跳过已经写完的分段
+///
+///   cargo run --bin export -- 21000000 21000099 --datasets logs,blocks --format parquet \
+///     --address 0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48 --event "Transfer(address,address,uint256)"
+#[tokio::main]
+async fn main() -> Result<()> {
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string())
+        .parse()?;
+    let provider = ProviderBuilder::new().on_http(rpc_url);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [from_block, to_block, options @ ..] = args.as_slice() else {
+        return Err(eyre!(USAGE));
+    };
+    let latest = provider.get_block_number().await?;
+    let parse_block = |block: &str| -> Result<u64> {
+        match block {
+            "latest" => Ok(latest),
+            _ => Ok(block.parse()?),
+        }
+    };
+    let from_block = parse_block(from_block)?;
+    let to_block = parse_block(to_block)?;
+
+    let mut out_dir = "export".to_string();
+    let mut format = Format::Csv;
+    let mut datasets = vec![Dataset::Logs];
+    let mut filter = Filter::new();
+    let ens = EnsResolver::new(provider.clone());
+    let mut chunk_blocks = 1000;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let value = options
+            .next()
+            .ok_or_else(|| eyre!("missing value of {option}\n{USAGE}"))?;
+        match option.as_str() {
+            "--out" => out_dir = value.clone(),
+            "--format" => format = value.parse()?,
+            "--datasets" => {
+                datasets = value
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<Vec<_>>>()?;
+            }
+            "--address" => filter = filter.address(ens.resolve_address(value).await?),
+            "--event" => filter = filter.event(value),
+            "--chunk-blocks" => chunk_blocks = value.parse()?,
+            _ => return Err(eyre!("unknown option {option}\n{USAGE}")),
+        }
+    }
+
+    let exporter = Exporter::new(provider, &out_dir, format)
+        .datasets(&datasets)
+        .filter(filter)
+        .chunk_blocks(chunk_blocks);
+    let summary = exporter.export(from_block, to_block).await?;
+
+    for file in &summary.files {
+        println!("wrote {}", file.display());
+    }
+    if !summary.skipped.is_empty() {
+        println!("skipped {} existing files", summary.skipped.len());
+    }
+    for file in &summary.replaced {
+        println!("merged and removed {}", file.display());
+    }
+    for (dataset, rows) in &summary.rows {
+        println!("{dataset}: {rows} rows");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use alloy::providers::ProviderBuilder;
+use eyre::Result;
+use futures_util::StreamExt;
+use query::fees::{FeeCeilings, FeeEstimate, FeeOracle};
+use std::pin::pin;
+
+const GWEI: u128 = 1_000_000_000;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // cargo run --bin fee_oracle
+    // 默认使用主网, 可以通过 RPC_URL 指定其他链
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string())
+        .parse()?;
+    let provider = ProviderBuilder::new().on_http(rpc_url);
+
+    // 最近 20 个区块, priority fee 最多 5 gwei
+    let oracle = FeeOracle::new(provider).ceilings(FeeCeilings {
+        priority_fee: Some(5 * GWEI),
+        ..Default::default()
+    });
+    print_estimate(&oracle.estimate().await?);
+
+    // 每个新区块更新一次
+    let mut estimates = pin!(oracle.stream().await?.take(3));
+    while let Some(estimate) = estimates.next().await {
+        print_estimate(&estimate?);
+    }
+
+    Ok(())
+}
+
+fn print_estimate(estimate: &FeeEstimate) {
+    println!(
+        "block {}: next base fee {} gwei, next blob base fee {:?} wei",
+        estimate.block_number,
+        gwei(estimate.next_base_fee),
+        estimate.next_blob_base_fee
+    );
+    for (name, level) in [
+        ("slow", estimate.slow),
+        ("standard", estimate.standard),
+        ("fast", estimate.fast),
+    ] {
+        println!(
+            "  {name:<8} max fee {:>10} gwei, priority fee {:>8} gwei, blob base fee {:?} wei",
+            gwei(level.max_fee_per_gas()),
+            gwei(level.priority_fee),
+            level.blob_base_fee
+        );
+    }
+}
+
+fn gwei(wei: u128) -> String {
+    format!("{:.3}", wei as f64 / GWEI as f64)
+}
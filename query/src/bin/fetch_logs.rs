@@ -0,0 +1,53 @@
+use alloy::{
+    primitives::address,
+    providers::{Provider, ProviderBuilder},
+    rpc::types::Filter,
+};
+use eyre::Result;
+use futures_util::{StreamExt, pin_mut};
+use query::logs::LogFetcher;
+
+/// query_logs 只查询单个区块, 这里查询最近 20000 个区块的 USDC Transfer 事件
+/// LogFetcher 会根据节点的限制自动调整每次查询的区块范围
+#[tokio::main]
+async fn main() -> Result<()> {
+    let http_rpc_url = "https://ethereum-rpc.publicnode.com".parse()?;
+    let http_provider = ProviderBuilder::new().on_http(http_rpc_url);
+
+    let latest_block_num = http_provider.get_block_number().await?;
+    let from_block = latest_block_num - 20_000;
+
+    let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    let filter = Filter::new()
+        .address(usdc)
+        .event("Transfer(address,address,uint256)")
+        .from_block(from_block)
+        .to_block(latest_block_num);
+
+    let fetcher = LogFetcher::new(http_provider, filter)?
+        .chunk_size(500)
+        .concurrency(4);
+    let logs = fetcher.stream();
+    pin_mut!(logs);
+
+    let mut count = 0;
+    let mut last_block = 0;
+    while let Some(log) = logs.next().await {
+        let log = log?;
+        // stream 按区块顺序输出
+        let block_number = log.block_number.unwrap_or_default();
+        assert!(block_number >= last_block);
+        last_block = block_number;
+
+        if count < 10 {
+            println!("log: {:?}", log);
+        }
+        count += 1;
+    }
+    println!(
+        "get {count} transfer logs from block {}, to block {}",
+        from_block, latest_block_num
+    );
+
+    Ok(())
+}
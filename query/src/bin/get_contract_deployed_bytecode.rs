@@ -1,8 +1,10 @@
 use alloy::{
+    eips::BlockId,
     primitives::address,
     providers::{Provider, ProviderBuilder},
 };
 use eyre::Result;
+use query::bytecode;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -13,9 +15,46 @@ async fn main() -> Result<()> {
     let http_provider = ProviderBuilder::new().on_http(http_url);
 
     let pool_address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
-    let bytecode = http_provider.get_code_at(pool_address).await?;
+    // 默认查询 latest, 可以通过参数指定区块
+    let block = match std::env::args().nth(1) {
+        Some(block) => block.parse()?,
+        None => BlockId::latest(),
+    };
+    let bytecode = http_provider
+        .get_code_at(pool_address)
+        .block_id(block)
+        .await?;
 
-    println!("contract uniswap V3 USDC-ETH pool's bytecode: {bytecode:?}");
+    println!("contract uniswap V3 USDC-ETH pool's bytecode at block {block}: {bytecode:?}");
+
+    // 解析 bytecode
+    let info = bytecode::inspect(&bytecode);
+    println!("code size: {} bytes", info.code_size);
+    if let Some(metadata) = &info.metadata {
+        println!(
+            "metadata: compiler {:?}, ipfs {:?}, bzzr1 {:?}, experimental {}",
+            metadata.compiler,
+            metadata.ipfs_cid(),
+            metadata.bzzr1,
+            metadata.experimental
+        );
+    }
+    println!("function selectors: {:?}", info.selectors);
+    println!(
+        "fork specific opcodes: {:?}, requires {}",
+        info.fork_opcodes,
+        info.min_fork()
+    );
+    // 常量或者 immutable, 部署后的 immutable 已经替换为实际的值, 例如 factory, token0, token1
+    for word in &info.push_words {
+        println!("PUSH32 at {:#06x}: {}", word.pc, word.value);
+    }
+    println!("{} jump destinations", info.jumpdests.len());
+
+    // 只打印前 50 行反汇编
+    for line in bytecode::listing(&bytecode).lines().take(50) {
+        println!("{line}");
+    }
 
     Ok(())
 }
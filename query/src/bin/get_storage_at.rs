@@ -1,4 +1,5 @@
 use alloy::{
+    eips::BlockId,
     primitives::{U256, address},
     providers::{Provider, ProviderBuilder},
 };
@@ -16,11 +17,17 @@ async fn main() -> Result<()> {
     let pool_address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
     let storage_slot = U256::from(0);
 
-    // 默认在latest block上调用, .block() 可以设置一个区块
+    // 默认在latest block上调用, 可以通过参数指定区块号, 区块哈希或者 latest/finalized/safe 等
+    // cargo run --bin get_storage_at -- 21000000
+    let block = match std::env::args().nth(1) {
+        Some(block) => block.parse()?,
+        None => BlockId::latest(),
+    };
     let storage = http_provider
         .get_storage_at(pool_address, storage_slot)
+        .block_id(block)
         .await?;
-    println!("contract uniswap V3 USDC=ETH pool Sloat 0: {storage:?}");
+    println!("contract uniswap V3 USDC=ETH pool Sloat 0 at block {block}: {storage:?}");
 
     Ok(())
 }
@@ -0,0 +1,74 @@
+use alloy::{
+    dyn_abi::DynSolValue, network::EthereumWallet, node_bindings::Anvil,
+    providers::ProviderBuilder, signers::local::PrivateKeySigner, sol,
+};
+use eyre::Result;
+use query::storage::{BatchSource, StorageDecoder, StorageLayout};
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.26; solc Counter.sol --viz-ir --optimize --bin
+    #[sol(rpc, bytecode="608060405234801561000f575f80fd5b505f5b600b8110156100e6575f60405180606001604052808360016100349190610100565b6001600160a01b031681526020018361004e426002610119565b6100589190610130565b6001600160401b03168152602001610071846001610100565b61008390670de0b6b3a7640000610119565b90528154600181810184555f93845260209384902083516002909302018054948401516001600160401b0316600160a01b026001600160e01b03199095166001600160a01b03909316929092179390931781556040909101519082015501610012565b50610143565b634e487b7160e01b5f52601160045260245ffd5b80820180821115610113576101136100ec565b92915050565b8082028115828204841417610113576101136100ec565b81810381811115610113576101136100ec565b603e8061014f5f395ff3fe60806040525f80fdfea264697066735822122061c6be102b852df2191c9d69e810dfdc06e005d40a4ee6dd27462a263b7c273b64736f6c634300081a0033")]
+    contract esRNT {
+        struct LockInfo {
+            address user;
+            uint64 startTime;
+            uint256 amount;
+        }
+
+        LockInfo[] private _locks;
+
+        constructor() {
+            for (uint256 i = 0; i < 11; i++) {
+                _locks.push(LockInfo(address(uint160(i + 1)), uint64(block.timestamp * 2 - i), 1e18 * (i + 1)));
+            }
+        }
+    }
+}
+
+/// 和 get_locks_data 读取同样的数据, 但不再手动计算 slot 和切分字节,
+/// 而是根据 solc --storage-layout 输出的 layout 解析
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().block_time(1).try_spawn()?;
+    let pk: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let wallet = EthereumWallet::new(pk);
+
+    let http_provider = ProviderBuilder::new()
+        .wallet(wallet)
+        .on_http(anvil.endpoint_url());
+
+    let contract = esRNT::deploy(http_provider.clone()).await?;
+    println!("Deployed esRNT contract at: {}", contract.address());
+
+    // solc esRNT.sol --storage-layout
+    let layout = StorageLayout::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/layout/esRNT.json"
+    ))?;
+
+    // 固定在当前区块读取, 避免读取过程中出块导致数据不一致
+    // 先读数组长度, 再把所有元素的 slot 放到一个 batch 中读取, 总共两次请求
+    let source = BatchSource::at_latest(http_provider, *contract.address()).await?;
+    println!("read storage at block {}", source.block_id());
+    let decoder = StorageDecoder::new(source, layout);
+
+    let DynSolValue::Array(locks) = decoder.read("_locks").await? else {
+        return Err(eyre::eyre!("_locks is not a dynamic array"));
+    };
+    for (i, lock) in locks.iter().enumerate() {
+        // struct 解析为 tuple, 成员顺序和 layout 中的 members 一致
+        if let DynSolValue::Tuple(fields) = lock {
+            println!(
+                "locks[{}]: user: {:?}, startTime: {:?}, amount: {:?}",
+                i,
+                fields[0].as_address(),
+                fields[1].as_uint().map(|(v, _)| v),
+                fields[2].as_uint().map(|(v, _)| v),
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use alloy::{
+    eips::BlockId,
+    primitives::{U256, address},
+    providers::{Provider, ProviderBuilder},
+};
+use eyre::Result;
+use query::storage::{ProofError, ProofSource, SlotSource};
+
+/// 和 get_storage_at 读取同样的 slot, 但是通过 eth_getProof 读取并校验证明,
+/// 公共 RPC 节点返回的数据不需要被信任
+#[tokio::main]
+async fn main() -> Result<()> {
+    let http_url = "https://ethereum-rpc.publicnode.com".parse()?;
+    let http_provider = ProviderBuilder::new().on_http(http_url);
+
+    // Uniswap V3 USDC-ETH pool on Ethereum mainnet.
+    let pool_address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+
+    // 区块哈希可以和其他节点, 或者轻客户端对比后通过 BlockId::hash 指定, 这里直接使用 latest,
+    // 区块头的哈希会重新计算并校验
+    let block_number = http_provider.get_block_number().await?;
+    let source =
+        ProofSource::new(http_provider, pool_address, BlockId::number(block_number)).await?;
+    println!(
+        "verify storage against block {block_number} state root {}",
+        source.state_root()
+    );
+
+    // slot0, feeGrowthGlobal0X128, feeGrowthGlobal1X128, protocolFees, liquidity
+    let slots: Vec<U256> = (0..5).map(U256::from).collect();
+    source.prefetch(&slots).await?;
+
+    for slot in slots {
+        match source.storage_at(slot).await {
+            Ok(value) => println!("verified slot {slot}: {value:#x}"),
+            Err(e) => match e.downcast_ref::<ProofError>() {
+                Some(proof_error) => println!("slot {slot} proof error: {proof_error}"),
+                None => return Err(e),
+            },
+        }
+    }
+
+    Ok(())
+}
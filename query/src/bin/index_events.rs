@@ -0,0 +1,110 @@
+use alloy::{
+    json_abi::Event,
+    network::EthereumWallet,
+    node_bindings::Anvil,
+    providers::{ProviderBuilder, ext::AnvilApi},
+    rpc::types::Filter,
+    signers::local::PrivateKeySigner,
+    sol,
+};
+use eyre::Result;
+use query::indexer::{IndexFilter, Indexer, IndexerDb};
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.25, 和 contracts/src/bin/events_errors.rs 中的合约相同
+    #[sol(rpc, bytecode = "608060405260008055348015601357600080fd5b506103e9806100236000396000f3fe608060405234801561001057600080fd5b50600436106100575760003560e01c80632baeceb71461005c5780632ccbdbca1461006657806361bc221a14610070578063c3e8b5ca1461008e578063d09de08a14610098575b600080fd5b6100646100a2565b005b61006e610103565b005b61007861013e565b60405161008591906101f9565b60405180910390f35b610096610144565b005b6100a061017f565b005b60016000808282546100b49190610243565b925050819055506000543373ffffffffffffffffffffffffffffffffffffffff167fdc69c403b972fc566a14058b3b18e1513da476de6ac475716e489fae0cbe4a2660405160405180910390a3565b6040517f23b0db14000000000000000000000000000000000000000000000000000000008152600401610135906102e3565b60405180910390fd5b60005481565b6040517fa5f9ec670000000000000000000000000000000000000000000000000000000081526004016101769061034f565b60405180910390fd5b6001600080828254610191919061036f565b925050819055506000543373ffffffffffffffffffffffffffffffffffffffff167ff6d1d8d205b41f9fb9549900a8dba5d669d68117a3a2b88c1ebc61163e8117ba60405160405180910390a3565b6000819050919050565b6101f3816101e0565b82525050565b600060208201905061020e60008301846101ea565b92915050565b7f4e487b7100000000000000000000000000000000000000000000000000000000600052601160045260246000fd5b600061024e826101e0565b9150610259836101e0565b92508282039050818112600084121682821360008512151617156102805761027f610214565b5b92915050565b600082825260208201905092915050565b7f4572726f72204100000000000000000000000000000000000000000000000000600082015250565b60006102cd600783610286565b91506102d882610297565b602082019050919050565b600060208201905081810360008301526102fc816102c0565b9050919050565b7f4572726f72204200000000000000000000000000000000000000000000000000600082015250565b6000610339600783610286565b915061034482610303565b602082019050919050565b600060208201905081810360008301526103688161032c565b9050919050565b600061037a826101e0565b9150610385836101e0565b9250828201905082811215600083121683821260008412151617156103ad576103ac610214565b5b9291505056fea2646970667358221220a878a3c1da1a1170e4496cdbc63bd5ed1587374bcd6cf6d4f1d5b88fa981795d64736f6c63430008190033")]
+    contract CounterWithError {
+        int256 public counter = 0;
+
+        event Increment(address indexed by, int256 indexed value);
+        event Decrement(address indexed by, int256 indexed value);
+
+        function increment() public {
+            counter += 1;
+            emit Increment(msg.sender, counter);
+        }
+
+        function decrement() public {
+            counter -= 1;
+            emit Decrement(msg.sender, counter);
+        }
+    }
+}
+
+/// 在 anvil 上运行 indexer, 包括重启后从 checkpoint 继续, 以及通过 snapshot/revert 制造的 reorg
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().try_spawn()?;
+    let pk: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::new(pk))
+        .on_http(anvil.endpoint_url());
+
+    let counter = CounterWithError::deploy(provider.clone()).await?;
+    println!(
+        "Deployed CounterWithError contract at: {}",
+        counter.address()
+    );
+
+    let db_path = std::env::temp_dir().join("query_index_events.sqlite");
+    let _ = std::fs::remove_file(&db_path);
+    let new_indexer = || -> Result<_> {
+        Ok(
+            Indexer::new(provider.clone(), IndexerDb::open(&db_path)?, 0)
+                .filter(IndexFilter::new(
+                    "increments",
+                    Event::parse("event Increment(address indexed by, int256 indexed value)")?,
+                    Filter::new().address(*counter.address()),
+                ))
+                .filter(IndexFilter::new(
+                    "decrements",
+                    Event::parse("event Decrement(address indexed by, int256 indexed value)")?,
+                    Filter::new().address(*counter.address()),
+                ))
+                .batch_size(2),
+        )
+    };
+
+    counter.increment().send().await?.get_receipt().await?;
+    // 之后回退到这里, 制造一次 reorg
+    let snapshot = provider.anvil_snapshot().await?;
+    counter.increment().send().await?.get_receipt().await?;
+    counter.decrement().send().await?.get_receipt().await?;
+
+    let mut indexer = new_indexer()?;
+    for event in indexer.sync().await? {
+        println!("{event:?}");
+    }
+    print_logs(indexer.db())?;
+    println!("checkpoint: {:?}", indexer.db().checkpoint()?);
+
+    // 模拟重启, 从数据库中的 checkpoint 继续, 没有新的区块需要处理
+    drop(indexer);
+    let mut indexer = new_indexer()?;
+    println!("events after restart: {:?}", indexer.sync().await?);
+
+    // 回退到 snapshot, 在新的分叉上 increment, 并多出几个块超过原来的高度
+    provider.anvil_revert(snapshot).await?;
+    counter.increment().send().await?.get_receipt().await?;
+    provider.anvil_mine(Some(3), None).await?;
+
+    // 第一个事件是 Reorged, 孤块中的 logs 被删除
+    for event in indexer.sync().await? {
+        println!("{event:?}");
+    }
+    print_logs(indexer.db())?;
+    println!("checkpoint after reorg: {:?}", indexer.db().checkpoint()?);
+
+    Ok(())
+}
+
+fn print_logs(db: &IndexerDb) -> Result<()> {
+    for filter in ["increments", "decrements"] {
+        for log in db.logs(filter)? {
+            println!("{filter} block {}: {:?}", log.block_number, log.decoded);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use alloy::{
+    network::EthereumWallet,
+    node_bindings::Anvil,
+    primitives::U256,
+    providers::{Provider, ProviderBuilder},
+    signers::local::PrivateKeySigner,
+    sol,
+};
+use eyre::Result;
+use query::multicall::{CallError, MULTICALL3_ADDRESS, Multicall};
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.26; solc Counter.sol --via-ir --optimize --bin
+    #[sol(rpc, bytecode="6080806040523460135760df908160198239f35b600080fdfe6080806040526004361015601257600080fd5b60003560e01c9081633fb5c1cb1460925781638381f58a146079575063d09de08a14603c57600080fd5b3460745760003660031901126074576000546000198114605e57600101600055005b634e487b7160e01b600052601160045260246000fd5b600080fd5b3460745760003660031901126074576020906000548152f35b34607457602036600319011260745760043560005500fea2646970667358221220e978270883b7baed10810c4079c941512e93a7ba1cd1108c781d4bc738d9090564736f6c634300081a0033")]
+    contract Counter {
+        uint256 public number;
+
+        function setNumber(uint256 newNumber) public {
+            number = newNumber;
+        }
+
+        function increment() public {
+            number++;
+        }
+    }
+}
+
+sol! {
+    #[sol(rpc)]
+    #[derive(Debug)]
+    interface IWETH9 {
+        function balanceOf(address owner) external view returns (uint256);
+    }
+}
+
+/// 在 anvil 上用一次 aggregate3 读取多个合约, Multicall3 不存在时自动部署
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().try_spawn()?;
+    let pk: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let owner = pk.address();
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::new(pk))
+        .on_http(anvil.endpoint_url());
+
+    let mut counters = Vec::new();
+    for i in 1..=3u64 {
+        let counter = Counter::deploy(provider.clone()).await?;
+        counter
+            .setNumber(U256::from(i * 10))
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        counters.push(counter);
+    }
+    println!(
+        "Multicall3 code before: {}",
+        provider.get_code_at(MULTICALL3_ADDRESS).await?
+    );
+
+    let mut multicall = Multicall::new(provider.clone());
+    let numbers = multicall.extend(counters.iter().map(|counter| counter.number()));
+    // Counter 没有 balanceOf, 调用会 revert
+    let reverted = multicall.add(IWETH9::new(*counters[0].address(), &provider).balanceOf(owner));
+    // 调用 EOA 成功但是没有返回数据, 解码失败
+    let no_code = multicall.add(IWETH9::new(owner, &provider).balanceOf(owner));
+    let results = multicall.call().await?;
+    println!(
+        "{} results, Multicall3 code after: {} bytes",
+        results.len(),
+        provider.get_code_at(MULTICALL3_ADDRESS).await?.len()
+    );
+
+    let numbers = results
+        .get_all(&numbers)
+        .into_iter()
+        .map(|number| Ok(number?.number))
+        .collect::<Result<Vec<_>, CallError>>()?;
+    println!("numbers: {numbers:?}");
+
+    let err = results.get(reverted).unwrap_err();
+    println!("balanceOf on Counter: {err}");
+    let err = results.get(no_code).unwrap_err();
+    println!("balanceOf on EOA: {err}");
+
+    // 指定区块读取: anvil_setCode 只修改当前状态, 之后挖出的区块才包含 Multicall3
+    let increment = async || -> Result<u64> {
+        let receipt = counters[0].increment().send().await?.get_receipt().await?;
+        Ok(receipt.block_number.unwrap_or_default())
+    };
+    let block = increment().await?;
+    increment().await?;
+    let mut multicall = Multicall::new(provider.clone());
+    let number = multicall.add(counters[0].number());
+    println!("latest: {}", multicall.call().await?.get(number)?.number);
+    let multicall = multicall.block(block.into());
+    println!(
+        "block {block}: {}",
+        multicall.call().await?.get(number)?.number
+    );
+
+    Ok(())
+}
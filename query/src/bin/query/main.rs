@@ -0,0 +1,263 @@
+mod output;
+
+use alloy::{
+    eips::{BlockId, BlockNumberOrTag},
+    primitives::{B256, U256, keccak256, utils::format_ether},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::Filter,
+};
+use eyre::{Result, bail, eyre};
+use futures_util::TryStreamExt;
+use query::{
+    bytecode,
+    ens::EnsResolver,
+    format::value_to_json,
+    logs::{EventRegistry, LogFetcher},
+    storage::{BatchSource, Encoding, SlotSource, StorageDecoder, StorageLayout},
+};
+use serde_json::{Map, Value, json};
+
+use crate::output::OutputFormat;
+
+const USAGE: &str = "\
+usage: query [--rpc-url <url>] [--block <number|hash|tag>] [--format table|json] <command> [args...]
+
+commands:
+  storage <address> <slot>...                  slot 的原始值
+  code <address>                               bytecode 的大小, 编译器, selector 等
+  logs <from> <to> [--address <address>] [--event <signature>]
+                                               区块范围内的 logs, 按常见事件解析
+  layout <address> <layout json> [label...]    按 storageLayout 解析状态变量, 默认读取除 mapping 外的所有变量
+  balance <address>                            ETH 余额
+  tx <hash>                                    交易
+  receipt <hash>                               交易的 receipt
+  block [number|hash|tag]                      区块, 默认为 --block 指定的区块
+
+地址都可以使用 ENS 名称, --rpc-url 默认读取 RPC_URL 环境变量, --block 默认 latest
+";
+
+/// 把查询功能合并为一个命令, 方便在脚本中使用
+///
+///   cargo run --bin query -- balance vitalik.eth --block 21000000
+///   cargo run --bin query -- --format json logs 21000000 21000010 --event "Transfer(address,address,uint256)"
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string());
+    let mut block = BlockId::latest();
+    let mut format = OutputFormat::Table;
+    let mut command = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| eyre!("missing value of {arg}\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--rpc-url" => rpc_url = value()?,
+            "--block" => block = value()?.parse()?,
+            "--format" => format = value()?.parse()?,
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            _ => command.push(arg),
+        }
+    }
+
+    let provider = ProviderBuilder::new().on_http(rpc_url.parse()?);
+    let cli = Cli {
+        // 名称也按 --block 指定的区块解析
+        ens: EnsResolver::new(provider.clone()).block(block),
+        provider,
+        block,
+    };
+    let Some((name, args)) = command.split_first() else {
+        bail!("missing command\n{USAGE}");
+    };
+    let output = match (name.as_str(), args) {
+        ("storage", [address, slots @ ..]) if !slots.is_empty() => {
+            cli.storage(address, slots).await?
+        }
+        ("code", [address]) => cli.code(address).await?,
+        ("logs", [from, to, options @ ..]) => cli.logs(from, to, options).await?,
+        ("layout", [address, layout, labels @ ..]) => cli.layout(address, layout, labels).await?,
+        ("balance", [address]) => cli.balance(address).await?,
+        ("tx", [hash]) => cli.tx(hash.parse()?).await?,
+        ("receipt", [hash]) => cli.receipt(hash.parse()?).await?,
+        ("block", []) => cli.block(block).await?,
+        ("block", [block]) => cli.block(block.parse()?).await?,
+        _ => bail!("invalid command {}\n{USAGE}", command.join(" ")),
+    };
+    output::print(&output, format)
+}
+
+struct Cli<P> {
+    provider: P,
+    ens: EnsResolver<P>,
+    block: BlockId,
+}
+
+impl<P: Provider + Clone> Cli<P> {
+    async fn storage(&self, address: &str, slots: &[String]) -> Result<Value> {
+        let address = self.ens.resolve_address(address).await?;
+        let slots = slots
+            .iter()
+            .map(|slot| slot.parse::<U256>())
+            .collect::<Result<Vec<_>, _>>()?;
+        // 所有 slot 在一个 batch 中读取
+        let source = BatchSource::new(self.provider.clone(), address, self.block);
+        source.prefetch(&slots).await?;
+        let mut rows = Vec::new();
+        for slot in slots {
+            let value = source.storage_at(slot).await?;
+            rows.push(json!({
+                "slot": format!("{slot:#x}"),
+                "value": B256::from(value),
+            }));
+        }
+        Ok(Value::Array(rows))
+    }
+
+    async fn code(&self, address: &str) -> Result<Value> {
+        let address = self.ens.resolve_address(address).await?;
+        let code = self
+            .provider
+            .get_code_at(address)
+            .block_id(self.block)
+            .await?;
+        let info = bytecode::inspect(&code);
+        let metadata = info.metadata.as_ref();
+        Ok(json!({
+            "address": address,
+            "size": info.code_size,
+            "code_hash": keccak256(&code),
+            "compiler": metadata
+                .and_then(|m| m.compiler.as_ref())
+                .map(|(name, version)| format!("{name} {version}")),
+            "ipfs": metadata.and_then(|m| m.ipfs_cid()),
+            "min_fork": info.min_fork().to_string(),
+            "selectors": info.selectors,
+        }))
+    }
+
+    async fn logs(&self, from: &str, to: &str, options: &[String]) -> Result<Value> {
+        // LogFetcher 需要具体的区块号, latest 等 tag 先转换成区块号
+        let mut filter = Filter::new()
+            .from_block(self.block_number(from.parse()?).await?)
+            .to_block(self.block_number(to.parse()?).await?);
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            let value = options
+                .next()
+                .ok_or_else(|| eyre!("missing value of {option}\n{USAGE}"))?;
+            match option.as_str() {
+                "--address" => filter = filter.address(self.ens.resolve_address(value).await?),
+                "--event" => filter = filter.event(value),
+                _ => bail!("unknown option {option}\n{USAGE}"),
+            }
+        }
+
+        let registry = EventRegistry::with_common();
+        let fetcher = LogFetcher::new(self.provider.clone(), filter)?;
+        let logs: Vec<_> = fetcher.stream().try_collect().await?;
+        let rows = logs
+            .iter()
+            .map(|log| {
+                let decoded = registry.decode(log.data());
+                json!({
+                    "block": log.block_number,
+                    "tx": log.transaction_hash,
+                    "index": log.log_index,
+                    "address": log.address(),
+                    "event": decoded.as_ref().map(|d| format!("{}.{}", d.source, d.event.name)),
+                    "params": decoded.as_ref().map(|d| d.to_json()),
+                })
+            })
+            .collect();
+        Ok(Value::Array(rows))
+    }
+
+    /// 十进制或 0x 开头的区块号直接返回, tag 和区块 hash 需要查询区块
+    async fn block_number(&self, block: BlockId) -> Result<u64> {
+        match block {
+            BlockId::Number(BlockNumberOrTag::Number(number)) => return Ok(number),
+            BlockId::Number(BlockNumberOrTag::Earliest) => return Ok(0),
+            _ => {}
+        }
+        let header = self
+            .provider
+            .get_block(block)
+            .await?
+            .ok_or_else(|| eyre!("block {block} not found"))?
+            .header;
+        Ok(header.number)
+    }
+
+    async fn layout(&self, address: &str, layout: &str, labels: &[String]) -> Result<Value> {
+        let address = self.ens.resolve_address(address).await?;
+        let layout = StorageLayout::from_file(layout)?;
+        // mapping 需要指定 key, 默认不读取
+        let labels = if labels.is_empty() {
+            let mut labels = Vec::new();
+            for item in &layout.storage {
+                if layout.type_info(&item.type_id)?.encoding != Encoding::Mapping {
+                    labels.push(item.label.clone());
+                }
+            }
+            labels
+        } else {
+            labels.to_vec()
+        };
+
+        let source = BatchSource::new(self.provider.clone(), address, self.block);
+        let decoder = StorageDecoder::new(source, layout);
+        let mut fields = Map::new();
+        for label in labels {
+            let value = decoder.read(&label).await?;
+            fields.insert(label, value_to_json(&value));
+        }
+        Ok(Value::Object(fields))
+    }
+
+    async fn balance(&self, address: &str) -> Result<Value> {
+        let address = self.ens.resolve_address(address).await?;
+        let balance = self
+            .provider
+            .get_balance(address)
+            .block_id(self.block)
+            .await?;
+        Ok(json!({
+            "address": address,
+            "wei": balance.to_string(),
+            "ether": format_ether(balance),
+        }))
+    }
+
+    async fn tx(&self, hash: B256) -> Result<Value> {
+        let tx = self
+            .provider
+            .get_transaction_by_hash(hash)
+            .await?
+            .ok_or_else(|| eyre!("transaction {hash} not found"))?;
+        Ok(serde_json::to_value(tx)?)
+    }
+
+    async fn receipt(&self, hash: B256) -> Result<Value> {
+        let receipt = self
+            .provider
+            .get_transaction_receipt(hash)
+            .await?
+            .ok_or_else(|| eyre!("receipt of {hash} not found"))?;
+        Ok(serde_json::to_value(receipt)?)
+    }
+
+    async fn block(&self, block: BlockId) -> Result<Value> {
+        let block = self
+            .provider
+            .get_block(block)
+            .await?
+            .ok_or_else(|| eyre!("block {block} not found"))?;
+        Ok(serde_json::to_value(block)?)
+    }
+}
@@ -0,0 +1,112 @@
+use std::str::FromStr;
+
+use eyre::{Result, eyre};
+use serde_json::{Map, Value};
+
+/// 输出格式, table 方便阅读, json 方便脚本处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            _ => Err(eyre!("unknown output format {s}, expected table or json")),
+        }
+    }
+}
+
+pub fn print(value: &Value, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        OutputFormat::Table => print!("{}", table(value)),
+    }
+    Ok(())
+}
+
+/// object 每个字段一行, object 的数组每个元素一行, 嵌套的值显示为单行 json
+fn table(value: &Value) -> String {
+    match value {
+        Value::Object(fields) => {
+            let rows = fields
+                .iter()
+                .map(|(key, value)| vec![key.clone(), cell(value)])
+                .collect::<Vec<_>>();
+            render(None, &rows)
+        }
+        Value::Array(values) if !values.is_empty() && values.iter().all(Value::is_object) => {
+            let mut headers: Vec<String> = Vec::new();
+            for fields in values.iter().filter_map(Value::as_object) {
+                for key in fields.keys() {
+                    if !headers.contains(key) {
+                        headers.push(key.clone());
+                    }
+                }
+            }
+            let rows = values
+                .iter()
+                .filter_map(Value::as_object)
+                .map(|fields| row(&headers, fields))
+                .collect::<Vec<_>>();
+            render(Some(&headers), &rows)
+        }
+        Value::Array(values) => values.iter().map(|value| cell(value) + "\n").collect(),
+        value => cell(value) + "\n",
+    }
+}
+
+fn row(headers: &[String], fields: &Map<String, Value>) -> Vec<String> {
+    headers
+        .iter()
+        .map(|header| fields.get(header).map(cell).unwrap_or_default())
+        .collect()
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// 按列对齐, 最后一列不补空格
+fn render(headers: Option<&[String]>, rows: &[Vec<String>]) -> String {
+    let columns = headers.map_or_else(|| rows.first().map_or(0, Vec::len), <[_]>::len);
+    let mut widths = vec![0; columns];
+    for row in headers.into_iter().chain(rows.iter().map(Vec::as_slice)) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    let mut push_row = |row: &[String]| {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        out.push_str(line.trim_end());
+        out.push('\n');
+    };
+    if let Some(headers) = headers {
+        push_row(headers);
+        let separator = widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>();
+        push_row(&separator);
+    }
+    for row in rows {
+        push_row(row);
+    }
+    out
+}
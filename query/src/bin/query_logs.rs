@@ -5,6 +5,7 @@ use alloy::{
 };
 use eyre::Result;
 use futures_util::stream::{self, StreamExt};
+use query::logs::EventRegistry;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -22,6 +23,9 @@ async fn main() -> Result<()> {
     let ws_connect = WsConnect::new(ws_url);
     let ws_provider = ProviderBuilder::new().on_ws(ws_connect).await?;
 
+    // 常见事件的 ABI, 用来解析 log
+    let registry = EventRegistry::with_common();
+
     // 查询指定区块区间的所有event 数据
     // get latest block number
     let latest_block_num = http_provider.get_block_number().await?;
@@ -59,9 +63,13 @@ async fn main() -> Result<()> {
     // }
 
     // 查询到的logs太多，这里转成stream打印前10个
+    // ERC-20 和 ERC-721 的 Transfer 签名相同, 根据 indexed 参数的数量区分
     let mut transfer_logs_stream = stream::iter(transfer_logs).take(10);
     while let Some(log) = transfer_logs_stream.next().await {
-        println!("log: {:?}", log);
+        match registry.decode(log.data()) {
+            Some(decoded) => println!("{}: {decoded}", log.address()),
+            None => println!("log: {:?}", log),
+        }
     }
 
     // 查询指定合约的log
@@ -77,7 +85,10 @@ async fn main() -> Result<()> {
     );
 
     for log in uniswap_logs {
-        println!("log: {:?}", log);
+        match registry.decode(log.data()) {
+            Some(decoded) => println!("{decoded}"),
+            None => println!("log: {:?}", log),
+        }
     }
 
     Ok(())
@@ -0,0 +1,76 @@
+use alloy::{
+    primitives::{Address, address},
+    providers::ProviderBuilder,
+};
+use eyre::Result;
+use query::ens::EnsResolver;
+use query::proxy::ProxyResolver;
+
+/// 主网上的代理合约
+const DEFAULT_ADDRESSES: [(&str, Address); 3] = [
+    // EigenLayer EigenPodManager, OpenZeppelin TransparentUpgradeableProxy
+    (
+        "EigenPodManager",
+        address!("0x91E677b07F7AF907ec9a428aafA9fc14a0d3A338"),
+    ),
+    // Compound v3 cUSDCv3, TransparentUpgradeableProxy
+    (
+        "cUSDCv3",
+        address!("0xc3d688B66703497DAA19211EEdff47f25384cdc3"),
+    ),
+    // Safe v1.3.0 singleton, 不是代理
+    (
+        "GnosisSafe",
+        address!("0xd9Db270c1B5E3Bd161E8c8503c55cEABeE709552"),
+    ),
+];
+
+/// 识别代理合约, 一直跟踪到最终的实现合约
+///
+///   cargo run --bin resolve_proxy -- [address or ENS name...]
+#[tokio::main]
+async fn main() -> Result<()> {
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string())
+        .parse()?;
+    let provider = ProviderBuilder::new().on_http(rpc_url);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let addresses = if args.is_empty() {
+        DEFAULT_ADDRESSES
+            .iter()
+            .map(|(name, address)| (name.to_string(), *address))
+            .collect()
+    } else {
+        let ens = EnsResolver::new(provider.clone());
+        let mut addresses = Vec::new();
+        for arg in args {
+            let address = ens.resolve_address(&arg).await?;
+            addresses.push((arg, address));
+        }
+        addresses
+    };
+
+    let resolver = ProxyResolver::new(provider);
+    for (name, address) in addresses {
+        let resolution = resolver.resolve(address).await?;
+        if resolution.hops.is_empty() {
+            println!("{name} ({address}) is not a proxy");
+            continue;
+        }
+        println!("{name} ({address}):");
+        for hop in &resolution.hops {
+            print!("  {} {} -> {}", hop.kind, hop.proxy, hop.implementation);
+            if let Some(admin) = hop.admin {
+                print!(", admin {admin}");
+            }
+            if let Some(beacon) = hop.beacon {
+                print!(", beacon {beacon}");
+            }
+            println!();
+        }
+        println!("  implementation: {}", resolution.implementation());
+    }
+
+    Ok(())
+}
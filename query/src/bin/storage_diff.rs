@@ -0,0 +1,60 @@
+use alloy::{
+    eips::BlockId,
+    primitives::{B256, U256},
+    providers::ProviderBuilder,
+};
+use eyre::{Result, eyre};
+use query::diff::{diff_blocks, diff_transaction};
+use query::ens::EnsResolver;
+
+/// 只打印变化的部分
+///
+/// 比较两个区块:
+///   cargo run --bin storage_diff -- <address or ENS name> <before block> <after block> [slot...]
+/// 比较一笔交易前后(需要节点支持 debug_traceTransaction):
+///   cargo run --bin storage_diff -- tx <tx hash>
+#[tokio::main]
+async fn main() -> Result<()> {
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string())
+        .parse()?;
+    let provider = ProviderBuilder::new().on_http(rpc_url);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [cmd, tx_hash] if cmd == "tx" => {
+            let tx_hash: B256 = tx_hash.parse()?;
+            let diffs = diff_transaction(&provider, tx_hash).await?;
+            println!("transaction {tx_hash} changed {} accounts", diffs.len());
+            for diff in diffs {
+                print!("{diff}");
+            }
+        }
+        [address, before, after, slots @ ..] => {
+            // 地址也可以是 ENS 名称
+            let address = EnsResolver::new(provider.clone())
+                .resolve_address(address)
+                .await?;
+            let before: BlockId = before.parse()?;
+            let after: BlockId = after.parse()?;
+            let slots = slots
+                .iter()
+                .map(|slot| slot.parse::<U256>())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let diff = diff_blocks(&provider, address, &slots, before, after).await?;
+            if diff.is_empty() {
+                println!("{address} not changed between {before} and {after}");
+            } else {
+                print!("{diff}");
+            }
+        }
+        _ => {
+            return Err(eyre!(
+                "usage: storage_diff <address or ENS name> <before block> <after block> [slot...] | storage_diff tx <tx hash>"
+            ));
+        }
+    }
+
+    Ok(())
+}
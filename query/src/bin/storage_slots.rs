@@ -0,0 +1,63 @@
+use alloy::{
+    network::EthereumWallet,
+    node_bindings::Anvil,
+    primitives::{Address, U256, address, utils::parse_ether},
+    providers::{Provider, ProviderBuilder},
+    signers::local::PrivateKeySigner,
+};
+use eyre::Result;
+use query::history::IWETH9;
+use query::storage::SlotPath;
+
+const WETH: Address = address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+/// 用 SlotPath 计算 mapping 和嵌套 mapping 的 slot, fork 主网读取 WETH9 的 balanceOf 和 allowance
+///
+/// struct 数组的例子见 get_locks_data 和 get_storage_by_layout
+///
+///   cargo run --bin storage_slots
+#[tokio::main]
+async fn main() -> Result<()> {
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string());
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().fork(rpc_url).try_spawn()?;
+    let pk: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let owner = pk.address();
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::new(pk))
+        .on_http(anvil.endpoint_url());
+
+    let weth = IWETH9::new(WETH, provider.clone());
+    weth.deposit()
+        .value(parse_ether("1")?)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    let spender = Address::with_last_byte(0xbb);
+    weth.approve(spender, U256::from(200))
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+
+    // balanceOf[owner]
+    let balance = SlotPath::new(U256::from(3)).key(owner);
+    let value = provider.get_storage_at(WETH, balance.slot()).await?;
+    println!(
+        "balanceOf[{owner}]: slot {:#x}, value {value}, balanceOf() {}",
+        balance.slot(),
+        weth.balanceOf(owner).call().await?._0
+    );
+
+    // allowance[owner][spender]
+    let allowance = SlotPath::new(U256::from(4)).key(owner).key(spender);
+    let value = provider.get_storage_at(WETH, allowance.slot()).await?;
+    println!(
+        "allowance[{owner}][{spender}]: slot {:#x}, value {value}",
+        allowance.slot()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use alloy::providers::{Provider, ProviderBuilder};
+use eyre::{Result, eyre};
+use query::ens::EnsResolver;
+use query::history::HistoryFetcher;
+
+/// 查询账户最近一段区块内的 ERC-20 转账, 重建余额并和 balanceOf 对比,
+/// stETH 这类 rebasing token 的余额会和计算结果不同
+///
+///   cargo run --bin token_history -- <address or ENS name> [blocks] [token address or ENS name...]
+#[tokio::main]
+async fn main() -> Result<()> {
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string())
+        .parse()?;
+    let provider = ProviderBuilder::new().on_http(rpc_url);
+
+    let mut args = std::env::args().skip(1);
+    let name = args
+        .next()
+        .ok_or_else(|| eyre!("usage: token_history <account> [blocks] [token...]"))?;
+    let blocks: u64 = args
+        .next()
+        .map_or(Ok(10_000), |blocks| blocks.parse())?
+        .max(1);
+
+    // 账户和 token 都可以是 ENS 名称, 例如 steth.lido.eth
+    let ens = EnsResolver::new(provider.clone());
+    let account = ens.resolve_address(&name).await?;
+    let mut tokens = Vec::new();
+    for token in args {
+        tokens.push(ens.resolve_address(&token).await?);
+    }
+    let latest = provider.get_block_number().await?;
+    let history = HistoryFetcher::new(provider, account)
+        .tokens(&tokens)
+        .fetch(latest.saturating_sub(blocks - 1), latest)
+        .await?;
+    println!(
+        "{name} ({account}) blocks {}..={}",
+        history.from_block, history.to_block
+    );
+    for token in &history.tokens {
+        println!(
+            "token {} start {:?} end {:?} computed {}",
+            token.token,
+            token.start_balance,
+            token.end_balance,
+            token.computed_balance()
+        );
+        for (transfer, balance) in token.transfers.iter().zip(&token.balances) {
+            println!(
+                "  block {} {} {} -> {} {} balance {balance}",
+                transfer.block_number, transfer.kind, transfer.from, transfer.to, transfer.amount
+            );
+        }
+        if let Some(diff) = token.mismatch() {
+            println!("  balanceOf differs from computed balance by {diff}");
+        }
+    }
+
+    Ok(())
+}
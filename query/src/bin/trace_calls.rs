@@ -0,0 +1,116 @@
+use alloy::{
+    eips::BlockId,
+    network::EthereumWallet,
+    node_bindings::Anvil,
+    primitives::{Address, U256},
+    providers::ProviderBuilder,
+    signers::local::PrivateKeySigner,
+    sol,
+    sol_types::SolCall,
+};
+use eyre::Result;
+use query::multicall::{MULTICALL3_ADDRESS, ensure_multicall3};
+use query::trace::{TraceDecoder, trace_call, trace_transaction};
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.26; solc Counter.sol --via-ir --optimize --bin
+    #[sol(rpc, abi, bytecode="6080806040523460135760df908160198239f35b600080fdfe6080806040526004361015601257600080fd5b60003560e01c9081633fb5c1cb1460925781638381f58a146079575063d09de08a14603c57600080fd5b3460745760003660031901126074576000546000198114605e57600101600055005b634e487b7160e01b600052601160045260246000fd5b600080fd5b3460745760003660031901126074576020906000548152f35b34607457602036600319011260745760043560005500fea2646970667358221220e978270883b7baed10810c4079c941512e93a7ba1cd1108c781d4bc738d9090564736f6c634300081a0033")]
+    contract Counter {
+        uint256 public number;
+
+        function setNumber(uint256 newNumber) public {
+            number = newNumber;
+        }
+
+        function increment() public {
+            number++;
+        }
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    // Multicall3 中用到的部分, 合约由 ensure_multicall3 部署
+    #[sol(rpc, abi)]
+    contract Multicall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+    }
+}
+
+fn call3(target: Address, call: impl SolCall) -> Multicall3::Call3 {
+    Multicall3::Call3 {
+        target,
+        allowFailure: false,
+        callData: call.abi_encode().into(),
+    }
+}
+
+/// 在 anvil 上通过 Multicall3 调用 Counter, 用 callTracer 获取调用树并根据 ABI 解析
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().try_spawn()?;
+    let pk: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::new(pk))
+        .on_http(anvil.endpoint_url());
+
+    ensure_multicall3(&provider).await?;
+    let counter = Counter::deploy(provider.clone()).await?;
+    let multicall = Multicall3::new(MULTICALL3_ADDRESS, provider.clone());
+    println!("Deployed Counter at {}", counter.address());
+
+    let mut decoder = TraceDecoder::with_common();
+    decoder
+        .add_abi("Counter", &Counter::abi::contract())
+        .add_abi("Multicall3", &Multicall3::abi::contract())
+        .label(*counter.address(), "Counter")
+        .label(MULTICALL3_ADDRESS, "Multicall3");
+
+    // 交易的调用树: aggregate3 依次调用 setNumber, increment, number
+    let receipt = multicall
+        .aggregate3(vec![
+            call3(
+                *counter.address(),
+                Counter::setNumberCall {
+                    newNumber: U256::from(42),
+                },
+            ),
+            call3(*counter.address(), Counter::incrementCall {}),
+            call3(*counter.address(), Counter::numberCall {}),
+        ])
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    let frame = trace_transaction(&provider, receipt.transaction_hash).await?;
+    print!("{}", decoder.decode(&frame));
+
+    // 子调用 Panic(0x11) 溢出, Multicall3 以 Error(string) revert
+    counter
+        .setNumber(U256::MAX)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    let tx = multicall
+        .aggregate3(vec![call3(*counter.address(), Counter::incrementCall {})])
+        .into_transaction_request();
+    let call = decoder.decode(&trace_call(&provider, tx, BlockId::latest()).await?);
+    print!("{call}");
+    println!("revert reason: {:?}", call.revert_reason);
+
+    Ok(())
+}
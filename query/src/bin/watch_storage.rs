@@ -0,0 +1,93 @@
+use std::pin::pin;
+use std::time::Duration;
+
+use alloy::{
+    dyn_abi::DynSolValue,
+    network::EthereumWallet,
+    node_bindings::Anvil,
+    primitives::{B256, U256},
+    providers::{ProviderBuilder, WsConnect},
+    signers::local::PrivateKeySigner,
+    sol,
+};
+use eyre::Result;
+use futures_util::StreamExt;
+use query::storage::StorageLayout;
+use query::watch::{StorageWatcher, WatchKey};
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.26; solc Counter.sol --via-ir --optimize --bin
+    #[sol(rpc, bytecode="6080806040523460135760df908160198239f35b600080fdfe6080806040526004361015601257600080fd5b60003560e01c9081633fb5c1cb1460925781638381f58a146079575063d09de08a14603c57600080fd5b3460745760003660031901126074576000546000198114605e57600101600055005b634e487b7160e01b600052601160045260246000fd5b600080fd5b3460745760003660031901126074576020906000548152f35b34607457602036600319011260745760043560005500fea2646970667358221220e978270883b7baed10810c4079c941512e93a7ba1cd1108c781d4bc738d9090564736f6c634300081a0033")]
+    contract Counter {
+        uint256 public number;
+
+        function setNumber(uint256 newNumber) public {
+            number = newNumber;
+        }
+
+        function increment() public {
+            number++;
+        }
+    }
+}
+
+/// 在 anvil 上修改 Counter 的 number, 分别通过 HTTP 轮询和 WebSocket 订阅监听变化
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Ensure `anvil` is available in $PATH.
+    let anvil = Anvil::new().block_time(1).try_spawn()?;
+    let pk: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::new(pk))
+        .on_http(anvil.endpoint_url());
+
+    let counter = Counter::deploy(provider.clone()).await?;
+    let address = *counter.address();
+    let layout = StorageLayout::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/layout/Counter.json"
+    ))?;
+
+    // HTTP 不支持订阅, 改为轮询新区块
+    let watcher = StorageWatcher::new(provider.clone())
+        .slots(address, &[U256::ZERO])
+        .variables(address, layout.clone(), &["number"])?
+        .poll_interval(Duration::from_millis(200));
+    let mut changes = pin!(watcher.stream().await?);
+    for i in 1..=2u64 {
+        counter.increment().send().await?.get_receipt().await?;
+        // 同一个区块中先输出 slot, 再输出状态变量
+        let slot = changes.next().await.unwrap()?;
+        let variable = changes.next().await.unwrap()?;
+        println!("{slot}\n{variable}");
+        assert_eq!(slot.key, WatchKey::Slot(U256::ZERO));
+        assert_eq!(
+            slot.new,
+            DynSolValue::FixedBytes(B256::from(U256::from(i)), 32)
+        );
+        assert_eq!(variable.key, WatchKey::Variable("number".to_string()));
+        assert_eq!(variable.old, DynSolValue::Uint(U256::from(i - 1), 256));
+        assert_eq!(variable.new, DynSolValue::Uint(U256::from(i), 256));
+        assert_eq!(slot.block_number, variable.block_number);
+    }
+
+    // WebSocket 订阅新区块
+    let ws_provider = ProviderBuilder::new()
+        .on_ws(WsConnect::new(anvil.ws_endpoint()))
+        .await?;
+    let watcher = StorageWatcher::new(ws_provider).variables(address, layout, &["number"])?;
+    let mut changes = pin!(watcher.stream().await?);
+    counter
+        .setNumber(U256::from(42))
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    let change = changes.next().await.unwrap()?;
+    println!("{change}");
+    assert_eq!(change.old, DynSolValue::Uint(U256::from(2), 256));
+    assert_eq!(change.new, DynSolValue::Uint(U256::from(42), 256));
+
+    Ok(())
+}
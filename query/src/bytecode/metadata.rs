@@ -0,0 +1,242 @@
+use alloy::primitives::{B256, Bytes};
+
+/// solc 追加在 runtime bytecode 末尾的 CBOR 编码的 metadata
+///
+/// 格式: <cbor map> <cbor 长度, 2 字节 big-endian>
+/// 参考: https://docs.soliditylang.org/en/latest/metadata.html#encoding-of-the-metadata-hash-in-the-bytecode
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// 编译器和版本, 例如 ("solc", "0.8.26")
+    pub compiler: Option<(String, String)>,
+    /// metadata.json 的 IPFS multihash
+    pub ipfs: Option<Bytes>,
+    /// 旧版本 solc 使用的 Swarm 哈希
+    pub bzzr0: Option<B256>,
+    pub bzzr1: Option<B256>,
+    /// 使用了 experimental 特性, 例如 pragma experimental SMTChecker
+    pub experimental: bool,
+    /// metadata 在 bytecode 中占用的长度, 包括最后 2 字节的长度
+    pub len: usize,
+}
+
+impl Metadata {
+    /// 从 bytecode 末尾解析 metadata, 没有 metadata 时返回 None
+    pub fn decode(code: &[u8]) -> Option<Self> {
+        let n = code.len();
+        if n < 2 {
+            return None;
+        }
+        let cbor_len = u16::from_be_bytes([code[n - 2], code[n - 1]]) as usize;
+        let start = n.checked_sub(2 + cbor_len)?;
+
+        let mut reader = Reader {
+            data: &code[start..n - 2],
+            pos: 0,
+        };
+        let Cbor::Map(entries) = reader.read()? else {
+            return None;
+        };
+        if reader.pos != reader.data.len() {
+            return None;
+        }
+
+        let mut metadata = Self {
+            len: cbor_len + 2,
+            ..Default::default()
+        };
+        for (key, value) in entries {
+            let Cbor::Text(key) = key else {
+                return None;
+            };
+            match (key.as_str(), value) {
+                ("ipfs", Cbor::Bytes(hash)) => metadata.ipfs = Some(hash.into()),
+                ("bzzr0", Cbor::Bytes(hash)) if hash.len() == 32 => {
+                    metadata.bzzr0 = Some(B256::from_slice(&hash))
+                }
+                ("bzzr1", Cbor::Bytes(hash)) if hash.len() == 32 => {
+                    metadata.bzzr1 = Some(B256::from_slice(&hash))
+                }
+                ("experimental", Cbor::Bool(experimental)) => metadata.experimental = experimental,
+                // release 版本为 3 字节, nightly 版本为完整的版本字符串
+                ("solc" | "vyper", version) => {
+                    let version = match version {
+                        Cbor::Bytes(v) => v.iter().map(u8::to_string).collect::<Vec<_>>().join("."),
+                        Cbor::Text(v) => v,
+                        Cbor::Array(v) => v
+                            .iter()
+                            .map(|part| match part {
+                                Cbor::Uint(n) => n.to_string(),
+                                _ => "?".to_string(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join("."),
+                        _ => continue,
+                    };
+                    metadata.compiler = Some((key, version));
+                }
+                _ => {}
+            }
+        }
+        Some(metadata)
+    }
+
+    /// IPFS 哈希的 CIDv0 表示, 例如 Qm...
+    pub fn ipfs_cid(&self) -> Option<String> {
+        self.ipfs
+            .as_ref()
+            .map(|hash| bs58::encode(hash).into_string())
+    }
+}
+
+/// metadata 中用到的 CBOR 类型
+#[derive(Debug)]
+enum Cbor {
+    Uint(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Cbor>),
+    Map(Vec<(Cbor, Cbor)>),
+    Bool(bool),
+    Null,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        let end = self.pos.checked_add(len)?;
+        let bytes = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(bytes)
+    }
+
+    fn uint(&mut self, len: usize) -> Option<u64> {
+        let bytes = self.take(len)?;
+        Some(bytes.iter().fold(0, |acc, b| (acc << 8) | *b as u64))
+    }
+
+    fn read(&mut self) -> Option<Cbor> {
+        let head = *self.take(1)?.first()?;
+        let major = head >> 5;
+        let info = head & 0x1f;
+        if major == 7 {
+            return match info {
+                20 => Some(Cbor::Bool(false)),
+                21 => Some(Cbor::Bool(true)),
+                22 => Some(Cbor::Null),
+                _ => None,
+            };
+        }
+
+        let arg = match info {
+            0..=23 => info as u64,
+            24 => self.uint(1)?,
+            25 => self.uint(2)?,
+            26 => self.uint(4)?,
+            27 => self.uint(8)?,
+            _ => return None,
+        };
+        let len = usize::try_from(arg).ok()?;
+        match major {
+            0 => Some(Cbor::Uint(arg)),
+            2 => Some(Cbor::Bytes(self.take(len)?.to_vec())),
+            3 => Some(Cbor::Text(
+                String::from_utf8(self.take(len)?.to_vec()).ok()?,
+            )),
+            4 => (0..len)
+                .map(|_| self.read())
+                .collect::<Option<_>>()
+                .map(Cbor::Array),
+            5 => (0..len)
+                .map(|_| Some((self.read()?, self.read()?)))
+                .collect::<Option<_>>()
+                .map(Cbor::Map),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{b256, hex};
+
+    use super::*;
+
+    /// 在 code 后面追加 CBOR 和 2 字节长度
+    fn with_metadata(code: &[u8], cbor: &[u8]) -> Vec<u8> {
+        let mut code = code.to_vec();
+        code.extend_from_slice(cbor);
+        code.extend_from_slice(&(cbor.len() as u16).to_be_bytes());
+        code
+    }
+
+    /// solc 0.8.26 的 {"ipfs": bytes(34), "solc": 0x00081a}
+    #[test]
+    fn solc_ipfs() {
+        let code = hex::decode(include_str!("testdata/counter.hex").trim()).unwrap();
+        let metadata = Metadata::decode(&code).unwrap();
+        assert_eq!(
+            metadata.compiler,
+            Some(("solc".to_string(), "0.8.26".to_string()))
+        );
+        assert_eq!(metadata.len, 0x33 + 2);
+        assert_eq!(metadata.ipfs.as_ref().unwrap().len(), 34);
+        assert_eq!(
+            metadata.ipfs_cid().as_deref(),
+            Some("Qme41y1oe9arWyemSus67oah6a4LMWhpaAyokTSoPgSNcC")
+        );
+        assert_eq!(metadata.bzzr1, None);
+        assert!(!metadata.experimental);
+    }
+
+    /// 旧版本的 {"bzzr1": bytes32, "solc": 0x00050b}
+    #[test]
+    fn solc_bzzr1() {
+        let hash = b256!("0x32cb5e746816b7fac95205c068b30da37bd40119a57265be331c162cae747124");
+        let mut cbor = hex::decode("a265627a7a72315820").unwrap();
+        cbor.extend_from_slice(hash.as_slice());
+        cbor.extend_from_slice(&hex::decode("64736f6c634300050b").unwrap());
+        let metadata = Metadata::decode(&with_metadata(&[0x60, 0x80], &cbor)).unwrap();
+        assert_eq!(
+            metadata.compiler,
+            Some(("solc".to_string(), "0.5.11".to_string()))
+        );
+        assert_eq!(metadata.bzzr1, Some(hash));
+        assert_eq!(metadata.ipfs, None);
+        assert_eq!(metadata.len, cbor.len() + 2);
+    }
+
+    /// nightly 版本为字符串, 以及 experimental
+    #[test]
+    fn nightly_experimental() {
+        // {"experimental": true, "solc": "0.8.27-nightly"}
+        let mut cbor = vec![0xa2, 0x6c];
+        cbor.extend_from_slice(b"experimental");
+        cbor.extend_from_slice(&[0xf5, 0x64]);
+        cbor.extend_from_slice(b"solc");
+        cbor.push(0x6e);
+        cbor.extend_from_slice(b"0.8.27-nightly");
+        let metadata = Metadata::decode(&with_metadata(&[0x00], &cbor)).unwrap();
+        assert!(metadata.experimental);
+        assert_eq!(
+            metadata.compiler,
+            Some(("solc".to_string(), "0.8.27-nightly".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_metadata() {
+        // 没有 metadata
+        assert_eq!(Metadata::decode(&[0x60, 0x80, 0x60, 0x40, 0x52]), None);
+        assert_eq!(Metadata::decode(&[0x00]), None);
+        // 长度超过 bytecode
+        assert_eq!(Metadata::decode(&[0xa1, 0x00, 0xff]), None);
+        // map 后面还有多余的数据
+        assert_eq!(Metadata::decode(&with_metadata(&[], &[0xa0, 0x00])), None);
+        // 不是 map
+        assert_eq!(Metadata::decode(&with_metadata(&[], &[0x80])), None);
+    }
+}
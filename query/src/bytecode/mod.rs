@@ -0,0 +1,300 @@
+//! 解析合约的 runtime bytecode
+mod metadata;
+pub mod opcode;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use alloy::primitives::{B256, Bytes, FixedBytes, hex};
+
+pub use metadata::Metadata;
+pub use opcode::Fork;
+
+/// 一条指令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub pc: usize,
+    pub opcode: u8,
+    /// PUSH 的数据, 超出 bytecode 末尾的部分按 0 处理
+    pub push_data: Option<Bytes>,
+}
+
+impl Instruction {
+    /// 未定义的 opcode 显示为 UNKNOWN(0x..)
+    pub fn name(&self) -> String {
+        opcode::name(self.opcode).map_or_else(
+            || format!("UNKNOWN({:#04x})", self.opcode),
+            ToString::to_string,
+        )
+    }
+}
+
+/// 反汇编, 跳过 PUSH 的数据
+pub fn disassemble(code: &[u8]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        let size = opcode::push_size(opcode);
+        let push_data = (size > 0).then(|| {
+            let mut data = vec![0; size];
+            let available = code.len().saturating_sub(pc + 1).min(size);
+            data[..available].copy_from_slice(&code[pc + 1..pc + 1 + available]);
+            Bytes::from(data)
+        });
+        instructions.push(Instruction {
+            pc,
+            opcode,
+            push_data,
+        });
+        pc += 1 + size;
+    }
+    instructions
+}
+
+/// PUSH32 出现的位置, 可能是常量, 也可能是 immutable 变量
+///
+/// 只看 runtime code 无法区分这两种情况, 需要用 [`immutables`] 和创建代码对比
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushWord {
+    pub pc: usize,
+    pub value: B256,
+}
+
+/// bytecode 的解析结果
+#[derive(Debug, Clone)]
+pub struct BytecodeInfo {
+    pub code_size: usize,
+    /// 末尾的 CBOR metadata, 解析指令时不包含这部分
+    pub metadata: Option<Metadata>,
+    /// 函数分发器中匹配到的 selector
+    pub selectors: Vec<FixedBytes<4>>,
+    pub jumpdests: Vec<usize>,
+    /// 硬分叉之后才有的 opcode 及出现次数
+    pub fork_opcodes: BTreeMap<(Fork, &'static str), usize>,
+    /// 所有 PUSH32, 包括 immutable 变量和 32 字节的常量
+    pub push_words: Vec<PushWord>,
+}
+
+impl BytecodeInfo {
+    /// 运行这段 bytecode 最低需要的硬分叉, 例如用到 PUSH0 时需要 Shanghai
+    pub fn min_fork(&self) -> Fork {
+        self.fork_opcodes
+            .keys()
+            .map(|(fork, _)| *fork)
+            .max()
+            .unwrap_or(Fork::Frontier)
+    }
+}
+
+/// 解析 metadata, selector, 硬分叉相关 opcode 和 PUSH32
+pub fn inspect(code: &[u8]) -> BytecodeInfo {
+    let metadata = Metadata::decode(code);
+    let body = &code[..code.len() - metadata.as_ref().map_or(0, |m| m.len)];
+    let instructions = disassemble(body);
+
+    let mut fork_opcodes = BTreeMap::new();
+    let mut jumpdests = Vec::new();
+    let mut push_words = Vec::new();
+    for instruction in &instructions {
+        let fork = opcode::introduced_in(instruction.opcode);
+        if fork > Fork::Frontier {
+            let name = opcode::name(instruction.opcode).unwrap_or("UNKNOWN");
+            *fork_opcodes.entry((fork, name)).or_default() += 1;
+        }
+        match instruction.opcode {
+            opcode::JUMPDEST => jumpdests.push(instruction.pc),
+            opcode::PUSH32 => push_words.push(PushWord {
+                pc: instruction.pc,
+                value: B256::from_slice(instruction.push_data.as_ref().unwrap()),
+            }),
+            _ => {}
+        }
+    }
+
+    BytecodeInfo {
+        code_size: code.len(),
+        metadata,
+        selectors: selectors(&instructions),
+        jumpdests,
+        fork_opcodes,
+        push_words,
+    }
+}
+
+/// 对比创建代码找出 runtime code 中的 immutable 变量
+///
+/// solc 把 runtime code 模板放在创建代码中, immutable 的位置是 PUSH32 0,
+/// constructor 执行时再填入实际的值. 在创建代码中找到除 PUSH32 数据之外都相同的模板,
+/// 模板中为 0 的 PUSH32 就是 immutable. 找不到模板时返回 None, 例如 runtime code
+/// 由 constructor 动态生成
+pub fn immutables(runtime: &[u8], creation: &[u8]) -> Option<Vec<PushWord>> {
+    if runtime.is_empty() {
+        return None;
+    }
+    let words = inspect(runtime).push_words;
+    let mut mask = vec![false; runtime.len()];
+    for word in &words {
+        let end = (word.pc + 33).min(runtime.len());
+        mask[word.pc + 1..end].fill(true);
+    }
+    let template = creation.windows(runtime.len()).find(|template| {
+        template
+            .iter()
+            .zip(runtime)
+            .zip(&mask)
+            .all(|((t, r), masked)| t == r || (*masked && *t == 0))
+    })?;
+    let immutables = words
+        .into_iter()
+        .filter(|word| {
+            let end = (word.pc + 33).min(runtime.len());
+            template[word.pc + 1..end].iter().all(|b| *b == 0)
+        })
+        .collect();
+    Some(immutables)
+}
+
+/// 从函数分发器中提取 selector
+///
+/// solc 生成的分发器为 `DUP1 PUSH4 <selector> EQ PUSH <dest> JUMPI`,
+/// via-ir 或者新版本中也可能是 `PUSH4 <selector> DUPn EQ PUSH <dest> JUMPI`
+///
+/// 开头是 0 字节的 selector 用更短的 PUSH1..PUSH3, 需要在左边补 0,
+/// 例如 `DUP1 PUSH3 0xf55d9d EQ`. 短 PUSH 和常量比较很常见(比如 returndatasize == 0),
+/// 所以只接受前面是 DUPn, 后面直接是 EQ 的形式
+fn selectors(instructions: &[Instruction]) -> Vec<FixedBytes<4>> {
+    let mut selectors = Vec::new();
+    for (i, instruction) in instructions.iter().enumerate() {
+        if !(opcode::PUSH1..=opcode::PUSH4).contains(&instruction.opcode) {
+            continue;
+        }
+        let is_dup = |instruction: &Instruction| {
+            (opcode::DUP1..=opcode::DUP16).contains(&instruction.opcode)
+        };
+        let rest = &instructions[i + 1..];
+        let rest = if instruction.opcode == opcode::PUSH4 {
+            match rest.first() {
+                Some(next) if is_dup(next) => &rest[1..],
+                _ => rest,
+            }
+        } else if i > 0 && is_dup(&instructions[i - 1]) {
+            rest
+        } else {
+            continue;
+        };
+        let is_dispatch = matches!(
+            rest,
+            [eq, push, jumpi, ..]
+                if eq.opcode == opcode::EQ
+                    && opcode::push_size(push.opcode) > 0
+                    && jumpi.opcode == opcode::JUMPI
+        );
+        if is_dispatch {
+            let data = instruction.push_data.as_ref().unwrap();
+            let mut selector = FixedBytes::<4>::ZERO;
+            selector[4 - data.len()..].copy_from_slice(data);
+            if !selectors.contains(&selector) {
+                selectors.push(selector);
+            }
+        }
+    }
+    selectors
+}
+
+/// 反汇编列表, 跳转目标单独一行标出
+pub fn listing(code: &[u8]) -> String {
+    let mut out = String::new();
+    for instruction in disassemble(code) {
+        if instruction.opcode == opcode::JUMPDEST {
+            let _ = writeln!(out, "loc_{:04x}:", instruction.pc);
+        }
+        let _ = write!(out, "  {:04x}  {}", instruction.pc, instruction.name());
+        if let Some(data) = &instruction.push_data {
+            let _ = write!(out, " 0x{}", hex::encode(data));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::fixed_bytes;
+
+    use super::*;
+
+    fn fixture(hex: &str) -> Vec<u8> {
+        hex::decode(hex.trim()).unwrap()
+    }
+
+    /// solc 0.8.26 --via-ir --optimize 编译的 Counter
+    #[test]
+    fn counter_selectors() {
+        let info = inspect(&fixture(include_str!("testdata/counter.hex")));
+        assert_eq!(
+            info.selectors,
+            [
+                fixed_bytes!("0x3fb5c1cb"),
+                fixed_bytes!("0x8381f58a"),
+                fixed_bytes!("0xd09de08a"),
+            ]
+        );
+    }
+
+    /// 主网合约的 runtime code (solc 0.8.9, 来自 alloy 的 trace_replayTransaction 测试数据),
+    /// selector 0x00f55d9d 用 PUSH3 压入
+    #[test]
+    fn short_push_selectors() {
+        let code = fixture(include_str!("testdata/parity_trace.hex"));
+        assert!(hex::encode(&code).contains("8062f55d9d14"));
+
+        let info = inspect(&code);
+        assert_eq!(info.selectors.len(), 2);
+        assert_eq!(info.selectors[0], fixed_bytes!("0x00f55d9d"));
+        assert_eq!(info.selectors[1], fixed_bytes!("0x1cff79cd"));
+    }
+
+    /// PUSH32 常量和 immutable 只能通过创建代码区分
+    #[test]
+    fn immutable_words() {
+        let constant = [0x11; 32];
+        let owner = B256::left_padding_from(&[0xab; 20]);
+        // PUSH32 <constant> PUSH32 <immutable> PUSH1 0 MSTORE STOP
+        let code = |immutable: &[u8]| {
+            let mut code = vec![opcode::PUSH32];
+            code.extend_from_slice(&constant);
+            code.push(opcode::PUSH32);
+            code.extend_from_slice(immutable);
+            code.extend_from_slice(&[0x60, 0x00, 0x52, 0x00]);
+            code
+        };
+        let runtime = code(owner.as_slice());
+
+        let info = inspect(&runtime);
+        assert_eq!(info.push_words.len(), 2);
+
+        // constructor 部分: PUSH1 len DUP1 PUSH1 offset PUSH1 0 CODECOPY PUSH1 0 RETURN, 后面是模板
+        let mut creation = vec![
+            0x60, 0x46, 0x80, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, 0x00, 0xf3, 0xfe,
+        ];
+        creation.extend(code(&[0; 32]));
+        // constructor 参数
+        creation.extend_from_slice(owner.as_slice());
+        assert_eq!(
+            immutables(&runtime, &creation),
+            Some(vec![PushWord {
+                pc: 33,
+                value: owner
+            }])
+        );
+
+        // 常量不同, 不是这段 runtime code 的创建代码
+        let mut other = code(&[0; 32]);
+        other[1] = 0x22;
+        assert_eq!(immutables(&runtime, &other), None);
+
+        let counter = fixture(include_str!("testdata/counter.hex"));
+        assert_eq!(immutables(&counter, &counter), Some(Vec::new()));
+    }
+}
@@ -0,0 +1,234 @@
+//! 查找合约的创建区块, 创建交易和部署者
+use std::fmt;
+
+use alloy::eips::{BlockId, BlockNumberOrTag};
+use alloy::primitives::{Address, B256};
+use alloy::providers::Provider;
+use alloy::rpc::types::trace::geth::CallFrame;
+use alloy::transports::{RpcError, TransportErrorKind};
+use eyre::{Result, eyre};
+
+use crate::trace::trace_block;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreationKind {
+    /// to 为空的部署交易
+    Transaction,
+    /// 合约中的 CREATE
+    Create,
+    /// 合约中的 CREATE2
+    Create2,
+}
+
+impl fmt::Display for CreationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// 创建合约的交易
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreationTx {
+    pub tx_hash: B256,
+    /// 发送交易的账户
+    pub deployer: Address,
+    /// 直接创建合约的账户, 工厂合约创建时为工厂合约, 否则和 deployer 相同
+    pub creator: Address,
+    pub kind: CreationKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractCreation {
+    pub address: Address,
+    pub block_number: u64,
+    /// 创世区块中的合约, 或者工厂合约创建但节点不支持 debug_traceBlockByNumber 时为 None
+    pub tx: Option<CreationTx>,
+}
+
+/// 先找到创建区块, 再从区块中找到创建交易, 需要 archive 节点
+pub async fn find_creation<P: Provider>(
+    provider: &P,
+    address: Address,
+) -> Result<Option<ContractCreation>> {
+    let Some(block_number) = creation_block(provider, address).await? else {
+        return Ok(None);
+    };
+    let tx = match block_number {
+        0 => None,
+        _ => creation_tx(provider, address, block_number).await?,
+    };
+    Ok(Some(ContractCreation {
+        address,
+        block_number,
+        tx,
+    }))
+}
+
+/// 二分查找第一个有 code 的区块, 最新区块没有 code 时为 None
+///
+/// 假设合约创建之后没有被 selfdestruct 再重新部署
+pub async fn creation_block<P: Provider>(provider: &P, address: Address) -> Result<Option<u64>> {
+    let latest = provider.get_block_number().await?;
+    if !has_code(provider, address, latest).await? {
+        return Ok(None);
+    }
+    if has_code(provider, address, 0).await? {
+        return Ok(Some(0));
+    }
+    // code(lo) 为空, code(hi) 不为空
+    let (mut lo, mut hi) = (0, latest);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if has_code(provider, address, mid).await? {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    Ok(Some(hi))
+}
+
+/// 在创建区块中找到创建交易
+///
+/// 部署交易可以从 receipt 的 contractAddress 找到, 工厂合约创建的需要 trace 区块中的交易
+pub async fn creation_tx<P: Provider>(
+    provider: &P,
+    address: Address,
+    block_number: u64,
+) -> Result<Option<CreationTx>> {
+    let block = BlockNumberOrTag::Number(block_number);
+    let receipts = provider
+        .get_block_receipts(block.into())
+        .await?
+        .ok_or_else(|| eyre!("receipts of block {block_number} not found"))?;
+    if let Some(receipt) = receipts
+        .iter()
+        .find(|receipt| receipt.contract_address == Some(address))
+    {
+        return Ok(Some(CreationTx {
+            tx_hash: receipt.transaction_hash,
+            deployer: receipt.from,
+            creator: receipt.from,
+            kind: CreationKind::Transaction,
+        }));
+    }
+
+    // 节点不支持 trace 时只能确定创建区块, 其他错误照常返回
+    let traces = match trace_block(provider, block).await {
+        Ok(traces) => traces,
+        Err(err) if is_unsupported_method(&err) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let creation = receipts.iter().zip(&traces).find_map(|(receipt, trace)| {
+        let frame = find_create(trace, address)?;
+        let kind = match frame.typ.as_str() {
+            "CREATE2" => CreationKind::Create2,
+            _ => CreationKind::Create,
+        };
+        Some(CreationTx {
+            tx_hash: receipt.transaction_hash,
+            deployer: trace.from,
+            creator: frame.from,
+            kind,
+        })
+    });
+    Ok(creation)
+}
+
+/// 节点没有 debug_traceBlockByNumber 的错误
+///
+/// 标准错误码是 -32601, 有些节点用其他错误码, 只在错误信息中说明方法不存在或不支持.
+/// 不匹配单独的 "not available", geth 的 "missing trie node ... is not available" 是状态缺失
+fn is_unsupported_method(err: &eyre::Report) -> bool {
+    let Some(err) = err.downcast_ref::<RpcError<TransportErrorKind>>() else {
+        return false;
+    };
+    let Some(resp) = err.as_error_resp() else {
+        return false;
+    };
+    let message = resp.message.to_lowercase();
+    let explicit = message.contains("method")
+        && (message.contains("not found")
+            || message.contains("does not exist")
+            || message.contains("not supported"));
+    resp.code == -32601 || explicit
+}
+
+async fn has_code<P: Provider>(provider: &P, address: Address, block: u64) -> Result<bool> {
+    let code = provider
+        .get_code_at(address)
+        .block_id(BlockId::number(block))
+        .await?;
+    Ok(!code.is_empty())
+}
+
+/// 创建 address 的 CREATE/CREATE2, 跳过失败的调用及其子调用
+fn find_create(frame: &CallFrame, address: Address) -> Option<&CallFrame> {
+    if frame.error.is_some() {
+        return None;
+    }
+    if matches!(frame.typ.as_str(), "CREATE" | "CREATE2") && frame.to == Some(address) {
+        return Some(frame);
+    }
+    frame
+        .calls
+        .iter()
+        .find_map(|call| find_create(call, address))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::rpc::json_rpc::ErrorPayload;
+
+    use super::*;
+
+    fn error_resp(code: i64, message: &'static str) -> eyre::Report {
+        RpcError::<TransportErrorKind>::ErrorResp(ErrorPayload {
+            code,
+            message: message.into(),
+            data: None,
+        })
+        .into()
+    }
+
+    #[test]
+    fn unsupported_method() {
+        // geth 没有开启 debug namespace
+        assert!(is_unsupported_method(&error_resp(
+            -32601,
+            "the method debug_traceBlockByNumber does not exist/is not available"
+        )));
+        assert!(is_unsupported_method(&error_resp(
+            -32000,
+            "method debug_traceBlockByNumber not supported"
+        )));
+        assert!(is_unsupported_method(&error_resp(
+            -32000,
+            "the method debug_traceBlockByNumber does not exist"
+        )));
+
+        // 非归档节点缺少历史状态, 不是不支持 trace
+        assert!(!is_unsupported_method(&error_resp(
+            -32000,
+            "missing trie node 5e1b4f0a25c2aa4fc1bd4e2f0b5ca8b6b7e7c4f1c9c2f3e4d5a6b7c8d9e0f1a2 (path ) state 0x5e1b4f0a25c2aa4fc1bd4e2f0b5ca8b6b7e7c4f1c9c2f3e4d5a6b7c8d9e0f1a2 is not available"
+        )));
+        assert!(!is_unsupported_method(&error_resp(
+            -32000,
+            "historical state is not available"
+        )));
+
+        // 限流, 超时和连接错误需要返回给调用者
+        assert!(!is_unsupported_method(&error_resp(
+            429,
+            "Too Many Requests"
+        )));
+        assert!(!is_unsupported_method(&error_resp(
+            -32000,
+            "execution timeout"
+        )));
+        assert!(!is_unsupported_method(
+            &TransportErrorKind::custom_str("connection refused").into()
+        ));
+        assert!(!is_unsupported_method(&eyre!("trace transaction failed")));
+    }
+}
@@ -0,0 +1,202 @@
+//! 比较合约在两个区块之间, 或者一笔交易前后的状态
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, B256, Bytes, U256, keccak256};
+use alloy::providers::Provider;
+use alloy::providers::ext::DebugApi;
+use alloy::rpc::types::trace::geth::{
+    AccountState, GethDebugTracingOptions, PreStateConfig, PreStateFrame,
+};
+use eyre::{Result, eyre};
+
+use crate::storage::{BatchSource, SlotSource};
+
+/// 一个值的变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<T> {
+    pub before: T,
+    pub after: T,
+}
+
+impl<T: PartialEq> Change<T> {
+    /// 没有变化时返回 None
+    fn new(before: T, after: T) -> Option<Self> {
+        (before != after).then_some(Self { before, after })
+    }
+}
+
+/// 一个账户变化的部分, 没有变化的字段为 None
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub address: Address,
+    pub balance: Option<Change<U256>>,
+    pub nonce: Option<Change<u64>>,
+    pub code: Option<Change<Bytes>>,
+    pub storage: BTreeMap<U256, Change<U256>>,
+}
+
+impl AccountDiff {
+    pub fn is_empty(&self) -> bool {
+        self.balance.is_none()
+            && self.nonce.is_none()
+            && self.code.is_none()
+            && self.storage.is_empty()
+    }
+}
+
+impl fmt::Display for AccountDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.address)?;
+        if let Some(balance) = &self.balance {
+            writeln!(f, "  balance: {} -> {}", balance.before, balance.after)?;
+        }
+        if let Some(nonce) = &self.nonce {
+            writeln!(f, "  nonce: {} -> {}", nonce.before, nonce.after)?;
+        }
+        if let Some(code) = &self.code {
+            writeln!(
+                f,
+                "  code: {} bytes ({}) -> {} bytes ({})",
+                code.before.len(),
+                keccak256(&code.before),
+                code.after.len(),
+                keccak256(&code.after)
+            )?;
+        }
+        for (slot, change) in &self.storage {
+            writeln!(
+                f,
+                "  slot {slot:#x}: {:#x} -> {:#x}",
+                change.before, change.after
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 比较账户在两个区块的 balance, nonce, code 和指定的 storage slot
+///
+/// storage 不能遍历, 需要指定要比较的 slot, 可以通过 [`crate::storage::SlotPath`] 计算
+pub async fn diff_blocks<P: Provider + Clone>(
+    provider: &P,
+    address: Address,
+    slots: &[U256],
+    before: BlockId,
+    after: BlockId,
+) -> Result<AccountDiff> {
+    let (balance_before, nonce_before, code_before) = account_at(provider, address, before).await?;
+    let (balance_after, nonce_after, code_after) = account_at(provider, address, after).await?;
+
+    let source_before = BatchSource::new(provider.clone(), address, before);
+    let source_after = BatchSource::new(provider.clone(), address, after);
+    source_before.prefetch(slots).await?;
+    source_after.prefetch(slots).await?;
+
+    let mut storage = BTreeMap::new();
+    for slot in slots {
+        let change = Change::new(
+            source_before.storage_at(*slot).await?,
+            source_after.storage_at(*slot).await?,
+        );
+        if let Some(change) = change {
+            storage.insert(*slot, change);
+        }
+    }
+
+    Ok(AccountDiff {
+        address,
+        balance: Change::new(balance_before, balance_after),
+        nonce: Change::new(nonce_before, nonce_after),
+        code: Change::new(code_before, code_after),
+        storage,
+    })
+}
+
+async fn account_at<P: Provider>(
+    provider: &P,
+    address: Address,
+    block: BlockId,
+) -> Result<(U256, u64, Bytes)> {
+    let balance = provider.get_balance(address).block_id(block).await?;
+    let nonce = provider
+        .get_transaction_count(address)
+        .block_id(block)
+        .await?;
+    let code = provider.get_code_at(address).block_id(block).await?;
+    Ok((balance, nonce, code))
+}
+
+/// 使用 debug_traceTransaction 的 prestateTracer diffMode 获取一笔交易修改的所有账户
+pub async fn diff_transaction<P: Provider>(
+    provider: &P,
+    tx_hash: B256,
+) -> Result<Vec<AccountDiff>> {
+    let options = GethDebugTracingOptions::prestate_tracer(PreStateConfig {
+        diff_mode: Some(true),
+        ..Default::default()
+    });
+    let frame = provider
+        .debug_trace_transaction(tx_hash, options)
+        .await?
+        .try_into_pre_state_frame()
+        .map_err(|e| eyre!("unexpected trace result, {e:?}"))?;
+    let PreStateFrame::Diff(diff) = frame else {
+        return Err(eyre!("node did not return prestate diff mode result"));
+    };
+
+    let addresses: BTreeSet<Address> = diff.pre.keys().chain(diff.post.keys()).copied().collect();
+    let diffs = addresses
+        .into_iter()
+        .map(|address| account_diff(address, diff.pre.get(&address), diff.post.get(&address)))
+        .filter(|diff| !diff.is_empty())
+        .collect();
+    Ok(diffs)
+}
+
+/// diffMode 中 post 只包含变化的字段, 被清零的 slot 和被删除的账户不会出现在 post 中
+fn account_diff(
+    address: Address,
+    pre: Option<&AccountState>,
+    post: Option<&AccountState>,
+) -> AccountDiff {
+    let empty = AccountState::default();
+    let pre = pre.unwrap_or(&empty);
+    let deleted = post.is_none();
+    let post = post.unwrap_or(&empty);
+
+    // post 中没有的字段: 账户被删除时为零值, 否则没有变化
+    fn field<T: Clone + Default + PartialEq>(
+        pre: &Option<T>,
+        post: &Option<T>,
+        deleted: bool,
+    ) -> Option<Change<T>> {
+        let before = pre.clone().unwrap_or_default();
+        let after = match post {
+            Some(after) => after.clone(),
+            None if deleted => T::default(),
+            None => return None,
+        };
+        Change::new(before, after)
+    }
+
+    let slots: BTreeSet<&B256> = pre.storage.keys().chain(post.storage.keys()).collect();
+    let storage = slots
+        .into_iter()
+        .filter_map(|slot| {
+            let before = pre.storage.get(slot).copied().unwrap_or_default();
+            let after = post.storage.get(slot).copied().unwrap_or_default();
+            Change::new(U256::from_be_bytes(before.0), U256::from_be_bytes(after.0))
+                .map(|change| (U256::from_be_bytes(slot.0), change))
+        })
+        .collect();
+
+    AccountDiff {
+        address,
+        balance: field(&pre.balance, &post.balance, deleted),
+        nonce: field(&pre.nonce, &post.nonce, deleted),
+        code: field(&pre.code, &post.code, deleted),
+        storage,
+    }
+}
@@ -0,0 +1,182 @@
+//! ENS 名称解析和反向解析
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, B256, Bytes, address, hex, keccak256};
+use alloy::providers::Provider;
+use alloy::sol;
+use eyre::{Result, eyre};
+
+/// 主网和测试网上 ENS registry 的地址都相同
+pub const ENS_REGISTRY_ADDRESS: Address = address!("0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+
+sol! {
+    #[sol(rpc)]
+    interface IEnsRegistry {
+        function owner(bytes32 node) external view returns (address);
+        function resolver(bytes32 node) external view returns (address);
+    }
+
+    #[sol(rpc)]
+    interface IEnsResolver {
+        function addr(bytes32 node) external view returns (address);
+        function text(bytes32 node, string key) external view returns (string);
+        function contenthash(bytes32 node) external view returns (bytes);
+        function name(bytes32 node) external view returns (string);
+    }
+}
+
+/// 名称的 node, 只把名称转成小写, 不做完整的 ENSIP-15 normalize
+pub fn namehash(name: &str) -> B256 {
+    name.to_lowercase()
+        .rsplit('.')
+        .filter(|label| !label.is_empty())
+        .fold(B256::ZERO, |node, label| {
+            keccak256([node.as_slice(), labelhash(label).as_slice()].concat())
+        })
+}
+
+pub fn labelhash(label: &str) -> B256 {
+    keccak256(label.to_lowercase())
+}
+
+/// 反向解析使用的名称, <小写十六进制地址>.addr.reverse
+pub fn reverse_name(address: Address) -> String {
+    format!("{}.addr.reverse", hex::encode(address))
+}
+
+/// 把 contenthash 转成 URI, 支持 IPFS(dag-pb, sha2-256)和 Swarm
+pub fn contenthash_uri(contenthash: &[u8]) -> Option<String> {
+    match contenthash {
+        // ipfs-ns, CIDv1, dag-pb, 转成 CIDv0 Qm...
+        [0xe3, 0x01, 0x01, 0x70, multihash @ ..] if multihash.len() == 34 => {
+            Some(format!("ipfs://{}", bs58::encode(multihash).into_string()))
+        }
+        // swarm-ns, CIDv1, swarm-manifest, keccak256
+        [0xe4, 0x01, 0x01, 0xfa, 0x01, 0x1b, 0x20, hash @ ..] if hash.len() == 32 => {
+            Some(format!("bzz://{}", hex::encode(hash)))
+        }
+        _ => None,
+    }
+}
+
+/// 通过 ENS registry 找到名称的 resolver, 再从 resolver 读取记录
+///
+/// 只查询名称本身的 resolver, 不支持 ENSIP-10 wildcard 和 CCIP-Read
+pub struct EnsResolver<P> {
+    provider: P,
+    registry: Address,
+    block: BlockId,
+}
+
+impl<P: Provider + Clone> EnsResolver<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            registry: ENS_REGISTRY_ADDRESS,
+            block: BlockId::latest(),
+        }
+    }
+
+    /// registry 地址, 默认 [`ENS_REGISTRY_ADDRESS`]
+    pub fn registry(mut self, registry: Address) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// 读取的区块, 默认 latest
+    pub fn block(mut self, block: BlockId) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// 名称的 resolver, 没有设置时为 None
+    pub async fn resolver(&self, name: &str) -> Result<Option<Address>> {
+        let registry = IEnsRegistry::new(self.registry, self.provider.clone());
+        let resolver = registry
+            .resolver(namehash(name))
+            .block(self.block)
+            .call()
+            .await?
+            ._0;
+        Ok((!resolver.is_zero()).then_some(resolver))
+    }
+
+    /// addr 记录
+    pub async fn address(&self, name: &str) -> Result<Option<Address>> {
+        let Some(resolver) = self.resolver_contract(name).await? else {
+            return Ok(None);
+        };
+        let address = resolver
+            .addr(namehash(name))
+            .block(self.block)
+            .call()
+            .await?
+            ._0;
+        Ok((!address.is_zero()).then_some(address))
+    }
+
+    /// text 记录, 例如 url, avatar, com.github
+    pub async fn text(&self, name: &str, key: &str) -> Result<Option<String>> {
+        let Some(resolver) = self.resolver_contract(name).await? else {
+            return Ok(None);
+        };
+        let text = resolver
+            .text(namehash(name), key.to_string())
+            .block(self.block)
+            .call()
+            .await?
+            ._0;
+        Ok((!text.is_empty()).then_some(text))
+    }
+
+    /// contenthash 记录, 可以用 [`contenthash_uri`] 转成 URI
+    pub async fn contenthash(&self, name: &str) -> Result<Option<Bytes>> {
+        let Some(resolver) = self.resolver_contract(name).await? else {
+            return Ok(None);
+        };
+        let contenthash = resolver
+            .contenthash(namehash(name))
+            .block(self.block)
+            .call()
+            .await?
+            ._0;
+        Ok((!contenthash.is_empty()).then_some(contenthash))
+    }
+
+    /// 反向解析, 只有名称正向解析回同一个地址时才返回
+    pub async fn lookup_address(&self, address: Address) -> Result<Option<String>> {
+        let reverse_name = reverse_name(address);
+        let Some(resolver) = self.resolver_contract(&reverse_name).await? else {
+            return Ok(None);
+        };
+        let name = resolver
+            .name(namehash(&reverse_name))
+            .block(self.block)
+            .call()
+            .await?
+            ._0;
+        if name.is_empty() || self.address(&name).await? != Some(address) {
+            return Ok(None);
+        }
+        Ok(Some(name))
+    }
+
+    /// 命令行参数中的地址, 可以是十六进制地址或者 ENS 名称
+    pub async fn resolve_address(&self, address_or_name: &str) -> Result<Address> {
+        if let Ok(address) = address_or_name.parse() {
+            return Ok(address);
+        }
+        self.address(address_or_name)
+            .await?
+            .ok_or_else(|| eyre!("ENS name {address_or_name} does not resolve to an address"))
+    }
+
+    async fn resolver_contract(
+        &self,
+        name: &str,
+    ) -> Result<Option<IEnsResolver::IEnsResolverInstance<(), P>>> {
+        Ok(self
+            .resolver(name)
+            .await?
+            .map(|resolver| IEnsResolver::new(resolver, self.provider.clone())))
+    }
+}
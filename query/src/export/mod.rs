@@ -0,0 +1,409 @@
+//! 把 logs, 区块, 交易和 receipt 导出为 CSV, JSON Lines 或者 Parquet 文件
+mod schema;
+mod writer;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use alloy::eips::{BlockId, BlockNumberOrTag};
+use alloy::providers::Provider;
+use alloy::rpc::types::{Block, Filter, TransactionReceipt};
+use eyre::{Result, bail, eyre};
+use futures_util::{StreamExt, TryStreamExt, pin_mut, stream};
+
+pub use schema::{Cell, Column, ColumnType};
+
+use crate::logs::{EventRegistry, LogFetcher};
+use schema::{
+    BLOCK_COLUMNS, LOG_COLUMNS, RECEIPT_COLUMNS, TRANSACTION_COLUMNS, block_row, log_row,
+    receipt_row, transaction_row,
+};
+use writer::ChunkWriter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dataset {
+    Logs,
+    Blocks,
+    Transactions,
+    Receipts,
+}
+
+impl Dataset {
+    pub const ALL: [Dataset; 4] = [
+        Dataset::Logs,
+        Dataset::Blocks,
+        Dataset::Transactions,
+        Dataset::Receipts,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Logs => "logs",
+            Self::Blocks => "blocks",
+            Self::Transactions => "transactions",
+            Self::Receipts => "receipts",
+        }
+    }
+
+    /// 每个数据集的列是固定的, 不随格式和数据变化
+    pub fn columns(&self) -> &'static [Column] {
+        match self {
+            Self::Logs => LOG_COLUMNS,
+            Self::Blocks => BLOCK_COLUMNS,
+            Self::Transactions => TRANSACTION_COLUMNS,
+            Self::Receipts => RECEIPT_COLUMNS,
+        }
+    }
+}
+
+impl fmt::Display for Dataset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Dataset {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|dataset| dataset.name() == s)
+            .ok_or_else(|| {
+                eyre!("unknown dataset {s}, expected logs, blocks, transactions or receipts")
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Jsonl,
+    Parquet,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "jsonl" | "json" => Ok(Self::Jsonl),
+            "parquet" => Ok(Self::Parquet),
+            _ => Err(eyre!("unknown format {s}, expected csv, jsonl or parquet")),
+        }
+    }
+}
+
+/// 导出结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    /// 本次写入的文件
+    pub files: Vec<PathBuf>,
+    /// 已经存在而跳过的文件
+    pub skipped: Vec<PathBuf>,
+    /// 只覆盖部分区块, 已经合并到新文件中而删除的文件
+    pub replaced: Vec<PathBuf>,
+    /// 本次写入的行数
+    pub rows: BTreeMap<Dataset, u64>,
+}
+
+/// 按区块分段导出, 每个数据集的每个分段写入一个文件:
+/// `<out_dir>/<dataset>/<dataset>_<from>_<to>.<ext>`
+///
+/// 分段按 chunk_blocks 对齐, 文件写完后才重命名为最终的文件名,
+/// 重新运行时跳过已经覆盖所需区块的文件, 从失败的分段继续;
+/// 同一个对齐分段中只覆盖部分区块的文件(例如上次的 toBlock 更小)会合并到新文件中再删除.
+/// 写入时按行输出, parquet 每 8192 行写入一个 RecordBatch, 内存占用不随分段大小增长
+pub struct Exporter<P> {
+    provider: P,
+    out_dir: PathBuf,
+    format: Format,
+    datasets: Vec<Dataset>,
+    filter: Filter,
+    registry: Option<EventRegistry>,
+    chunk_blocks: u64,
+    concurrency: usize,
+}
+
+impl<P: Provider + Clone> Exporter<P> {
+    pub fn new(provider: P, out_dir: impl Into<PathBuf>, format: Format) -> Self {
+        Self {
+            provider,
+            out_dir: out_dir.into(),
+            format,
+            datasets: vec![Dataset::Logs],
+            filter: Filter::new(),
+            registry: Some(EventRegistry::with_common()),
+            chunk_blocks: 1000,
+            concurrency: 8,
+        }
+    }
+
+    /// 导出的数据集, 默认只导出 logs
+    pub fn datasets(mut self, datasets: &[Dataset]) -> Self {
+        self.datasets = datasets.to_vec();
+        self.datasets.sort();
+        self.datasets.dedup();
+        self
+    }
+
+    /// logs 的地址和 topics 条件, 区块范围由 export 的参数决定
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// 用于解析 logs 的事件, 默认 [`EventRegistry::with_common`], None 时不解析
+    pub fn registry(mut self, registry: Option<EventRegistry>) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// 每个文件包含的区块数量, 默认 1000
+    pub fn chunk_blocks(mut self, chunk_blocks: u64) -> Self {
+        self.chunk_blocks = chunk_blocks.max(1);
+        self
+    }
+
+    /// 同时查询的区块数量, 默认 8
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 分段文件的路径
+    pub fn chunk_path(&self, dataset: Dataset, from_block: u64, to_block: u64) -> PathBuf {
+        self.out_dir.join(dataset.name()).join(format!(
+            "{dataset}_{from_block:010}_{to_block:010}.{}",
+            self.format.extension()
+        ))
+    }
+
+    /// 导出 filter 中的区块范围
+    pub async fn export_filter(&self, filter: &Filter) -> Result<ExportSummary> {
+        let (Some(from_block), Some(to_block)) = (filter.get_from_block(), filter.get_to_block())
+        else {
+            bail!("filter fromBlock and toBlock must be block numbers");
+        };
+        self.export(from_block, to_block).await
+    }
+
+    /// 导出 [from_block, to_block] 之间的数据
+    pub async fn export(&self, from_block: u64, to_block: u64) -> Result<ExportSummary> {
+        if from_block > to_block {
+            bail!("fromBlock {from_block} > toBlock {to_block}");
+        }
+        for dataset in &self.datasets {
+            std::fs::create_dir_all(self.out_dir.join(dataset.name()))?;
+        }
+
+        let mut summary = ExportSummary::default();
+        let mut start = from_block;
+        while start <= to_block {
+            let chunk_start = start / self.chunk_blocks * self.chunk_blocks;
+            let chunk_end = chunk_start + self.chunk_blocks - 1;
+            let end = chunk_end.min(to_block);
+
+            // 需要写入的数据集, 以及同一个对齐分段中需要合并的旧文件,
+            // 每个数据集的旧文件不同, 合并之后的区块范围也分别计算
+            let mut pending = Vec::new();
+            for dataset in &self.datasets {
+                let existing = self.chunk_files(*dataset, chunk_start, chunk_end)?;
+                if let Some((_, _, path)) = existing
+                    .iter()
+                    .find(|(from, to, _)| *from <= start && *to >= end)
+                {
+                    summary.skipped.push(path.clone());
+                    continue;
+                }
+                let (mut range_start, mut range_end) = (start, end);
+                for (from, to, _) in &existing {
+                    range_start = range_start.min(*from);
+                    range_end = range_end.max(*to);
+                }
+                let old = existing.into_iter().map(|(_, _, path)| path).collect();
+                pending.push((*dataset, range_start, range_end, old));
+            }
+
+            // 区块范围相同的 blocks, transactions 和 receipts 共用一次区块查询
+            let mut block_datasets: BTreeMap<_, Vec<_>> = BTreeMap::new();
+            for (dataset, range_start, range_end, old) in pending {
+                if dataset == Dataset::Logs {
+                    self.export_logs(range_start, range_end, old, &mut summary)
+                        .await?;
+                } else {
+                    block_datasets
+                        .entry((range_start, range_end))
+                        .or_default()
+                        .push((dataset, old));
+                }
+            }
+            for ((range_start, range_end), datasets) in block_datasets {
+                self.export_blocks(range_start, range_end, datasets, &mut summary)
+                    .await?;
+            }
+
+            start = end + 1;
+        }
+        Ok(summary)
+    }
+
+    /// 对齐分段 [chunk_start, chunk_end] 中已经存在的文件, 以及文件包含的区块范围
+    fn chunk_files(
+        &self,
+        dataset: Dataset,
+        chunk_start: u64,
+        chunk_end: u64,
+    ) -> Result<Vec<(u64, u64, PathBuf)>> {
+        let prefix = format!("{dataset}_");
+        let suffix = format!(".{}", self.format.extension());
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(self.out_dir.join(dataset.name()))? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            // 未完成的 .tmp 文件不匹配后缀
+            let range = name
+                .strip_prefix(&prefix)
+                .and_then(|name| name.strip_suffix(&suffix))
+                .and_then(|name| name.split_once('_'))
+                .and_then(|(from, to)| Some((from.parse::<u64>().ok()?, to.parse::<u64>().ok()?)));
+            if let Some((from, to)) = range
+                && from >= chunk_start
+                && to <= chunk_end
+            {
+                files.push((from, to, path));
+            }
+        }
+        Ok(files)
+    }
+
+    async fn export_logs(
+        &self,
+        start: u64,
+        end: u64,
+        old: Vec<PathBuf>,
+        summary: &mut ExportSummary,
+    ) -> Result<()> {
+        let path = self.chunk_path(Dataset::Logs, start, end);
+        let mut writer = ChunkWriter::create(&path, self.format, LOG_COLUMNS)?;
+
+        let filter = self.filter.clone().from_block(start).to_block(end);
+        let fetcher = LogFetcher::new(self.provider.clone(), filter)?;
+        let logs = fetcher.stream();
+        pin_mut!(logs);
+        while let Some(log) = logs.next().await {
+            writer.write_row(log_row(&log?, self.registry.as_ref()))?;
+        }
+
+        self.finish(Dataset::Logs, writer, path, old, summary)
+    }
+
+    async fn export_blocks(
+        &self,
+        start: u64,
+        end: u64,
+        datasets: Vec<(Dataset, Vec<PathBuf>)>,
+        summary: &mut ExportSummary,
+    ) -> Result<()> {
+        let mut writers = datasets
+            .into_iter()
+            .map(|(dataset, old)| {
+                let path = self.chunk_path(dataset, start, end);
+                let writer = ChunkWriter::create(&path, self.format, dataset.columns())?;
+                Ok((dataset, writer, path, old))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let full = writers
+            .iter()
+            .any(|(dataset, ..)| *dataset == Dataset::Transactions);
+        let with_receipts = writers
+            .iter()
+            .any(|(dataset, ..)| *dataset == Dataset::Receipts);
+        // 按区块顺序输出, 同时最多查询 concurrency 个区块
+        let blocks = stream::iter(start..=end)
+            .map(|number| self.fetch_block(number, full, with_receipts))
+            .buffered(self.concurrency);
+        pin_mut!(blocks);
+        while let Some((block, receipts)) = blocks.try_next().await? {
+            for (dataset, writer, ..) in &mut writers {
+                match dataset {
+                    Dataset::Blocks => writer.write_row(block_row(&block))?,
+                    Dataset::Transactions => {
+                        for tx in block.transactions.txns() {
+                            writer.write_row(transaction_row(tx))?;
+                        }
+                    }
+                    Dataset::Receipts => {
+                        for receipt in &receipts {
+                            writer.write_row(receipt_row(receipt))?;
+                        }
+                    }
+                    Dataset::Logs => unreachable!("logs are exported by export_logs"),
+                }
+            }
+        }
+
+        for (dataset, writer, path, old) in writers {
+            self.finish(dataset, writer, path, old, summary)?;
+        }
+        Ok(())
+    }
+
+    async fn fetch_block(
+        &self,
+        number: u64,
+        full: bool,
+        with_receipts: bool,
+    ) -> Result<(Block, Vec<TransactionReceipt>)> {
+        let request = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(number));
+        let block = if full { request.full() } else { request }
+            .await?
+            .ok_or_else(|| eyre!("block {number} not found"))?;
+        let receipts = if with_receipts {
+            self.provider
+                .get_block_receipts(BlockId::number(number))
+                .await?
+                .ok_or_else(|| eyre!("receipts of block {number} not found"))?
+        } else {
+            Vec::new()
+        };
+        Ok((block, receipts))
+    }
+
+    fn finish(
+        &self,
+        dataset: Dataset,
+        writer: ChunkWriter,
+        path: PathBuf,
+        old: Vec<PathBuf>,
+        summary: &mut ExportSummary,
+    ) -> Result<()> {
+        let rows = writer.finish()?;
+        *summary.rows.entry(dataset).or_default() += rows;
+        // 新文件完整写入之后再删除被合并的旧文件
+        for old in old.into_iter().filter(|old| *old != path) {
+            std::fs::remove_file(&old)?;
+            summary.replaced.push(old);
+        }
+        summary.files.push(path);
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+use alloy::consensus::{Transaction as ConsensusTransaction, Typed2718};
+use alloy::network::TransactionResponse;
+use alloy::primitives::hex;
+use alloy::rpc::types::{Block, Log, Transaction, TransactionReceipt};
+use serde_json::Value;
+
+use crate::logs::EventRegistry;
+
+/// 列的类型, 所有格式使用相同的 schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    UInt64,
+    Bool,
+    /// 地址, 哈希和 bytes 是 0x 开头的十六进制, 超过 u64 的整数是十进制字符串
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column {
+    pub name: &'static str,
+    pub ty: ColumnType,
+}
+
+const fn uint(name: &'static str) -> Column {
+    Column {
+        name,
+        ty: ColumnType::UInt64,
+    }
+}
+
+const fn boolean(name: &'static str) -> Column {
+    Column {
+        name,
+        ty: ColumnType::Bool,
+    }
+}
+
+const fn text(name: &'static str) -> Column {
+    Column {
+        name,
+        ty: ColumnType::Text,
+    }
+}
+
+/// 一个单元格, None 表示 null
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cell {
+    UInt64(Option<u64>),
+    Bool(Option<bool>),
+    Text(Option<String>),
+}
+
+impl Cell {
+    fn text(value: impl ToString) -> Self {
+        Self::Text(Some(value.to_string()))
+    }
+
+    fn hex(bytes: impl AsRef<[u8]>) -> Self {
+        Self::Text(Some(hex::encode_prefixed(bytes)))
+    }
+
+    /// CSV 中 null 为空字符串
+    pub fn to_csv(&self) -> String {
+        match self {
+            Self::UInt64(v) => v.map(|v| v.to_string()).unwrap_or_default(),
+            Self::Bool(v) => v.map(|v| v.to_string()).unwrap_or_default(),
+            Self::Text(v) => v.clone().unwrap_or_default(),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::UInt64(v) => v.map_or(Value::Null, Value::from),
+            Self::Bool(v) => v.map_or(Value::Null, Value::from),
+            Self::Text(v) => v.clone().map_or(Value::Null, Value::from),
+        }
+    }
+}
+
+pub(super) const LOG_COLUMNS: &[Column] = &[
+    uint("block_number"),
+    text("block_hash"),
+    text("transaction_hash"),
+    uint("transaction_index"),
+    uint("log_index"),
+    text("address"),
+    text("topic0"),
+    text("topic1"),
+    text("topic2"),
+    text("topic3"),
+    text("data"),
+    boolean("removed"),
+    // EventRegistry 解析出来的事件, 无法解析时为 null
+    text("event_source"),
+    text("event_signature"),
+    text("decoded"),
+];
+
+pub(super) const BLOCK_COLUMNS: &[Column] = &[
+    uint("number"),
+    text("hash"),
+    text("parent_hash"),
+    uint("timestamp"),
+    text("miner"),
+    text("state_root"),
+    uint("gas_limit"),
+    uint("gas_used"),
+    uint("base_fee_per_gas"),
+    uint("blob_gas_used"),
+    uint("transaction_count"),
+];
+
+pub(super) const TRANSACTION_COLUMNS: &[Column] = &[
+    uint("block_number"),
+    text("block_hash"),
+    uint("transaction_index"),
+    text("hash"),
+    uint("type"),
+    text("from"),
+    text("to"),
+    uint("nonce"),
+    text("value"),
+    uint("gas_limit"),
+    text("gas_price"),
+    text("max_fee_per_gas"),
+    text("max_priority_fee_per_gas"),
+    text("input"),
+];
+
+pub(super) const RECEIPT_COLUMNS: &[Column] = &[
+    uint("block_number"),
+    text("block_hash"),
+    uint("transaction_index"),
+    text("transaction_hash"),
+    boolean("status"),
+    uint("gas_used"),
+    uint("cumulative_gas_used"),
+    text("effective_gas_price"),
+    uint("blob_gas_used"),
+    text("contract_address"),
+    uint("log_count"),
+];
+
+pub(super) fn log_row(log: &Log, registry: Option<&EventRegistry>) -> Vec<Cell> {
+    let topic = |i: usize| Cell::Text(log.topics().get(i).map(|topic| topic.to_string()));
+    let decoded = registry.and_then(|registry| registry.decode(log.data()));
+    vec![
+        Cell::UInt64(log.block_number),
+        Cell::Text(log.block_hash.map(|hash| hash.to_string())),
+        Cell::Text(log.transaction_hash.map(|hash| hash.to_string())),
+        Cell::UInt64(log.transaction_index),
+        Cell::UInt64(log.log_index),
+        Cell::text(log.address()),
+        topic(0),
+        topic(1),
+        topic(2),
+        topic(3),
+        Cell::hex(&log.data().data),
+        Cell::Bool(Some(log.removed)),
+        Cell::Text(decoded.as_ref().map(|decoded| decoded.source.to_string())),
+        Cell::Text(
+            decoded
+                .as_ref()
+                .map(|decoded| decoded.event.full_signature()),
+        ),
+        Cell::Text(decoded.map(|decoded| decoded.to_json().to_string())),
+    ]
+}
+
+pub(super) fn block_row(block: &Block) -> Vec<Cell> {
+    let header = &block.header;
+    vec![
+        Cell::UInt64(Some(header.number)),
+        Cell::text(header.hash),
+        Cell::text(header.parent_hash),
+        Cell::UInt64(Some(header.timestamp)),
+        Cell::text(header.beneficiary),
+        Cell::text(header.state_root),
+        Cell::UInt64(Some(header.gas_limit)),
+        Cell::UInt64(Some(header.gas_used)),
+        Cell::UInt64(header.base_fee_per_gas),
+        Cell::UInt64(header.blob_gas_used),
+        Cell::UInt64(Some(block.transactions.len() as u64)),
+    ]
+}
+
+pub(super) fn transaction_row(tx: &Transaction) -> Vec<Cell> {
+    vec![
+        Cell::UInt64(tx.block_number),
+        Cell::Text(tx.block_hash.map(|hash| hash.to_string())),
+        Cell::UInt64(tx.transaction_index),
+        Cell::text(tx.tx_hash()),
+        Cell::UInt64(Some(tx.ty().into())),
+        Cell::text(TransactionResponse::from(tx)),
+        Cell::Text(tx.to().map(|to| to.to_string())),
+        Cell::UInt64(Some(tx.nonce())),
+        Cell::text(tx.value()),
+        Cell::UInt64(Some(tx.gas_limit())),
+        Cell::Text(ConsensusTransaction::gas_price(tx).map(|price| price.to_string())),
+        Cell::Text(
+            tx.is_dynamic_fee()
+                .then(|| ConsensusTransaction::max_fee_per_gas(tx).to_string()),
+        ),
+        Cell::Text(tx.max_priority_fee_per_gas().map(|price| price.to_string())),
+        Cell::hex(tx.input()),
+    ]
+}
+
+pub(super) fn receipt_row(receipt: &TransactionReceipt) -> Vec<Cell> {
+    vec![
+        Cell::UInt64(receipt.block_number),
+        Cell::Text(receipt.block_hash.map(|hash| hash.to_string())),
+        Cell::UInt64(receipt.transaction_index),
+        Cell::text(receipt.transaction_hash),
+        Cell::Bool(Some(receipt.status())),
+        Cell::UInt64(Some(receipt.gas_used)),
+        Cell::UInt64(Some(receipt.inner.cumulative_gas_used())),
+        Cell::text(receipt.effective_gas_price),
+        Cell::UInt64(receipt.blob_gas_used),
+        Cell::Text(receipt.contract_address.map(|address| address.to_string())),
+        Cell::UInt64(Some(receipt.inner.logs().len() as u64)),
+    ]
+}
@@ -0,0 +1,155 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use eyre::Result;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::{Map, Value};
+
+use super::Format;
+use super::schema::{Cell, Column, ColumnType};
+
+/// 写入一个分段文件, 先写到 .tmp 文件, finish 时再重命名, 保证存在的文件都是完整的
+pub(super) struct ChunkWriter {
+    tmp_path: PathBuf,
+    path: PathBuf,
+    rows: u64,
+    inner: Inner,
+}
+
+/// parquet 每个 RecordBatch 的行数, 内存中最多保存这么多行
+const PARQUET_BATCH_ROWS: usize = 8192;
+
+enum Inner {
+    Csv(Box<csv::Writer<BufWriter<File>>>),
+    Jsonl(&'static [Column], BufWriter<File>),
+    /// parquet 按列写入, 每 PARQUET_BATCH_ROWS 行写一个 RecordBatch
+    Parquet(&'static [Column], Box<ArrowWriter<File>>, Vec<Vec<Cell>>),
+}
+
+impl ChunkWriter {
+    pub(super) fn create(path: &Path, format: Format, columns: &'static [Column]) -> Result<Self> {
+        let tmp_path = path.with_extension(format!("{}.tmp", format.extension()));
+        let file = File::create(&tmp_path)?;
+        let inner = match format {
+            Format::Csv => {
+                let mut writer = csv::Writer::from_writer(BufWriter::new(file));
+                writer.write_record(columns.iter().map(|column| column.name))?;
+                Inner::Csv(Box::new(writer))
+            }
+            Format::Jsonl => Inner::Jsonl(columns, BufWriter::new(file)),
+            Format::Parquet => {
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer = ArrowWriter::try_new(file, parquet_schema(columns), Some(props))?;
+                Inner::Parquet(columns, Box::new(writer), Vec::new())
+            }
+        };
+        Ok(Self {
+            tmp_path,
+            path: path.to_path_buf(),
+            rows: 0,
+            inner,
+        })
+    }
+
+    pub(super) fn write_row(&mut self, row: Vec<Cell>) -> Result<()> {
+        match &mut self.inner {
+            Inner::Csv(writer) => writer.write_record(row.iter().map(Cell::to_csv))?,
+            Inner::Jsonl(columns, writer) => {
+                let object = columns
+                    .iter()
+                    .zip(&row)
+                    .map(|(column, cell)| (column.name.to_string(), cell.to_json()))
+                    .collect::<Map<_, _>>();
+                serde_json::to_writer(&mut *writer, &Value::Object(object))?;
+                writer.write_all(b"\n")?;
+            }
+            Inner::Parquet(columns, writer, rows) => {
+                rows.push(row);
+                if rows.len() == PARQUET_BATCH_ROWS {
+                    write_batch(writer, columns, rows)?;
+                }
+            }
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// 写完并重命名为最终的文件名, 返回行数
+    pub(super) fn finish(self) -> Result<u64> {
+        match self.inner {
+            Inner::Csv(mut writer) => writer.flush()?,
+            Inner::Jsonl(_, mut writer) => writer.flush()?,
+            Inner::Parquet(columns, mut writer, mut rows) => {
+                if !rows.is_empty() {
+                    write_batch(&mut writer, columns, &mut rows)?;
+                }
+                writer.close()?;
+            }
+        }
+        std::fs::rename(&self.tmp_path, &self.path)?;
+        Ok(self.rows)
+    }
+}
+
+fn parquet_schema(columns: &[Column]) -> SchemaRef {
+    Arc::new(Schema::new(
+        columns
+            .iter()
+            .map(|column| {
+                let data_type = match column.ty {
+                    ColumnType::UInt64 => DataType::UInt64,
+                    ColumnType::Bool => DataType::Boolean,
+                    ColumnType::Text => DataType::Utf8,
+                };
+                Field::new(column.name, data_type, true)
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// 把缓存的行转换为列写入一个 RecordBatch, 并清空缓存
+fn write_batch(
+    writer: &mut ArrowWriter<File>,
+    columns: &[Column],
+    rows: &mut Vec<Vec<Cell>>,
+) -> Result<()> {
+    let arrays = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| -> ArrayRef {
+            let cells = rows.iter().map(|row| &row[i]);
+            match column.ty {
+                ColumnType::UInt64 => {
+                    Arc::new(UInt64Array::from_iter(cells.map(|cell| match cell {
+                        Cell::UInt64(v) => *v,
+                        _ => None,
+                    })))
+                }
+                ColumnType::Bool => {
+                    Arc::new(BooleanArray::from_iter(cells.map(|cell| match cell {
+                        Cell::Bool(v) => *v,
+                        _ => None,
+                    })))
+                }
+                ColumnType::Text => {
+                    Arc::new(StringArray::from_iter(cells.map(|cell| match cell {
+                        Cell::Text(v) => v.clone(),
+                        _ => None,
+                    })))
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    rows.clear();
+
+    writer.write(&RecordBatch::try_new(parquet_schema(columns), arrays)?)?;
+    Ok(())
+}
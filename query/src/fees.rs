@@ -0,0 +1,238 @@
+//! 根据 eth_feeHistory 估算 slow/standard/fast 三档的 gas 价格
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::Provider;
+use alloy::rpc::types::FeeHistory;
+use eyre::{Result, eyre};
+use futures_util::{Stream, StreamExt, stream};
+
+/// EIP-1559 每个区块 base fee 最多上涨 12.5%
+const BASE_FEE_MAX_CHANGE: f64 = 1.125;
+
+/// 一档的建议价格, 单位 wei
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeLevel {
+    pub base_fee: u128,
+    pub priority_fee: u128,
+    /// 节点不支持 blob(Cancun 之前)时为 None
+    pub blob_base_fee: Option<u128>,
+}
+
+impl FeeLevel {
+    /// 交易的 maxFeePerGas
+    pub fn max_fee_per_gas(&self) -> u128 {
+        self.base_fee + self.priority_fee
+    }
+}
+
+/// 基于某个区块之前的 fee history 的估算结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimate {
+    /// fee history 中最新的区块
+    pub block_number: u64,
+    /// 下一个区块的 base fee
+    pub next_base_fee: u128,
+    pub next_blob_base_fee: Option<u128>,
+    pub slow: FeeLevel,
+    pub standard: FeeLevel,
+    pub fast: FeeLevel,
+}
+
+/// 价格上限, 估算结果超过上限时使用上限
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeeCeilings {
+    pub base_fee: Option<u128>,
+    pub priority_fee: Option<u128>,
+    pub blob_base_fee: Option<u128>,
+}
+
+/// 采样最近若干区块的 eth_feeHistory
+///
+/// - priority fee: 每档对应一个 reward 百分位, 按区块从旧到新做 EMA, 跳过空区块
+/// - base fee 和 blob base fee: 下一个区块的值, standard 和 fast 分别预留 1 个和 2 个区块的最大涨幅
+pub struct FeeOracle<P> {
+    provider: P,
+    block_count: u64,
+    percentiles: [f64; 3],
+    ema_alpha: f64,
+    ceilings: FeeCeilings,
+}
+
+impl<P: Provider> FeeOracle<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            block_count: 20,
+            percentiles: [10.0, 50.0, 90.0],
+            ema_alpha: 0.3,
+            ceilings: FeeCeilings::default(),
+        }
+    }
+
+    /// 采样的区块数量, 默认 20
+    pub fn block_count(mut self, block_count: u64) -> Self {
+        self.block_count = block_count.max(1);
+        self
+    }
+
+    /// slow/standard/fast 使用的 reward 百分位, 默认 10/50/90
+    pub fn percentiles(mut self, slow: f64, standard: f64, fast: f64) -> Self {
+        self.percentiles = [slow, standard, fast];
+        self
+    }
+
+    /// EMA 中最新区块的权重, 0 到 1 之间, 默认 0.3, 越大越接近最新区块
+    pub fn ema_alpha(mut self, ema_alpha: f64) -> Self {
+        self.ema_alpha = ema_alpha.clamp(f64::EPSILON, 1.0);
+        self
+    }
+
+    pub fn ceilings(mut self, ceilings: FeeCeilings) -> Self {
+        self.ceilings = ceilings;
+        self
+    }
+
+    /// 基于最新区块估算
+    pub async fn estimate(&self) -> Result<FeeEstimate> {
+        self.estimate_at(BlockNumberOrTag::Latest).await
+    }
+
+    /// 基于指定区块及之前的区块估算
+    pub async fn estimate_at(&self, block: BlockNumberOrTag) -> Result<FeeEstimate> {
+        let history = self
+            .provider
+            .get_fee_history(self.block_count, block, &self.percentiles)
+            .await?;
+        self.estimate_from_history(&history)
+    }
+
+    /// 从 fee history 计算, reward 的百分位顺序必须和 [`FeeOracle::percentiles`] 相同
+    pub fn estimate_from_history(&self, history: &FeeHistory) -> Result<FeeEstimate> {
+        let next_base_fee = history
+            .next_block_base_fee()
+            .ok_or_else(|| eyre!("fee history without base fee"))?;
+        let next_blob_base_fee = history.next_block_blob_base_fee().filter(|fee| *fee > 0);
+        let block_number = (history.oldest_block + history.gas_used_ratio.len() as u64)
+            .checked_sub(1)
+            .ok_or_else(|| eyre!("empty fee history"))?;
+
+        let rewards = history.reward.as_deref().unwrap_or_default();
+        let level = |i: usize| {
+            // 空区块的 reward 都是 0, 不参与计算
+            let samples = rewards
+                .iter()
+                .zip(&history.gas_used_ratio)
+                .filter(|(_, ratio)| **ratio > 0.0)
+                .filter_map(|(reward, _)| reward.get(i).copied());
+            let priority_fee = ema(samples, self.ema_alpha).unwrap_or_default();
+            let headroom = BASE_FEE_MAX_CHANGE.powi(i as i32);
+            FeeLevel {
+                base_fee: cap(scale(next_base_fee, headroom), self.ceilings.base_fee),
+                priority_fee: cap(priority_fee, self.ceilings.priority_fee),
+                blob_base_fee: next_blob_base_fee
+                    .map(|fee| cap(scale(fee, headroom), self.ceilings.blob_base_fee)),
+            }
+        };
+
+        Ok(FeeEstimate {
+            block_number,
+            next_base_fee,
+            next_blob_base_fee,
+            slow: level(0),
+            standard: level(1),
+            fast: level(2),
+        })
+    }
+}
+
+impl<P: Provider + Clone> FeeOracle<P> {
+    /// 每个新区块更新一次估算, 通过 HTTP 轮询新区块
+    pub async fn stream(&self) -> Result<impl Stream<Item = Result<FeeEstimate>> + '_> {
+        let poller = self.provider.watch_blocks().await?;
+        let estimates =
+            poller
+                .into_stream()
+                .flat_map(stream::iter)
+                .then(move |block_hash| async move {
+                    let block = self
+                        .provider
+                        .get_block_by_hash(block_hash)
+                        .await?
+                        .ok_or_else(|| eyre!("block {block_hash} not found"))?;
+                    self.estimate_at(BlockNumberOrTag::Number(block.header.number))
+                        .await
+                });
+        Ok(estimates)
+    }
+}
+
+/// 指数移动平均, 按从旧到新的顺序
+fn ema(samples: impl IntoIterator<Item = u128>, alpha: f64) -> Option<u128> {
+    samples
+        .into_iter()
+        .map(|sample| sample as f64)
+        .reduce(|ema, sample| alpha * sample + (1.0 - alpha) * ema)
+        .map(|ema| ema.round() as u128)
+}
+
+fn scale(fee: u128, factor: f64) -> u128 {
+    (fee as f64 * factor).ceil() as u128
+}
+
+fn cap(fee: u128, ceiling: Option<u128>) -> u128 {
+    ceiling.map_or(fee, |ceiling| fee.min(ceiling))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::providers::ProviderBuilder;
+
+    use super::*;
+
+    const GWEI: u128 = 1_000_000_000;
+
+    /// 用构造的 fee history 检查 EMA, 空区块和上限
+    #[test]
+    fn estimate_from_history() -> Result<()> {
+        let history = FeeHistory {
+            oldest_block: 100,
+            // 最后一个是下一个区块的 base fee
+            base_fee_per_gas: vec![10 * GWEI, 11 * GWEI, 12 * GWEI, 16 * GWEI],
+            gas_used_ratio: vec![0.5, 0.0, 0.9],
+            base_fee_per_blob_gas: vec![1, 1, 1, 2],
+            blob_gas_used_ratio: vec![0.0, 0.0, 0.5],
+            reward: Some(vec![
+                vec![GWEI, 2 * GWEI, 4 * GWEI],
+                // 空区块, 不参与计算
+                vec![0, 0, 0],
+                vec![2 * GWEI, 4 * GWEI, 8 * GWEI],
+            ]),
+        };
+        // 不会发送请求
+        let provider = ProviderBuilder::new().on_http("http://localhost:8545".parse()?);
+        let oracle = FeeOracle::new(provider)
+            .ema_alpha(0.5)
+            .ceilings(FeeCeilings {
+                base_fee: Some(17 * GWEI),
+                priority_fee: Some(5 * GWEI),
+                blob_base_fee: None,
+            });
+        let estimate = oracle.estimate_from_history(&history)?;
+
+        assert_eq!(estimate.block_number, 102);
+        assert_eq!(estimate.next_base_fee, 16 * GWEI);
+        assert_eq!(estimate.next_blob_base_fee, Some(2));
+        // 0.5 * 2 + 0.5 * 1 = 1.5 gwei
+        assert_eq!(estimate.slow.priority_fee, 1_500_000_000);
+        assert_eq!(estimate.standard.priority_fee, 3 * GWEI);
+        // 0.5 * 8 + 0.5 * 4 = 6 gwei, 超过 5 gwei 上限
+        assert_eq!(estimate.fast.priority_fee, 5 * GWEI);
+        assert_eq!(estimate.slow.base_fee, 16 * GWEI);
+        // 16 * 1.125 = 18 gwei, 超过 17 gwei 上限
+        assert_eq!(estimate.standard.base_fee, 17 * GWEI);
+        assert_eq!(estimate.fast.base_fee, 17 * GWEI);
+        assert_eq!(estimate.slow.blob_base_fee, Some(2));
+        assert_eq!(estimate.standard.blob_base_fee, Some(3));
+        assert_eq!(estimate.fast.max_fee_per_gas(), 22 * GWEI);
+        Ok(())
+    }
+}
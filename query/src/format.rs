@@ -0,0 +1,35 @@
+use alloy::dyn_abi::DynSolValue;
+use alloy::hex;
+use serde_json::{Value, json};
+
+/// 把解析出来的 [`DynSolValue`] 转换为 json
+///
+/// 整数转换为十进制字符串, 避免超过 json number 的精度, bytes 转换为 0x 开头的十六进制
+pub fn value_to_json(value: &DynSolValue) -> Value {
+    match value {
+        DynSolValue::Bool(b) => json!(b),
+        DynSolValue::Int(i, _) => json!(i.to_string()),
+        DynSolValue::Uint(u, _) => json!(u.to_string()),
+        DynSolValue::FixedBytes(word, size) => json!(hex::encode_prefixed(&word[..*size])),
+        DynSolValue::Address(address) => json!(address.to_checksum(None)),
+        DynSolValue::Function(function) => json!(function.to_string()),
+        DynSolValue::Bytes(bytes) => json!(hex::encode_prefixed(bytes)),
+        DynSolValue::String(s) => json!(s),
+        DynSolValue::Array(values)
+        | DynSolValue::FixedArray(values)
+        | DynSolValue::Tuple(values) => Value::Array(values.iter().map(value_to_json).collect()),
+        // 开启 eip712 feature 时的 CustomStruct
+        #[allow(unreachable_patterns)]
+        _ => value.as_fixed_seq().map_or(Value::Null, |values| {
+            Value::Array(values.iter().map(value_to_json).collect())
+        }),
+    }
+}
+
+/// 用于打印的字符串, 和 [`value_to_json`] 的格式相同, 字符串不带引号
+pub fn value_to_string(value: &DynSolValue) -> String {
+    match value_to_json(value) {
+        Value::String(s) => s,
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,341 @@
+//! 一个账户的 ERC-20 转账记录, 以及根据转账重建的余额变化
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use alloy::contract::Error as ContractError;
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, B256, I256, U256, address};
+use alloy::providers::Provider;
+use alloy::rpc::types::{Filter, Log};
+use alloy::sol;
+use alloy::sol_types::SolEvent;
+use eyre::Result;
+use futures_util::future::try_join3;
+use futures_util::{StreamExt, TryStreamExt, stream};
+
+use crate::logs::LogFetcher;
+
+sol! {
+    #[sol(rpc)]
+    interface IERC20 {
+        event Transfer(address indexed from, address indexed to, uint256 value);
+
+        function balanceOf(address owner) external view returns (uint256);
+    }
+
+    // WETH9 的状态变量: name, symbol, decimals, balanceOf(slot 3), allowance(slot 4)
+    #[sol(rpc)]
+    interface IWETH9 {
+        event Deposit(address indexed dst, uint256 wad);
+        event Withdrawal(address indexed src, uint256 wad);
+
+        function deposit() external payable;
+        function withdraw(uint256 wad) external;
+        function transfer(address to, uint256 value) external returns (bool);
+        function approve(address guy, uint256 wad) external returns (bool);
+        function balanceOf(address owner) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
+    }
+}
+
+/// 常见链上的 WETH9, 没有指定 token 时只在这些合约上查询 Deposit/Withdrawal
+///
+/// 其他合约也可能发出相同签名的事件, 但不一定对应余额变化
+pub const KNOWN_WETH: &[Address] = &[
+    // mainnet
+    address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+    // sepolia
+    address!("0xfFf9976782d46CC05630D1f6eBAb18b2324d6B14"),
+    // holesky
+    address!("0x94373a4919B3240D86eA41593D5eBa789FEF3848"),
+    // OP Stack 预部署合约: optimism, base 等
+    address!("0x4200000000000000000000000000000000000006"),
+    // arbitrum one
+    address!("0x82aF49447D8a07e3bd95BD0d56f35241523fBab1"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferKind {
+    Transfer,
+    /// WETH9 的 deposit, 不会同时发出 Transfer
+    Deposit,
+    /// WETH9 的 withdraw, 不会同时发出 Transfer
+    Withdrawal,
+}
+
+impl fmt::Display for TransferKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// 一次转账, Deposit 的 from 和 Withdrawal 的 to 为 0 地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenTransfer {
+    pub token: Address,
+    pub kind: TransferKind,
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+    pub block_number: u64,
+    pub tx_hash: B256,
+    pub log_index: u64,
+}
+
+impl TokenTransfer {
+    /// 对 account 余额的影响, 转给自己时为 0
+    pub fn delta(&self, account: Address) -> I256 {
+        let amount = I256::from_raw(self.amount);
+        match (self.from == account, self.to == account) {
+            (true, false) => -amount,
+            (false, true) => amount,
+            _ => I256::ZERO,
+        }
+    }
+}
+
+/// 一个 token 的转账记录和余额变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenHistory {
+    pub token: Address,
+    /// 起始区块之前的余额, balanceOf revert 或返回值无法解码时为 None, 按 0 计算
+    pub start_balance: Option<U256>,
+    pub transfers: Vec<TokenTransfer>,
+    /// 每次转账之后的余额
+    pub balances: Vec<I256>,
+    /// 结束区块的 balanceOf, revert 或返回值无法解码时为 None
+    pub end_balance: Option<U256>,
+}
+
+impl TokenHistory {
+    /// 根据转账计算出来的结束余额
+    pub fn computed_balance(&self) -> I256 {
+        self.balances
+            .last()
+            .copied()
+            .unwrap_or_else(|| I256::from_raw(self.start_balance.unwrap_or_default()))
+    }
+
+    /// balanceOf 和计算结果的差值, 例如 rebasing token 或者不发出 Transfer 的 mint/burn
+    pub fn mismatch(&self) -> Option<I256> {
+        let end_balance = I256::from_raw(self.end_balance?);
+        let diff = end_balance - self.computed_balance();
+        (!diff.is_zero()).then_some(diff)
+    }
+}
+
+/// 账户在区块范围内所有 token 的历史, 按 token 地址排序
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountHistory {
+    pub account: Address,
+    pub from_block: u64,
+    pub to_block: u64,
+    pub tokens: Vec<TokenHistory>,
+}
+
+/// 查询账户转入转出的 ERC-20 Transfer 以及 WETH9 Deposit/Withdrawal,
+/// 按 token 重建余额, 并和起止区块的 balanceOf 对比
+pub struct HistoryFetcher<P> {
+    provider: P,
+    account: Address,
+    tokens: Vec<Address>,
+    weth: Vec<Address>,
+    concurrency: usize,
+}
+
+impl<P: Provider + Clone> HistoryFetcher<P> {
+    pub fn new(provider: P, account: Address) -> Self {
+        Self {
+            provider,
+            account,
+            tokens: Vec::new(),
+            weth: KNOWN_WETH.to_vec(),
+            concurrency: 4,
+        }
+    }
+
+    /// 只查询这些 token, 默认查询所有合约的 Transfer
+    ///
+    /// 指定 token 后 Deposit/Withdrawal 也只在这些 token 上查询
+    pub fn tokens(mut self, tokens: &[Address]) -> Self {
+        self.tokens = tokens.to_vec();
+        self
+    }
+
+    /// 没有指定 token 时查询 Deposit/Withdrawal 的合约, 默认 [`KNOWN_WETH`]
+    pub fn weth(mut self, weth: &[Address]) -> Self {
+        self.weth = weth.to_vec();
+        self
+    }
+
+    /// 同时查询 balanceOf 的 token 数量
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub async fn fetch(&self, from_block: u64, to_block: u64) -> Result<AccountHistory> {
+        let account = self.account.into_word();
+        let transfer = self
+            .filter(from_block, to_block)
+            .event_signature(IERC20::Transfer::SIGNATURE_HASH);
+        // 不限制地址时任何合约都可以发出 Deposit/Withdrawal, 空的地址列表也不限制
+        let weth_tokens = if self.tokens.is_empty() {
+            self.weth.clone()
+        } else {
+            self.tokens.clone()
+        };
+        let weth = Filter::new()
+            .from_block(from_block)
+            .to_block(to_block)
+            .address(weth_tokens.clone())
+            .event_signature(vec![
+                IWETH9::Deposit::SIGNATURE_HASH,
+                IWETH9::Withdrawal::SIGNATURE_HASH,
+            ])
+            .topic1(account);
+        let (sent, received, weth) = try_join3(
+            self.logs(transfer.clone().topic1(account)),
+            self.logs(transfer.topic2(account)),
+            async {
+                if weth_tokens.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.logs(weth).await
+            },
+        )
+        .await?;
+
+        // 转给自己的 Transfer 会同时出现在转出和转入中
+        let mut seen = HashSet::new();
+        let mut by_token: BTreeMap<Address, Vec<TokenTransfer>> = BTreeMap::new();
+        for transfer in sent
+            .iter()
+            .chain(&received)
+            .chain(&weth)
+            .filter_map(decode_transfer)
+        {
+            if seen.insert((transfer.tx_hash, transfer.log_index)) {
+                by_token.entry(transfer.token).or_default().push(transfer);
+            }
+        }
+
+        let mut tokens: Vec<TokenHistory> = stream::iter(by_token)
+            .map(|(token, mut transfers)| async move {
+                transfers.sort_by_key(|transfer| (transfer.block_number, transfer.log_index));
+                let start_balance = match from_block.checked_sub(1) {
+                    Some(block) => self.balance_of(token, block).await?,
+                    None => Some(U256::ZERO),
+                };
+                let end_balance = self.balance_of(token, to_block).await?;
+                let mut balance = I256::from_raw(start_balance.unwrap_or_default());
+                let balances = transfers
+                    .iter()
+                    .map(|transfer| {
+                        balance += transfer.delta(self.account);
+                        balance
+                    })
+                    .collect();
+                Ok::<_, eyre::Report>(TokenHistory {
+                    token,
+                    start_balance,
+                    transfers,
+                    balances,
+                    end_balance,
+                })
+            })
+            .buffer_unordered(self.concurrency)
+            .try_collect()
+            .await?;
+        tokens.sort_by_key(|history| history.token);
+
+        Ok(AccountHistory {
+            account: self.account,
+            from_block,
+            to_block,
+            tokens,
+        })
+    }
+
+    fn filter(&self, from_block: u64, to_block: u64) -> Filter {
+        let filter = Filter::new().from_block(from_block).to_block(to_block);
+        if self.tokens.is_empty() {
+            filter
+        } else {
+            filter.address(self.tokens.clone())
+        }
+    }
+
+    async fn logs(&self, filter: Filter) -> Result<Vec<Log>> {
+        let fetcher = LogFetcher::new(self.provider.clone(), filter)?;
+        fetcher.stream().try_collect().await
+    }
+
+    /// 不是 ERC-20 时为 None: 调用 revert, 或者返回值无法解码 (包括没有代码的地址).
+    /// 节点没有历史状态, 限流和连接错误返回给调用者
+    async fn balance_of(&self, token: Address, block: u64) -> Result<Option<U256>> {
+        let token = IERC20::new(token, self.provider.clone());
+        let result = token
+            .balanceOf(self.account)
+            .block(BlockId::number(block))
+            .call()
+            .await;
+        match result {
+            Ok(balance) => Ok(Some(balance._0)),
+            Err(ContractError::ZeroData(..) | ContractError::AbiError(_)) => Ok(None),
+            Err(ContractError::TransportError(err))
+                if err
+                    .as_error_resp()
+                    .is_some_and(|resp| resp.message.contains("revert")) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// ERC-721 的 Transfer 签名相同, 但是 tokenId 也是 indexed, 有 4 个 topic
+fn decode_transfer(log: &Log) -> Option<TokenTransfer> {
+    let topics = log.topics();
+    let (kind, from, to) = match (topics.first()?, topics.len()) {
+        (&IERC20::Transfer::SIGNATURE_HASH, 3) => (
+            TransferKind::Transfer,
+            topic_address(topics[1])?,
+            topic_address(topics[2])?,
+        ),
+        (&IWETH9::Deposit::SIGNATURE_HASH, 2) => (
+            TransferKind::Deposit,
+            Address::ZERO,
+            topic_address(topics[1])?,
+        ),
+        (&IWETH9::Withdrawal::SIGNATURE_HASH, 2) => (
+            TransferKind::Withdrawal,
+            topic_address(topics[1])?,
+            Address::ZERO,
+        ),
+        _ => return None,
+    };
+    let data = log.data().data.as_ref();
+    if data.len() != 32 {
+        return None;
+    }
+    Some(TokenTransfer {
+        token: log.address(),
+        kind,
+        from,
+        to,
+        amount: U256::from_be_slice(data),
+        block_number: log.block_number?,
+        tx_hash: log.transaction_hash?,
+        log_index: log.log_index?,
+    })
+}
+
+/// 高 12 字节不为 0 的不是合法的地址 topic
+fn topic_address(topic: B256) -> Option<Address> {
+    topic[..12]
+        .iter()
+        .all(|byte| *byte == 0)
+        .then(|| Address::from_word(topic))
+}
@@ -0,0 +1,233 @@
+use std::path::Path;
+
+use alloy::primitives::{Address, B256, Bytes};
+use eyre::Result;
+use rusqlite::{Connection, OptionalExtension, params};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS logs (
+    filter TEXT NOT NULL,
+    event TEXT NOT NULL,
+    block_number INTEGER NOT NULL,
+    block_hash TEXT NOT NULL,
+    transaction_hash TEXT,
+    log_index INTEGER NOT NULL,
+    address TEXT NOT NULL,
+    topics TEXT NOT NULL,
+    data TEXT NOT NULL,
+    decoded TEXT,
+    PRIMARY KEY (filter, block_hash, log_index)
+);
+CREATE INDEX IF NOT EXISTS logs_block_number ON logs (block_number);
+CREATE TABLE IF NOT EXISTS blocks (
+    number INTEGER PRIMARY KEY,
+    hash TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS checkpoint (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    block_number INTEGER NOT NULL,
+    block_hash TEXT NOT NULL
+);
+";
+
+/// 已经处理到的区块
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub block_number: u64,
+    pub block_hash: B256,
+}
+
+/// 保存到数据库中的 log
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedLog {
+    /// 匹配到的 filter 名称
+    pub filter: String,
+    pub event: String,
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub transaction_hash: Option<B256>,
+    pub log_index: u64,
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+    /// 按事件参数名解析出来的值, 解析失败时为 None
+    pub decoded: Option<serde_json::Value>,
+}
+
+/// 保存 logs, 已处理区块的哈希和 checkpoint 的 SQLite 数据库
+pub struct IndexerDb {
+    conn: Connection,
+}
+
+impl IndexerDb {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn checkpoint(&self) -> Result<Option<Checkpoint>> {
+        let checkpoint = self
+            .conn
+            .query_row(
+                "SELECT block_number, block_hash FROM checkpoint WHERE id = 0",
+                (),
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?;
+        checkpoint
+            .map(|(block_number, block_hash)| {
+                Ok(Checkpoint {
+                    block_number: block_number as u64,
+                    block_hash: block_hash.parse()?,
+                })
+            })
+            .transpose()
+    }
+
+    /// 区块号不小于 from_number 的已处理区块的哈希, 按区块号从大到小
+    pub fn blocks(&self, from_number: u64) -> Result<Vec<(u64, B256)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT number, hash FROM blocks WHERE number >= ?1 ORDER BY number DESC")?;
+        let rows = stmt.query_map(params![from_number as i64], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut blocks = Vec::new();
+        for row in rows {
+            let (number, hash) = row?;
+            blocks.push((number as u64, hash.parse()?));
+        }
+        Ok(blocks)
+    }
+
+    /// 在一个事务中保存一批 logs, 涉及的区块哈希以及新的 checkpoint
+    pub fn commit_batch(
+        &mut self,
+        logs: &[IndexedLog],
+        blocks: &[(u64, B256)],
+        checkpoint: Checkpoint,
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for log in logs {
+            let topics = serde_json::to_string(&log.topics)?;
+            let decoded = log.decoded.as_ref().map(|v| v.to_string());
+            tx.execute(
+                "INSERT OR REPLACE INTO logs
+                 (filter, event, block_number, block_hash, transaction_hash, log_index, address, topics, data, decoded)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    log.filter,
+                    log.event,
+                    log.block_number as i64,
+                    log.block_hash.to_string(),
+                    log.transaction_hash.map(|hash| hash.to_string()),
+                    log.log_index as i64,
+                    log.address.to_string(),
+                    topics,
+                    log.data.to_string(),
+                    decoded,
+                ],
+            )?;
+        }
+        for (number, hash) in blocks {
+            tx.execute(
+                "INSERT OR REPLACE INTO blocks (number, hash) VALUES (?1, ?2)",
+                params![*number as i64, hash.to_string()],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO checkpoint (id, block_number, block_hash) VALUES (0, ?1, ?2)",
+            params![
+                checkpoint.block_number as i64,
+                checkpoint.block_hash.to_string()
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 删除 ancestor 之后的所有数据, 并把 checkpoint 回退到 ancestor, 返回删除的 log 数量
+    ///
+    /// ancestor 为 None 时删除所有数据, 从头开始
+    pub fn rollback_to(&mut self, ancestor: Option<Checkpoint>) -> Result<usize> {
+        let after = ancestor.map_or(-1, |checkpoint| checkpoint.block_number as i64);
+        let tx = self.conn.transaction()?;
+        let removed = tx.execute("DELETE FROM logs WHERE block_number > ?1", params![after])?;
+        tx.execute("DELETE FROM blocks WHERE number > ?1", params![after])?;
+        match ancestor {
+            Some(checkpoint) => {
+                tx.execute(
+                    "INSERT OR REPLACE INTO checkpoint (id, block_number, block_hash) VALUES (0, ?1, ?2)",
+                    params![
+                        checkpoint.block_number as i64,
+                        checkpoint.block_hash.to_string()
+                    ],
+                )?;
+            }
+            None => {
+                tx.execute("DELETE FROM checkpoint", ())?;
+            }
+        }
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    /// 按区块顺序读取 filter 匹配到的 logs
+    pub fn logs(&self, filter: &str) -> Result<Vec<IndexedLog>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT filter, event, block_number, block_hash, transaction_hash, log_index, address, topics, data, decoded
+             FROM logs WHERE filter = ?1 ORDER BY block_number, log_index",
+        )?;
+        let rows = stmt.query_map(params![filter], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, String>(7)?,
+                row.get::<_, String>(8)?,
+                row.get::<_, Option<String>>(9)?,
+            ))
+        })?;
+
+        let mut logs = Vec::new();
+        for row in rows {
+            let (
+                filter,
+                event,
+                block_number,
+                block_hash,
+                transaction_hash,
+                log_index,
+                address,
+                topics,
+                data,
+                decoded,
+            ) = row?;
+            logs.push(IndexedLog {
+                filter,
+                event,
+                block_number: block_number as u64,
+                block_hash: block_hash.parse()?,
+                transaction_hash: transaction_hash.map(|hash| hash.parse()).transpose()?,
+                log_index: log_index as u64,
+                address: address.parse()?,
+                topics: serde_json::from_str(&topics)?,
+                data: data.parse()?,
+                decoded: decoded.map(|v| serde_json::from_str(&v)).transpose()?,
+            });
+        }
+        Ok(logs)
+    }
+}
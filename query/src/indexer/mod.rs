@@ -0,0 +1,275 @@
+//! 把合约的事件保存到本地 SQLite 数据库
+mod db;
+
+use std::time::Duration;
+
+use alloy::dyn_abi::EventExt;
+use alloy::json_abi::Event;
+use alloy::primitives::B256;
+use alloy::providers::Provider;
+use alloy::rpc::types::{Filter, Log};
+use eyre::{Result, bail, eyre};
+use futures_util::{StreamExt, pin_mut};
+use serde_json::{Map, Value};
+
+pub use db::{Checkpoint, IndexedLog, IndexerDb};
+
+use crate::format::value_to_json;
+use crate::logs::LogFetcher;
+
+/// 需要索引的事件
+#[derive(Debug, Clone)]
+pub struct IndexFilter {
+    pub name: String,
+    pub event: Event,
+    pub filter: Filter,
+}
+
+impl IndexFilter {
+    /// filter 中设置合约地址等条件, 事件签名由 event 设置, 区块范围由 indexer 设置
+    ///
+    /// anonymous 事件没有 topic0, 只能通过地址和 indexed 参数过滤
+    pub fn new(name: impl Into<String>, event: Event, filter: Filter) -> Self {
+        let filter = if event.anonymous {
+            filter
+        } else {
+            filter.event_signature(event.selector())
+        };
+        Self {
+            name: name.into(),
+            event,
+            filter,
+        }
+    }
+
+    fn index(&self, log: Log) -> Result<IndexedLog> {
+        let decoded = self.event.decode_log(log.data(), true).ok().map(|decoded| {
+            // 按照参数顺序把 indexed 和非 indexed 的值合并
+            let mut indexed = decoded.indexed.iter();
+            let mut body = decoded.body.iter();
+            let mut fields = Map::new();
+            for (i, input) in self.event.inputs.iter().enumerate() {
+                let value = if input.indexed {
+                    indexed.next()
+                } else {
+                    body.next()
+                };
+                let name = if input.name.is_empty() {
+                    i.to_string()
+                } else {
+                    input.name.clone()
+                };
+                fields.insert(name, value.map_or(Value::Null, value_to_json));
+            }
+            Value::Object(fields)
+        });
+
+        Ok(IndexedLog {
+            filter: self.name.clone(),
+            event: self.event.signature(),
+            block_number: log
+                .block_number
+                .ok_or_else(|| eyre!("log without block number"))?,
+            block_hash: log
+                .block_hash
+                .ok_or_else(|| eyre!("log without block hash"))?,
+            transaction_hash: log.transaction_hash,
+            log_index: log
+                .log_index
+                .ok_or_else(|| eyre!("log without log index"))?,
+            address: log.address(),
+            topics: log.topics().to_vec(),
+            data: log.data().data.clone(),
+            decoded,
+        })
+    }
+}
+
+/// indexer 每一步的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexEvent {
+    /// 处理了 from_block..=to_block, 保存了 logs 条数据
+    Indexed {
+        from_block: u64,
+        to_block: u64,
+        logs: usize,
+    },
+    /// checkpoint 的区块哈希和链上不一致, 回退到仍在链上的 ancestor
+    Reorged {
+        ancestor: Option<u64>,
+        removed_logs: usize,
+    },
+}
+
+/// 从 start_block 开始跟踪 filters 匹配的事件, 保存到 SQLite
+///
+/// 每批区块处理完后保存 checkpoint, 重启后从 checkpoint 继续;
+/// 每一步开始前检查 checkpoint 的区块哈希, 不一致时回退孤块中的数据
+pub struct Indexer<P> {
+    provider: P,
+    db: IndexerDb,
+    filters: Vec<IndexFilter>,
+    start_block: u64,
+    batch_size: u64,
+    reorg_depth: u64,
+    poll_interval: Duration,
+}
+
+impl<P: Provider + Clone> Indexer<P> {
+    pub fn new(provider: P, db: IndexerDb, start_block: u64) -> Self {
+        Self {
+            provider,
+            db,
+            filters: Vec::new(),
+            start_block,
+            batch_size: 1_000,
+            reorg_depth: 64,
+            poll_interval: Duration::from_secs(12),
+        }
+    }
+
+    pub fn filter(mut self, filter: IndexFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// 每批处理的区块数量, 默认 1000
+    pub fn batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// 发生 reorg 时最多往回检查的区块数量, 默认 64
+    pub fn reorg_depth(mut self, reorg_depth: u64) -> Self {
+        self.reorg_depth = reorg_depth;
+        self
+    }
+
+    /// 追上最新区块后的轮询间隔, 默认 12s
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn db(&self) -> &IndexerDb {
+        &self.db
+    }
+
+    /// 一直运行, 追上最新区块后按 poll_interval 轮询
+    pub async fn run(&mut self, mut on_event: impl FnMut(&IndexEvent)) -> Result<()> {
+        loop {
+            for event in self.sync().await? {
+                on_event(&event);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// 处理到当前最新的区块
+    pub async fn sync(&mut self) -> Result<Vec<IndexEvent>> {
+        let mut events = Vec::new();
+        while let Some(event) = self.step().await? {
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    /// 检查 reorg 或者处理下一批区块, 已经是最新区块时返回 None
+    pub async fn step(&mut self) -> Result<Option<IndexEvent>> {
+        if let Some(reorg) = self.check_reorg().await? {
+            return Ok(Some(reorg));
+        }
+
+        let from_block = self
+            .db
+            .checkpoint()?
+            .map_or(self.start_block, |checkpoint| checkpoint.block_number + 1);
+        let latest = self.provider.get_block_number().await?;
+        if from_block > latest {
+            return Ok(None);
+        }
+        let to_block = latest.min(from_block + self.batch_size - 1);
+        let to_hash = self
+            .block_hash(to_block)
+            .await?
+            .ok_or_else(|| eyre!("block {to_block} not found"))?;
+
+        let mut logs = Vec::new();
+        for index_filter in &self.filters {
+            let filter = index_filter
+                .filter
+                .clone()
+                .from_block(from_block)
+                .to_block(to_block);
+            let fetcher = LogFetcher::new(self.provider.clone(), filter)?;
+            let stream = fetcher.stream();
+            pin_mut!(stream);
+            while let Some(log) = stream.next().await {
+                logs.push(index_filter.index(log?)?);
+            }
+        }
+
+        // 记录包含 logs 的区块和 checkpoint 区块的哈希, 用于之后检查 reorg
+        let mut blocks: Vec<(u64, B256)> = logs
+            .iter()
+            .map(|log| (log.block_number, log.block_hash))
+            .collect();
+        blocks.push((to_block, to_hash));
+        blocks.sort_unstable();
+        blocks.dedup();
+
+        let checkpoint = Checkpoint {
+            block_number: to_block,
+            block_hash: to_hash,
+        };
+        self.db.commit_batch(&logs, &blocks, checkpoint)?;
+
+        Ok(Some(IndexEvent::Indexed {
+            from_block,
+            to_block,
+            logs: logs.len(),
+        }))
+    }
+
+    /// checkpoint 的区块哈希和链上不一致时, 在 checkpoint 之前 reorg_depth 个区块内
+    /// 从最近的区块开始往前找仍在链上的区块并回退
+    async fn check_reorg(&mut self) -> Result<Option<IndexEvent>> {
+        let Some(checkpoint) = self.db.checkpoint()? else {
+            return Ok(None);
+        };
+        if self.block_hash(checkpoint.block_number).await? == Some(checkpoint.block_hash) {
+            return Ok(None);
+        }
+
+        let from_number = checkpoint.block_number.saturating_sub(self.reorg_depth);
+        let mut ancestor = None;
+        for (number, hash) in self.db.blocks(from_number)? {
+            if self.block_hash(number).await? == Some(hash) {
+                ancestor = Some(Checkpoint {
+                    block_number: number,
+                    block_hash: hash,
+                });
+                break;
+            }
+        }
+        // 范围内没有找到 ancestor, 并且范围之前还有已处理的区块, 不能确定从哪里继续
+        if ancestor.is_none() && from_number > self.start_block {
+            bail!(
+                "reorg deeper than {} blocks below checkpoint {}",
+                self.reorg_depth,
+                checkpoint.block_number
+            );
+        }
+        let removed_logs = self.db.rollback_to(ancestor)?;
+
+        Ok(Some(IndexEvent::Reorged {
+            ancestor: ancestor.map(|checkpoint| checkpoint.block_number),
+            removed_logs,
+        }))
+    }
+
+    async fn block_hash(&self, number: u64) -> Result<Option<B256>> {
+        let block = self.provider.get_block_by_number(number.into()).await?;
+        Ok(block.map(|block| block.header.hash))
+    }
+}
@@ -0,0 +1,16 @@
+pub mod bytecode;
+pub mod creation;
+pub mod diff;
+pub mod ens;
+pub mod export;
+pub mod fees;
+pub mod format;
+pub mod history;
+pub mod indexer;
+pub mod logs;
+pub mod multicall;
+pub mod proxy;
+pub mod storage;
+pub mod timestamp;
+pub mod trace;
+pub mod watch;
@@ -0,0 +1,93 @@
+//! 常见合约的事件, 第一个元素是来源(标准或者协议)
+
+/// ERC-20 和 ERC-721 的 Transfer/Approval 签名相同, 区别在于 ERC-721 的第三个参数是 indexed
+pub(super) const COMMON_EVENTS: &[(&str, &str)] = &[
+    (
+        "ERC20",
+        "event Transfer(address indexed from, address indexed to, uint256 value)",
+    ),
+    (
+        "ERC20",
+        "event Approval(address indexed owner, address indexed spender, uint256 value)",
+    ),
+    (
+        "ERC721",
+        "event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)",
+    ),
+    (
+        "ERC721",
+        "event Approval(address indexed owner, address indexed approved, uint256 indexed tokenId)",
+    ),
+    (
+        "ERC721",
+        "event ApprovalForAll(address indexed owner, address indexed operator, bool approved)",
+    ),
+    (
+        "ERC1155",
+        "event TransferSingle(address indexed operator, address indexed from, address indexed to, uint256 id, uint256 value)",
+    ),
+    (
+        "ERC1155",
+        "event TransferBatch(address indexed operator, address indexed from, address indexed to, uint256[] ids, uint256[] values)",
+    ),
+    ("ERC1155", "event URI(string value, uint256 indexed id)"),
+    ("WETH9", "event Deposit(address indexed dst, uint256 wad)"),
+    (
+        "WETH9",
+        "event Withdrawal(address indexed src, uint256 wad)",
+    ),
+    (
+        "Ownable",
+        "event OwnershipTransferred(address indexed previousOwner, address indexed newOwner)",
+    ),
+    ("ERC1967", "event Upgraded(address indexed implementation)"),
+    (
+        "ERC1967",
+        "event AdminChanged(address previousAdmin, address newAdmin)",
+    ),
+    ("ERC1967", "event BeaconUpgraded(address indexed beacon)"),
+    ("Initializable", "event Initialized(uint64 version)"),
+    // OpenZeppelin v4
+    ("Initializable", "event Initialized(uint8 version)"),
+    (
+        "UniswapV2Factory",
+        "event PairCreated(address indexed token0, address indexed token1, address pair, uint256)",
+    ),
+    (
+        "UniswapV2Pair",
+        "event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to)",
+    ),
+    (
+        "UniswapV2Pair",
+        "event Sync(uint112 reserve0, uint112 reserve1)",
+    ),
+    (
+        "UniswapV2Pair",
+        "event Mint(address indexed sender, uint256 amount0, uint256 amount1)",
+    ),
+    (
+        "UniswapV2Pair",
+        "event Burn(address indexed sender, uint256 amount0, uint256 amount1, address indexed to)",
+    ),
+    (
+        "UniswapV3Pool",
+        "event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick)",
+    ),
+    (
+        "UniswapV3Pool",
+        "event Initialize(uint160 sqrtPriceX96, int24 tick)",
+    ),
+    (
+        "UniswapV3Pool",
+        "event Mint(address sender, address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1)",
+    ),
+    (
+        "UniswapV3Pool",
+        "event Burn(address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1)",
+    ),
+    // MakerDAO 的 DSNote, 匿名事件, 第一个 topic 是函数 selector
+    (
+        "DSNote",
+        "event LogNote(bytes4 indexed sig, address indexed guy, bytes32 indexed foo, bytes32 indexed bar, uint256 wad, bytes fax) anonymous",
+    ),
+];
@@ -0,0 +1,304 @@
+use std::time::Duration;
+
+use alloy::providers::Provider;
+use alloy::rpc::types::{Filter, Log};
+use alloy::transports::{RpcError, TransportErrorKind};
+use eyre::{Result, eyre};
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, FuturesOrdered, Stream, StreamExt, TryStreamExt};
+
+/// 节点返回这些错误时, 说明区块范围太大或者结果太多, 需要缩小区块范围
+///
+/// 不能用 "exceed", "limit exceeded" 这类宽泛的词, 限流错误中也会出现
+const RANGE_ERROR_MARKERS: &[&str] = &[
+    "block range",
+    "range too",
+    "returned more than",
+    "too many results",
+    "too many logs",
+    "too large",
+    "response size",
+];
+
+/// 节点限流的错误, 需要等待后重试
+const RATE_LIMIT_MARKERS: &[&str] = &[
+    "rate limit",
+    "too many requests",
+    "request rate",
+    "exceeded the quota",
+];
+
+/// 把一个大区块范围的 Filter 切分成多个区块段查询 logs
+///
+/// - 节点返回区块范围或者结果数量的错误时, 缩小区块段重试
+/// - 返回的 logs 较少时, 扩大之后的区块段
+/// - 同时查询多个区块段, 按区块顺序输出
+pub struct LogFetcher<P> {
+    provider: P,
+    filter: Filter,
+    from_block: u64,
+    to_block: u64,
+    chunk_size: u64,
+    max_chunk_size: u64,
+    concurrency: usize,
+    target_logs: usize,
+    max_retries: u32,
+    retry_delay: Duration,
+}
+
+/// 一个区块段的查询结果
+struct Chunk {
+    logs: Vec<Log>,
+    /// 实际可用的区块段大小, 出现范围错误后会小于请求的大小
+    chunk_size: u64,
+    shrunk: bool,
+}
+
+impl<P: Provider> LogFetcher<P> {
+    /// filter 中的 fromBlock 和 toBlock 必须是具体的区块号
+    pub fn new(provider: P, filter: Filter) -> Result<Self> {
+        let from_block = filter
+            .get_from_block()
+            .ok_or_else(|| eyre!("filter fromBlock must be a block number"))?;
+        let to_block = filter
+            .get_to_block()
+            .ok_or_else(|| eyre!("filter toBlock must be a block number"))?;
+        if from_block > to_block {
+            return Err(eyre!("fromBlock {from_block} > toBlock {to_block}"));
+        }
+        Ok(Self {
+            provider,
+            filter,
+            from_block,
+            to_block,
+            chunk_size: 2_000,
+            max_chunk_size: 100_000,
+            concurrency: 4,
+            target_logs: 5_000,
+            max_retries: 5,
+            retry_delay: Duration::from_secs(1),
+        })
+    }
+
+    /// 初始区块段大小, 默认 2000
+    pub fn chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// 区块段扩大的上限, 默认 100000
+    pub fn max_chunk_size(mut self, max_chunk_size: u64) -> Self {
+        self.max_chunk_size = max_chunk_size.max(1);
+        self
+    }
+
+    /// 同时查询的区块段数量, 默认 4
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 一个区块段返回的 logs 少于 target_logs 的一半时扩大区块段, 默认 5000
+    pub fn target_logs(mut self, target_logs: usize) -> Self {
+        self.target_logs = target_logs;
+        self
+    }
+
+    /// 限流时最多重试的次数, 默认 5
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// 限流后第一次重试前等待的时间, 之后每次翻倍, 默认 1 秒
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// 按区块顺序输出所有 logs, 出错后 stream 结束
+    pub fn stream(&self) -> impl Stream<Item = Result<Log>> + '_ {
+        let state = StreamState {
+            next_block: self.from_block,
+            chunk_size: self.chunk_size.min(self.max_chunk_size),
+            in_flight: FuturesOrdered::new(),
+            done: false,
+        };
+
+        stream::unfold(state, move |mut state| async move {
+            if state.done {
+                return None;
+            }
+            while state.in_flight.len() < self.concurrency && state.next_block <= self.to_block {
+                let from = state.next_block;
+                let to = from.saturating_add(state.chunk_size - 1).min(self.to_block);
+                state.next_block = to + 1;
+                state
+                    .in_flight
+                    .push_back(Box::pin(self.fetch_range(from, to)));
+            }
+
+            match state.in_flight.next().await? {
+                Ok(chunk) => {
+                    state.chunk_size = if chunk.shrunk {
+                        chunk.chunk_size
+                    } else if chunk.logs.len() < self.target_logs / 2 {
+                        state.chunk_size.saturating_mul(2).min(self.max_chunk_size)
+                    } else {
+                        state.chunk_size
+                    };
+                    Some((Ok(chunk.logs), state))
+                }
+                Err(e) => {
+                    state.done = true;
+                    Some((Err(e), state))
+                }
+            }
+        })
+        .map_ok(|logs| stream::iter(logs).map(Ok))
+        .try_flatten()
+    }
+
+    /// 查询 from..=to 的 logs, 限流时等待后重试, 范围错误时把剩余的区块减半重试
+    async fn fetch_range(&self, from: u64, to: u64) -> Result<Chunk> {
+        let mut logs = Vec::new();
+        let mut start = from;
+        let mut chunk_size = to - from + 1;
+        let mut shrunk = false;
+        let mut retries = 0;
+
+        while start <= to {
+            let end = start.saturating_add(chunk_size - 1).min(to);
+            let filter = self.filter.clone().from_block(start).to_block(end);
+            match self.provider.get_logs(&filter).await {
+                Ok(mut chunk_logs) => {
+                    logs.append(&mut chunk_logs);
+                    start = end + 1;
+                    retries = 0;
+                }
+                // 限流错误的信息可能也像范围错误, 先检查限流
+                Err(e) if is_rate_limit_error(&e) => {
+                    if retries >= self.max_retries {
+                        return Err(eyre!(
+                            "get logs {start}..={end} still rate limited after {retries} retries, {e}"
+                        ));
+                    }
+                    let delay = self
+                        .retry_delay
+                        .saturating_mul(2u32.saturating_pow(retries));
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+                Err(e) if end > start && is_range_error(&e) => {
+                    chunk_size = (end - start).div_ceil(2);
+                    shrunk = true;
+                }
+                Err(e) => return Err(eyre!("get logs {start}..={end} failed, {e}")),
+            }
+        }
+
+        Ok(Chunk {
+            logs,
+            chunk_size,
+            shrunk,
+        })
+    }
+}
+
+/// 节点限流的错误, HTTP 429 或者错误信息中说明了限流
+///
+/// 例如 infura: -32005 project ID request rate exceeded,
+/// alchemy: 429 Your app has exceeded its compute units per second capacity
+pub fn is_rate_limit_error(err: &RpcError<TransportErrorKind>) -> bool {
+    if let RpcError::Transport(TransportErrorKind::HttpError(http)) = err
+        && http.is_rate_limit_err()
+    {
+        return true;
+    }
+    if err.as_error_resp().is_some_and(|resp| resp.code == 429) {
+        return true;
+    }
+    let message = err.to_string().to_lowercase();
+    RATE_LIMIT_MARKERS
+        .iter()
+        .any(|marker| message.contains(marker))
+}
+
+struct StreamState<'a> {
+    next_block: u64,
+    chunk_size: u64,
+    in_flight: FuturesOrdered<BoxFuture<'a, Result<Chunk>>>,
+    done: bool,
+}
+
+/// 区块范围太大或者结果太多的错误
+///
+/// 不同节点的错误码和错误信息不同, 例如
+/// infura: -32005 query returned more than 10000 results
+/// alchemy: Log response size exceeded
+/// publicnode: exceed maximum block range
+///
+/// 限流错误(见 [`is_rate_limit_error`])不算范围错误, infura 的限流也使用 -32005
+pub fn is_range_error(err: &RpcError<TransportErrorKind>) -> bool {
+    if is_rate_limit_error(err) {
+        return false;
+    }
+    if err.as_error_resp().is_some_and(|resp| resp.code == -32005) {
+        return true;
+    }
+    let message = err.to_string().to_lowercase();
+    RANGE_ERROR_MARKERS
+        .iter()
+        .any(|marker| message.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::rpc::json_rpc::ErrorPayload;
+
+    use super::*;
+
+    fn error_resp(code: i64, message: &'static str) -> RpcError<TransportErrorKind> {
+        RpcError::ErrorResp(ErrorPayload {
+            code,
+            message: message.into(),
+            data: None,
+        })
+    }
+
+    #[test]
+    fn range_errors() {
+        for err in [
+            error_resp(-32005, "query returned more than 10000 results"),
+            error_resp(-32602, "Log response size exceeded."),
+            error_resp(-32000, "exceed maximum block range: 50000"),
+            error_resp(-32000, "block range is too wide"),
+        ] {
+            assert!(is_range_error(&err), "{err}");
+            assert!(!is_rate_limit_error(&err), "{err}");
+        }
+    }
+
+    #[test]
+    fn rate_limit_errors() {
+        for err in [
+            TransportErrorKind::http_error(429, "Too Many Requests".to_string()),
+            error_resp(
+                429,
+                "Your app has exceeded its compute units per second capacity",
+            ),
+            error_resp(-32005, "project ID request rate exceeded"),
+            error_resp(-32000, "daily request limit exceeded, rate limited"),
+        ] {
+            assert!(is_rate_limit_error(&err), "{err}");
+            assert!(!is_range_error(&err), "{err}");
+        }
+    }
+
+    #[test]
+    fn other_errors() {
+        let err = error_resp(-32000, "header not found");
+        assert!(!is_range_error(&err));
+        assert!(!is_rate_limit_error(&err));
+    }
+}
@@ -0,0 +1,8 @@
+//! 查询和处理合约的 logs
+mod common;
+mod fetcher;
+mod registry;
+
+pub use fetcher::{LogFetcher, is_range_error, is_rate_limit_error};
+pub(crate) use registry::read_abi_file;
+pub use registry::{DecodedLog, DecodedParam, EventRegistry, RegisteredEvent};
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use alloy::dyn_abi::{DynSolType, DynSolValue, EventExt, Specifier};
+use alloy::json_abi::{Event, JsonAbi};
+use alloy::primitives::{B256, LogData};
+use eyre::{Result, eyre};
+use serde_json::{Map, Value};
+
+use super::common::COMMON_EVENTS;
+use crate::format::{value_to_json, value_to_string};
+
+/// 注册的事件和它的来源, 来源是 ABI 文件名或者标准名称, 例如 ERC20
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisteredEvent {
+    pub source: String,
+    pub event: Event,
+}
+
+impl RegisteredEvent {
+    fn indexed_count(&self) -> usize {
+        self.event
+            .inputs
+            .iter()
+            .filter(|input| input.indexed)
+            .count()
+    }
+
+    /// 同一个签名按 indexed 的位置区分, 例如 ERC-20 和 ERC-721 的 Transfer
+    fn same_shape(&self, other: &Event) -> bool {
+        self.event.signature() == other.signature()
+            && self
+                .event
+                .inputs
+                .iter()
+                .map(|input| input.indexed)
+                .eq(other.inputs.iter().map(|input| input.indexed))
+    }
+}
+
+/// 根据 topics[0] 从注册的事件 ABI 中找到匹配的事件并解析 log
+///
+/// 同一个 selector 可能对应多个事件, 依次尝试 topic 数量一致并且能解析成功的事件;
+/// 匿名事件没有 topics[0], 只有非匿名事件都不匹配时才会尝试
+#[derive(Debug, Clone, Default)]
+pub struct EventRegistry {
+    events: HashMap<B256, Vec<RegisteredEvent>>,
+    anonymous: Vec<RegisteredEvent>,
+}
+
+impl EventRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 包含常见标准和协议的事件
+    pub fn with_common() -> Self {
+        let mut registry = Self::new();
+        for (source, signature) in COMMON_EVENTS {
+            let event = Event::parse(signature).expect("invalid bundled event");
+            registry.add_event(*source, event);
+        }
+        registry
+    }
+
+    /// 注册一个事件, 签名和 indexed 位置都相同的事件会被替换
+    pub fn add_event(&mut self, source: impl Into<String>, event: Event) -> &mut Self {
+        let entries = if event.anonymous {
+            &mut self.anonymous
+        } else {
+            self.events.entry(event.selector()).or_default()
+        };
+        let entry = RegisteredEvent {
+            source: source.into(),
+            event,
+        };
+        match entries
+            .iter_mut()
+            .find(|registered| registered.same_shape(&entry.event))
+        {
+            Some(registered) => *registered = entry,
+            None => entries.push(entry),
+        }
+        self
+    }
+
+    /// 注册 ABI 中的所有事件, 返回事件数量
+    pub fn add_abi(&mut self, source: &str, abi: &JsonAbi) -> usize {
+        let mut count = 0;
+        for event in abi.events() {
+            self.add_event(source, event.clone());
+            count += 1;
+        }
+        count
+    }
+
+    /// 读取 JsonAbi 文件, 也支持包含 abi 字段的编译产物(例如 foundry 的 out/*.json), 来源为文件名
+    pub fn load_abi_file(&mut self, path: impl AsRef<Path>) -> Result<usize> {
+        let (source, abi) = read_abi_file(path)?;
+        Ok(self.add_abi(&source, &abi))
+    }
+
+    /// 注册的事件数量
+    pub fn len(&self) -> usize {
+        self.events.values().map(Vec::len).sum::<usize>() + self.anonymous.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 解析 log, 没有匹配的事件时返回 None
+    pub fn decode(&self, log: &LogData) -> Option<DecodedLog<'_>> {
+        let topics = log.topics();
+        let candidates = topics
+            .first()
+            .and_then(|selector| self.events.get(selector))
+            .into_iter()
+            .flatten()
+            .filter(|registered| registered.indexed_count() + 1 == topics.len());
+        let anonymous = self
+            .anonymous
+            .iter()
+            .filter(|registered| registered.indexed_count() == topics.len());
+
+        candidates.chain(anonymous).find_map(|registered| {
+            let decoded = registered.event.decode_log(log, true).ok()?;
+            // topic 中的值类型必须是规范的编码, 例如 address 的高 12 字节为 0
+            let indexed_topics = &topics[usize::from(!registered.event.anonymous)..];
+            let canonical = decoded
+                .indexed
+                .iter()
+                .zip(indexed_topics)
+                .all(|(value, topic)| value.as_word().is_none_or(|word| word == *topic));
+            if !canonical {
+                return None;
+            }
+            Some(DecodedLog {
+                source: &registered.source,
+                event: &registered.event,
+                params: merge_params(&registered.event, decoded.indexed, decoded.body)?,
+            })
+        })
+    }
+}
+
+/// 解析出来的一个参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedParam {
+    pub name: String,
+    pub ty: String,
+    pub indexed: bool,
+    /// indexed 的动态类型(string, bytes, 数组和 struct)在 topic 中只保存 keccak256 哈希,
+    /// 此时 value 是 32 字节的哈希, 无法还原原始值
+    pub hashed: bool,
+    pub value: DynSolValue,
+}
+
+/// 解析后的 log
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedLog<'a> {
+    pub source: &'a str,
+    pub event: &'a Event,
+    /// 按照事件参数的顺序, 包括 indexed 和非 indexed 参数
+    pub params: Vec<DecodedParam>,
+}
+
+impl DecodedLog<'_> {
+    pub fn param(&self, name: &str) -> Option<&DynSolValue> {
+        self.params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| &param.value)
+    }
+
+    /// 参数名到值的 json 对象, 没有名字的参数使用位置作为 key
+    pub fn to_json(&self) -> Value {
+        let fields = self
+            .params
+            .iter()
+            .map(|param| (param.name.clone(), value_to_json(&param.value)))
+            .collect::<Map<_, _>>();
+        Value::Object(fields)
+    }
+}
+
+impl fmt::Display for DecodedLog<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}(", self.source, self.event.name)?;
+        for (i, param) in self.params.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            let value = value_to_string(&param.value);
+            let hashed = if param.hashed { " (hash)" } else { "" };
+            write!(f, "{}={value}{hashed}", param.name)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// 读取 JsonAbi 文件, 也支持包含 abi 字段的编译产物, 返回文件名和 ABI
+pub(crate) fn read_abi_file(path: impl AsRef<Path>) -> Result<(String, JsonAbi)> {
+    let path = path.as_ref();
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| eyre!("read abi {} failed, {e}", path.display()))?;
+    let mut value: Value = serde_json::from_str(&json)?;
+    if let Some(abi) = value.get_mut("abi") {
+        value = abi.take();
+    }
+    let abi: JsonAbi = serde_json::from_value(value)?;
+    let source = path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    Ok((source, abi))
+}
+
+/// 按照参数顺序把 indexed 和非 indexed 的值合并
+fn merge_params(
+    event: &Event,
+    indexed: Vec<DynSolValue>,
+    body: Vec<DynSolValue>,
+) -> Option<Vec<DecodedParam>> {
+    let mut indexed = indexed.into_iter();
+    let mut body = body.into_iter();
+    event
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            let value = if input.indexed {
+                indexed.next()?
+            } else {
+                body.next()?
+            };
+            let hashed = input.indexed && input.resolve().is_ok_and(|ty| !is_value_type(&ty));
+            let name = if input.name.is_empty() {
+                i.to_string()
+            } else {
+                input.name.clone()
+            };
+            Some(DecodedParam {
+                name,
+                ty: input.selector_type().into_owned(),
+                indexed: input.indexed,
+                hashed,
+                value,
+            })
+        })
+        .collect()
+}
+
+/// 值类型直接保存在 topic 中, 其他类型保存哈希
+fn is_value_type(ty: &DynSolType) -> bool {
+    matches!(
+        ty,
+        DynSolType::Bool
+            | DynSolType::Int(_)
+            | DynSolType::Uint(_)
+            | DynSolType::FixedBytes(_)
+            | DynSolType::Address
+            | DynSolType::Function
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, Bytes, U256, address, b256, keccak256};
+    use alloy::sol_types::SolValue;
+
+    use super::*;
+
+    const FROM: Address = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+    const TO: Address = address!("1f9840a85d5aF5bf1D1762F925BDADdC4201F984");
+
+    fn registry() -> EventRegistry {
+        let mut registry = EventRegistry::with_common();
+        registry
+            .load_abi_file(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/abi/NameRegistry.json"
+            ))
+            .unwrap();
+        registry
+    }
+
+    /// ERC-20 和 ERC-721 的 Transfer 签名相同, 按 indexed 数量区分
+    #[test]
+    fn erc20_erc721_transfer() {
+        let registry = registry();
+        let transfer = keccak256("Transfer(address,address,uint256)");
+
+        // ERC-20: value 在 data 中
+        let erc20 = LogData::new_unchecked(
+            vec![transfer, FROM.into_word(), TO.into_word()],
+            U256::from(1_000_000).abi_encode().into(),
+        );
+        let decoded = registry.decode(&erc20).unwrap();
+        assert_eq!(decoded.source, "ERC20");
+        assert_eq!(decoded.param("from"), Some(&DynSolValue::Address(FROM)));
+        assert_eq!(
+            decoded.param("value"),
+            Some(&DynSolValue::Uint(U256::from(1_000_000), 256))
+        );
+
+        // ERC-721: tokenId 是 indexed, data 为空
+        let erc721 = LogData::new_unchecked(
+            vec![
+                transfer,
+                FROM.into_word(),
+                TO.into_word(),
+                B256::from(U256::from(42)),
+            ],
+            Bytes::new(),
+        );
+        let decoded = registry.decode(&erc721).unwrap();
+        assert_eq!(decoded.source, "ERC721");
+        assert_eq!(
+            decoded.param("tokenId"),
+            Some(&DynSolValue::Uint(U256::from(42), 256))
+        );
+
+        // topic 中的地址高位不为 0, 不是合法的 Transfer
+        let invalid = LogData::new_unchecked(
+            vec![transfer, B256::repeat_byte(0xff), TO.into_word()],
+            U256::from(1).abi_encode().into(),
+        );
+        assert!(registry.decode(&invalid).is_none());
+    }
+
+    /// indexed 的动态类型在 topic 中是哈希
+    #[test]
+    fn hashed_indexed_params() {
+        let registry = registry();
+        let log = LogData::new_unchecked(
+            vec![
+                keccak256("NameRegistered(string,address,string)"),
+                keccak256("alice"),
+                FROM.into_word(),
+            ],
+            ("alice.eth".to_string(),).abi_encode_params().into(),
+        );
+        let decoded = registry.decode(&log).unwrap();
+        assert_eq!(decoded.source, "NameRegistry");
+        assert!(decoded.params[0].hashed);
+        assert!(!decoded.params[1].hashed);
+        assert_eq!(
+            decoded.param("name"),
+            Some(&DynSolValue::FixedBytes(keccak256("alice"), 32))
+        );
+        assert_eq!(decoded.param("owner"), Some(&DynSolValue::Address(FROM)));
+        assert_eq!(
+            decoded.param("label"),
+            Some(&DynSolValue::String("alice.eth".into()))
+        );
+    }
+
+    /// 匿名事件没有签名 topic, 按 topic 数量和 data 匹配
+    #[test]
+    fn anonymous_event() {
+        let registry = registry();
+        let note = LogData::new_unchecked(
+            vec![
+                b256!("0xa9059cbb00000000000000000000000000000000000000000000000000000000"),
+                FROM.into_word(),
+            ],
+            (Bytes::from_static(b"note"),).abi_encode_params().into(),
+        );
+        let decoded = registry.decode(&note).unwrap();
+        assert!(decoded.event.anonymous);
+        assert_eq!(decoded.event.name, "Note");
+        assert_eq!(decoded.param("caller"), Some(&DynSolValue::Address(FROM)));
+
+        // 未注册的事件
+        let unknown = LogData::new_unchecked(
+            vec![keccak256("Unknown(uint256)")],
+            U256::from(1).abi_encode().into(),
+        );
+        assert!(registry.decode(&unknown).is_none());
+    }
+
+    /// OpenZeppelin v4 和 v5 的 Initialized
+    #[test]
+    fn initialized_versions() {
+        let registry = EventRegistry::with_common();
+        for (signature, bits) in [("Initialized(uint8)", 8), ("Initialized(uint64)", 64)] {
+            let log = LogData::new_unchecked(
+                vec![keccak256(signature)],
+                U256::from(1).abi_encode().into(),
+            );
+            let decoded = registry.decode(&log).unwrap();
+            assert_eq!(decoded.source, "Initializable");
+            assert_eq!(
+                decoded.param("version"),
+                Some(&DynSolValue::Uint(U256::from(1), bits))
+            );
+        }
+    }
+}
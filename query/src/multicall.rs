@@ -0,0 +1,264 @@
+//! 使用 Multicall3 的 aggregate3 把多个合约读取合并成一次 eth_call
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use alloy::eips::BlockId;
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, Bytes};
+use alloy::providers::bindings::IMulticall3::{self, Call3, aggregate3Call};
+use alloy::providers::ext::AnvilApi;
+use alloy::providers::{MulticallItem, Provider};
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol_types::{SolCall, decode_revert_reason};
+use eyre::{Result, bail};
+
+pub use alloy::providers::MULTICALL3_ADDRESS;
+
+/// 单个调用失败的原因, 不影响同一批次中的其他调用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallError {
+    /// 调用 revert, 包含 revert data
+    Reverted(Bytes),
+    /// 调用成功但是返回值无法按函数的返回类型解码, 例如调用的地址没有代码
+    Decode { data: Bytes, error: String },
+    /// 句柄来自另一个 [`Multicall`]
+    ForeignHandle { index: usize },
+    /// 句柄在发送之后才添加, 不在这次的结果中
+    MissingResult { index: usize, len: usize },
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reverted(data) => match decode_revert_reason(data) {
+                Some(reason) => write!(f, "call reverted: {reason}"),
+                None => write!(f, "call reverted with {data}"),
+            },
+            Self::Decode { data, error } => write!(f, "decode return data {data} failed, {error}"),
+            Self::ForeignHandle { index } => {
+                write!(f, "call handle {index} belongs to another multicall")
+            }
+            Self::MissingResult { index, len } => {
+                write!(
+                    f,
+                    "call handle {index} added after the call, only {len} results"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+/// [`Multicall::add`] 返回的句柄, 用于从 [`MulticallResults`] 中取出对应类型的返回值
+#[derive(Debug)]
+pub struct CallHandle<D> {
+    batch: u64,
+    index: usize,
+    _decoder: PhantomData<fn() -> D>,
+}
+
+impl<D> Clone for CallHandle<D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for CallHandle<D> {}
+
+impl<D> CallHandle<D> {
+    /// 调用在批次中的位置
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// 把任意 `sol!(rpc)` 生成的 call builder 打包到一次 aggregate3 中
+///
+/// 每个调用都设置 allowFailure, 单个调用失败只体现在它自己的结果中;
+/// 在 anvil 上如果 Multicall3 不存在会自动部署
+pub struct Multicall<P> {
+    provider: P,
+    /// 区分不同 Multicall 的句柄
+    batch: u64,
+    address: Address,
+    block: BlockId,
+    calls: Vec<Call3>,
+}
+
+impl<P: Provider> Multicall<P> {
+    pub fn new(provider: P) -> Self {
+        static NEXT_BATCH: AtomicU64 = AtomicU64::new(0);
+        Self {
+            provider,
+            batch: NEXT_BATCH.fetch_add(1, Ordering::Relaxed),
+            address: MULTICALL3_ADDRESS,
+            block: BlockId::latest(),
+            calls: Vec::new(),
+        }
+    }
+
+    /// Multicall3 合约地址, 默认 [`MULTICALL3_ADDRESS`]
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// 读取的区块, 默认 latest
+    pub fn block(mut self, block: BlockId) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// 添加一个调用, 例如 `counter.number()`
+    pub fn add<C: MulticallItem>(&mut self, call: C) -> CallHandle<C::Decoder> {
+        self.calls.push(Call3 {
+            target: call.target(),
+            allowFailure: true,
+            callData: call.input(),
+        });
+        CallHandle {
+            batch: self.batch,
+            index: self.calls.len() - 1,
+            _decoder: PhantomData,
+        }
+    }
+
+    /// 添加多个同类型的调用
+    pub fn extend<C: MulticallItem>(
+        &mut self,
+        calls: impl IntoIterator<Item = C>,
+    ) -> Vec<CallHandle<C::Decoder>> {
+        calls.into_iter().map(|call| self.add(call)).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// 发送 aggregate3 eth_call
+    pub async fn call(&self) -> Result<MulticallResults> {
+        if self.calls.is_empty() {
+            return Ok(MulticallResults {
+                batch: self.batch,
+                results: Vec::new(),
+            });
+        }
+
+        let input = aggregate3Call {
+            calls: self.calls.clone(),
+        }
+        .abi_encode();
+        let tx = TransactionRequest::default()
+            .with_to(self.address)
+            .with_input(input);
+        let mut output = self.provider.call(tx.clone()).block(self.block).await?;
+        // 调用没有代码的地址会成功并返回空数据
+        if output.is_empty() {
+            if self.address != MULTICALL3_ADDRESS || !ensure_multicall3(&self.provider).await? {
+                bail!("no Multicall3 contract at {}", self.address);
+            }
+            output = self.provider.call(tx).block(self.block).await?;
+            // anvil_setCode 只修改当前状态, 之前的区块中仍然没有 Multicall3
+            if output.is_empty() {
+                bail!(
+                    "no Multicall3 contract at {} in block {}, it was deployed with anvil_setCode after that block",
+                    self.address,
+                    self.block
+                );
+            }
+        }
+
+        let results = aggregate3Call::abi_decode_returns(&output, false)?.returnData;
+        if results.len() != self.calls.len() {
+            bail!(
+                "aggregate3 returned {} results for {} calls",
+                results.len(),
+                self.calls.len()
+            );
+        }
+        Ok(MulticallResults {
+            batch: self.batch,
+            results,
+        })
+    }
+}
+
+/// aggregate3 的结果, 通过 [`CallHandle`] 解码
+#[derive(Debug, Clone)]
+pub struct MulticallResults {
+    batch: u64,
+    results: Vec<IMulticall3::Result>,
+}
+
+impl MulticallResults {
+    /// 解码单个调用的返回值, 句柄必须来自同一个 [`Multicall`] 并且在发送之前添加
+    pub fn get<D: SolCall>(&self, handle: CallHandle<D>) -> Result<D::Return, CallError> {
+        if handle.batch != self.batch {
+            return Err(CallError::ForeignHandle {
+                index: handle.index,
+            });
+        }
+        let result = self
+            .results
+            .get(handle.index)
+            .ok_or(CallError::MissingResult {
+                index: handle.index,
+                len: self.results.len(),
+            })?;
+        if !result.success {
+            return Err(CallError::Reverted(result.returnData.clone()));
+        }
+        D::abi_decode_returns(&result.returnData, true).map_err(|e| CallError::Decode {
+            data: result.returnData.clone(),
+            error: e.to_string(),
+        })
+    }
+
+    /// 按顺序解码一组同类型调用的返回值
+    pub fn get_all<D: SolCall>(
+        &self,
+        handles: &[CallHandle<D>],
+    ) -> Vec<Result<D::Return, CallError>> {
+        handles.iter().map(|handle| self.get(*handle)).collect()
+    }
+
+    /// 未解码的结果, 按添加的顺序
+    pub fn raw(&self) -> &[IMulticall3::Result] {
+        &self.results
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+}
+
+/// 如果 [`MULTICALL3_ADDRESS`] 没有代码并且节点是 anvil, 通过 anvil_setCode 部署 Multicall3
+///
+/// 返回调用结束后 Multicall3 是否可用
+pub async fn ensure_multicall3<P: Provider>(provider: &P) -> Result<bool> {
+    if !provider.get_code_at(MULTICALL3_ADDRESS).await?.is_empty() {
+        return Ok(true);
+    }
+    let client_version = provider.get_client_version().await?;
+    if !client_version.to_lowercase().starts_with("anvil") {
+        return Ok(false);
+    }
+
+    // 不发送交易, 通过 eth_call 执行 creation code 得到 runtime code
+    let tx = TransactionRequest::default().with_deploy_code(IMulticall3::BYTECODE.clone());
+    let runtime_code = provider.call(tx).await?;
+    provider
+        .anvil_set_code(MULTICALL3_ADDRESS, runtime_code)
+        .await?;
+    Ok(true)
+}
@@ -0,0 +1,242 @@
+//! 识别代理合约, 并找到最终的实现合约
+use std::fmt;
+
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, B256, Bytes, U256, b256, bytes, fixed_bytes};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
+use eyre::{Result, bail};
+
+use crate::bytecode;
+use crate::storage::{BatchSource, SlotSource};
+
+/// EIP-1967 bytes32(uint256(keccak256('eip1967.proxy.implementation')) - 1)
+pub const EIP1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+/// EIP-1967 bytes32(uint256(keccak256('eip1967.proxy.admin')) - 1)
+pub const EIP1967_ADMIN_SLOT: B256 =
+    b256!("0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103");
+/// EIP-1967 bytes32(uint256(keccak256('eip1967.proxy.beacon')) - 1)
+pub const EIP1967_BEACON_SLOT: B256 =
+    b256!("0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50");
+/// EIP-1822 keccak256("PROXIABLE")
+pub const EIP1822_PROXIABLE_SLOT: B256 =
+    b256!("0xc5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7");
+
+/// Safe 的 singleton(masterCopy) 存放在 slot 0,
+/// 代理合约用 PUSH32 masterCopy() 的 selector 判断是否直接返回 singleton
+const SAFE_MASTER_COPY_WORD: B256 =
+    b256!("0xa619486e00000000000000000000000000000000000000000000000000000000");
+
+/// EIP-1167 minimal proxy: <prefix> <20 字节实现地址> <suffix>
+const EIP1167_PREFIX: Bytes = bytes!("363d3d373d3d3d363d73");
+const EIP1167_SUFFIX: Bytes = bytes!("5af43d82803e903d91602b57fd5bf3");
+/// 使用 PUSH0 的版本(ERC-7511)
+const EIP7511_PREFIX: Bytes = bytes!("365f5f375f5f365f73");
+const EIP7511_SUFFIX: Bytes = bytes!("5af43d5f5f3e5f3d91602a57fd5bf3");
+
+/// beacon 的 implementation()
+const IMPLEMENTATION_SELECTOR: [u8; 4] = fixed_bytes!("0x5c60da1b").0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyKind {
+    /// EIP-1967 transparent 或者 UUPS 代理
+    Eip1967,
+    /// EIP-1967 beacon 代理, 实现地址从 beacon 合约读取
+    Eip1967Beacon,
+    /// EIP-1167 minimal proxy(clone)
+    Eip1167,
+    /// EIP-1822 UUPS
+    Eip1822,
+    /// Safe 的 proxy, 实现地址是 singleton
+    Safe,
+}
+
+impl fmt::Display for ProxyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// 一层代理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHop {
+    pub proxy: Address,
+    pub kind: ProxyKind,
+    pub implementation: Address,
+    /// EIP-1967 admin slot 中的地址
+    pub admin: Option<Address>,
+    /// beacon 代理的 beacon 合约
+    pub beacon: Option<Address>,
+}
+
+/// 从最外层的代理一直到实现合约
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyResolution {
+    pub address: Address,
+    pub hops: Vec<ProxyHop>,
+}
+
+impl ProxyResolution {
+    /// 最终的实现合约, 不是代理时为地址本身
+    pub fn implementation(&self) -> Address {
+        self.hops
+            .last()
+            .map_or(self.address, |hop| hop.implementation)
+    }
+
+    /// 经过的所有地址, 包括最外层的地址和最终的实现合约
+    pub fn addresses(&self) -> Vec<Address> {
+        std::iter::once(self.address)
+            .chain(self.hops.iter().map(|hop| hop.implementation))
+            .collect()
+    }
+}
+
+/// 使用 get_code_at 和 get_storage_at 识别代理合约
+pub struct ProxyResolver<P> {
+    provider: P,
+    block: BlockId,
+    max_depth: usize,
+}
+
+impl<P: Provider + Clone> ProxyResolver<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            block: BlockId::latest(),
+            max_depth: 8,
+        }
+    }
+
+    /// 读取的区块, 默认 latest
+    pub fn block(mut self, block: BlockId) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// 最多跟踪的代理层数, 默认 8
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// 一直跟踪到不是代理的合约
+    pub async fn resolve(&self, address: Address) -> Result<ProxyResolution> {
+        let mut hops: Vec<ProxyHop> = Vec::new();
+        let mut current = address;
+        while let Some(hop) = self.detect(current).await? {
+            if hop.implementation == address || hops.iter().any(|h| h.proxy == hop.implementation) {
+                bail!("proxy loop at {}", hop.implementation);
+            }
+            if hops.len() == self.max_depth {
+                bail!("more than {} proxy hops from {address}", self.max_depth);
+            }
+            current = hop.implementation;
+            hops.push(hop);
+        }
+        Ok(ProxyResolution { address, hops })
+    }
+
+    /// 判断 address 是不是代理, 是的话返回下一层的实现地址
+    pub async fn detect(&self, address: Address) -> Result<Option<ProxyHop>> {
+        let code = self
+            .provider
+            .get_code_at(address)
+            .block_id(self.block)
+            .await?;
+        if code.is_empty() {
+            return Ok(None);
+        }
+
+        let hop = |kind, implementation| ProxyHop {
+            proxy: address,
+            kind,
+            implementation,
+            admin: None,
+            beacon: None,
+        };
+
+        // minimal proxy 的实现地址直接写在 bytecode 中
+        if let Some(implementation) = minimal_proxy_implementation(&code) {
+            return Ok(Some(hop(ProxyKind::Eip1167, implementation)));
+        }
+
+        // 一次 batch 请求读取所有候选 slot
+        let source = BatchSource::new(self.provider.clone(), address, self.block);
+        let slots = [
+            EIP1967_IMPLEMENTATION_SLOT,
+            EIP1967_ADMIN_SLOT,
+            EIP1967_BEACON_SLOT,
+            EIP1822_PROXIABLE_SLOT,
+            B256::ZERO,
+        ]
+        .map(|slot| U256::from_be_bytes(slot.0));
+        source.prefetch(&slots).await?;
+        let mut values = [None; 5];
+        for (value, slot) in values.iter_mut().zip(slots) {
+            *value = word_address(source.storage_at(slot).await?);
+        }
+        let [implementation, admin, beacon, proxiable, slot0] = values;
+
+        if let Some(implementation) = implementation {
+            return Ok(Some(ProxyHop {
+                admin,
+                ..hop(ProxyKind::Eip1967, implementation)
+            }));
+        }
+        if let Some(beacon) = beacon {
+            let implementation = self.beacon_implementation(beacon).await?;
+            return Ok(Some(ProxyHop {
+                beacon: Some(beacon),
+                ..hop(ProxyKind::Eip1967Beacon, implementation)
+            }));
+        }
+        if let Some(implementation) = proxiable {
+            return Ok(Some(hop(ProxyKind::Eip1822, implementation)));
+        }
+
+        let info = bytecode::inspect(&code);
+        let is_safe = info
+            .push_words
+            .iter()
+            .any(|word| word.value == SAFE_MASTER_COPY_WORD);
+        Ok(slot0
+            .filter(|_| is_safe)
+            .map(|singleton| hop(ProxyKind::Safe, singleton)))
+    }
+
+    /// 调用 beacon 的 implementation()
+    async fn beacon_implementation(&self, beacon: Address) -> Result<Address> {
+        let tx = TransactionRequest::default()
+            .to(beacon)
+            .input(Bytes::from(IMPLEMENTATION_SELECTOR).into());
+        let output = self.provider.call(tx).block(self.block).await?;
+        if output.len() < 32 {
+            bail!("beacon {beacon} implementation() returned {output}");
+        }
+        word_address(U256::from_be_slice(&output[..32]))
+            .ok_or_else(|| eyre::eyre!("beacon {beacon} has no implementation"))
+    }
+}
+
+/// EIP-1167 和 ERC-7511 minimal proxy 中的实现地址
+pub fn minimal_proxy_implementation(code: &[u8]) -> Option<Address> {
+    [
+        (&EIP1167_PREFIX, &EIP1167_SUFFIX),
+        (&EIP7511_PREFIX, &EIP7511_SUFFIX),
+    ]
+    .into_iter()
+    .find_map(|(prefix, suffix)| {
+        let rest = code.strip_prefix(prefix.as_ref())?;
+        let address = rest.strip_suffix(suffix.as_ref())?;
+        (address.len() == 20).then(|| Address::from_slice(address))
+    })
+}
+
+/// slot 中存放的地址, 高 12 字节必须为 0, 值为 0 时返回 None
+fn word_address(word: U256) -> Option<Address> {
+    let bytes = word.to_be_bytes::<32>();
+    let address = Address::from_slice(&bytes[12..]);
+    (bytes[..12].iter().all(|b| *b == 0) && !address.is_zero()).then_some(address)
+}
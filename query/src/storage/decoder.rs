@@ -0,0 +1,251 @@
+use alloy::dyn_abi::DynSolValue;
+use alloy::primitives::{Address, B256, I256, U256};
+use eyre::{Result, bail, eyre};
+use futures_util::future::BoxFuture;
+
+use super::layout::{Encoding, StorageLayout};
+use super::slot::{MappingKey, array_data_slot, element_position, mapping_slot};
+use super::source::SlotSource;
+
+/// 根据 solc 的 storageLayout 解析合约的状态变量
+///
+/// 支持打包的值类型, struct, 定长数组, 动态数组, string/bytes 以及指定 key 的 mapping,
+/// 返回 [`DynSolValue`], struct 解析为 Tuple
+pub struct StorageDecoder<S> {
+    source: S,
+    layout: StorageLayout,
+}
+
+impl<S: SlotSource + Sync> StorageDecoder<S> {
+    pub fn new(source: S, layout: StorageLayout) -> Self {
+        Self { source, layout }
+    }
+
+    pub fn layout(&self) -> &StorageLayout {
+        &self.layout
+    }
+
+    /// 读取一个状态变量
+    pub async fn read(&self, label: &str) -> Result<DynSolValue> {
+        let item = self.layout.item(label)?;
+        self.prefetch(&item.type_id, [item.slot]).await?;
+        self.decode(&item.type_id, item.slot, item.offset).await
+    }
+
+    /// 按 keys 依次读取 mapping 中的值, 例如 balances[owner], allowance[owner][spender]
+    pub async fn read_mapping(&self, label: &str, keys: &[MappingKey]) -> Result<DynSolValue> {
+        let item = self.layout.item(label)?;
+        let mut type_id = item.type_id.as_str();
+        let mut slot = item.slot;
+        for key in keys {
+            let info = self.layout.type_info(type_id)?;
+            if info.encoding != Encoding::Mapping {
+                bail!("{} is not a mapping", info.label);
+            }
+            slot = mapping_slot(key, slot);
+            type_id = info
+                .value
+                .as_deref()
+                .ok_or_else(|| eyre!("mapping {} without value type", info.label))?;
+        }
+        self.prefetch(type_id, [slot]).await?;
+        self.decode(type_id, slot, 0).await
+    }
+
+    /// 状态变量不依赖其他数据就能确定的 slot, 可以和其他 slot 放到同一批中 prefetch
+    pub fn slots(&self, label: &str) -> Result<Vec<U256>> {
+        let item = self.layout.item(label)?;
+        let mut slots = Vec::new();
+        self.static_slots(&item.type_id, item.slot, &mut slots)?;
+        // 打包在同一个 slot 中的值只需要读取一次
+        slots.sort_unstable();
+        slots.dedup();
+        Ok(slots)
+    }
+
+    /// 预先读取 type_id 类型的值在这些位置上不依赖其他数据就能确定的 slot,
+    /// 动态数组和 string/bytes 的数据要等读到长度之后再预读
+    async fn prefetch(&self, type_id: &str, slots: impl IntoIterator<Item = U256>) -> Result<()> {
+        let mut needed = Vec::new();
+        for slot in slots {
+            self.static_slots(type_id, slot, &mut needed)?;
+        }
+        needed.sort_unstable();
+        needed.dedup();
+        self.source.prefetch(&needed).await
+    }
+
+    fn static_slots(&self, type_id: &str, slot: U256, out: &mut Vec<U256>) -> Result<()> {
+        let info = self.layout.type_info(type_id)?;
+        match info.encoding {
+            Encoding::Inplace => {
+                if let Some(members) = &info.members {
+                    for member in members {
+                        self.static_slots(&member.type_id, slot.wrapping_add(member.slot), out)?;
+                    }
+                } else if let Some(base) = &info.base {
+                    let len = fixed_array_len(&info.label)?;
+                    let elem = self.layout.type_info(base)?;
+                    for i in 0..len {
+                        let (elem_slot, _) = element_position(slot, i, elem.number_of_bytes);
+                        self.static_slots(base, elem_slot, out)?;
+                    }
+                } else {
+                    out.push(slot);
+                }
+            }
+            Encoding::DynamicArray | Encoding::Bytes => out.push(slot),
+            Encoding::Mapping => {}
+        }
+        Ok(())
+    }
+
+    fn decode<'a>(
+        &'a self,
+        type_id: &'a str,
+        slot: U256,
+        offset: usize,
+    ) -> BoxFuture<'a, Result<DynSolValue>> {
+        Box::pin(async move {
+            let info = self.layout.type_info(type_id)?;
+            match info.encoding {
+                Encoding::Inplace => {
+                    if let Some(members) = &info.members {
+                        // struct 从新的 slot 开始, 成员的 slot 是相对 struct 起始 slot 的偏移
+                        let mut values = Vec::with_capacity(members.len());
+                        for member in members {
+                            let member_slot = slot.wrapping_add(member.slot);
+                            values.push(
+                                self.decode(&member.type_id, member_slot, member.offset)
+                                    .await?,
+                            );
+                        }
+                        Ok(DynSolValue::Tuple(values))
+                    } else if let Some(base) = &info.base {
+                        // 定长数组, 长度只能从类型名中获取, 例如 uint256[3]
+                        let len = fixed_array_len(&info.label)?;
+                        let values = self.decode_elements(base, slot, len).await?;
+                        Ok(DynSolValue::FixedArray(values))
+                    } else {
+                        let word = self.source.storage_at(slot).await?;
+                        decode_value(type_id, word, offset, info.number_of_bytes)
+                    }
+                }
+                Encoding::DynamicArray => {
+                    let base = info
+                        .base
+                        .as_deref()
+                        .ok_or_else(|| eyre!("array {} without base type", info.label))?;
+                    // slot 中存放数组长度, 元素从 keccak256(slot) 开始
+                    let len: usize = self.source.storage_at(slot).await?.try_into()?;
+                    let values = self
+                        .decode_elements(base, array_data_slot(slot), len)
+                        .await?;
+                    Ok(DynSolValue::Array(values))
+                }
+                Encoding::Bytes => {
+                    let bytes = self.read_bytes(slot).await?;
+                    if type_id.starts_with("t_string") {
+                        Ok(DynSolValue::String(String::from_utf8(bytes)?))
+                    } else {
+                        Ok(DynSolValue::Bytes(bytes))
+                    }
+                }
+                Encoding::Mapping => {
+                    bail!("mapping {} can only be read with keys", info.label)
+                }
+            }
+        })
+    }
+
+    /// 依次解析数组元素
+    async fn decode_elements(
+        &self,
+        base: &str,
+        start: U256,
+        len: usize,
+    ) -> Result<Vec<DynSolValue>> {
+        let elem = self.layout.type_info(base)?;
+        let positions: Vec<_> = (0..len)
+            .map(|i| element_position(start, i, elem.number_of_bytes))
+            .collect();
+        self.prefetch(base, positions.iter().map(|(slot, _)| *slot))
+            .await?;
+
+        let mut values = Vec::with_capacity(len);
+        for (slot, offset) in positions {
+            values.push(self.decode(base, slot, offset).await?);
+        }
+        Ok(values)
+    }
+
+    /// string 和 bytes
+    ///
+    /// 长度小于 32 字节时, 数据存放在 slot 的高位, 最低字节为 len * 2;
+    /// 否则 slot 中存放 len * 2 + 1, 数据从 keccak256(slot) 开始
+    async fn read_bytes(&self, slot: U256) -> Result<Vec<u8>> {
+        let word = self.source.storage_at(slot).await?;
+        if !word.bit(0) {
+            let len = (word.byte(0) / 2) as usize;
+            return Ok(word.to_be_bytes::<32>()[..len].to_vec());
+        }
+
+        let len: usize = ((word - U256::from(1)) / U256::from(2)).try_into()?;
+        let start = array_data_slot(slot);
+        let chunks: Vec<_> = (0..len.div_ceil(32))
+            .map(|i| start.wrapping_add(U256::from(i)))
+            .collect();
+        self.source.prefetch(&chunks).await?;
+
+        let mut data = Vec::with_capacity(chunks.len() * 32);
+        for chunk in chunks {
+            let chunk = self.source.storage_at(chunk).await?;
+            data.extend_from_slice(&chunk.to_be_bytes::<32>());
+        }
+        data.truncate(len);
+        Ok(data)
+    }
+}
+
+/// 从类型名中解析定长数组的长度, 例如 uint256[3], struct S[2][4]
+fn fixed_array_len(label: &str) -> Result<usize> {
+    let len = label
+        .strip_suffix(']')
+        .and_then(|s| s.rsplit_once('['))
+        .map(|(_, len)| len)
+        .ok_or_else(|| eyre!("invalid fixed array type {label}"))?;
+    Ok(len.parse()?)
+}
+
+/// 从 slot 中取出 offset 开始的 size 个字节, 按类型解析
+fn decode_value(type_id: &str, word: U256, offset: usize, size: usize) -> Result<DynSolValue> {
+    if size == 0 || offset + size > 32 {
+        bail!("invalid position offset {offset} size {size} for {type_id}");
+    }
+    let word = word.to_be_bytes::<32>();
+    let value = &word[32 - offset - size..32 - offset];
+
+    let value = if type_id == "t_bool" {
+        DynSolValue::Bool(value[size - 1] != 0)
+    } else if type_id.starts_with("t_address") || type_id.starts_with("t_contract") {
+        DynSolValue::Address(Address::from_slice(value))
+    } else if type_id.starts_with("t_int") {
+        // 负数需要符号扩展到 32 字节
+        let mut buf = if value[0] & 0x80 != 0 {
+            [0xff; 32]
+        } else {
+            [0; 32]
+        };
+        buf[32 - size..].copy_from_slice(value);
+        DynSolValue::Int(I256::from_be_bytes(buf), size * 8)
+    } else if type_id.starts_with("t_bytes") {
+        // bytesN 是左对齐的
+        let mut buf = B256::ZERO;
+        buf[..size].copy_from_slice(value);
+        DynSolValue::FixedBytes(buf, size)
+    } else {
+        // uint, enum, 用户自定义值类型
+        DynSolValue::Uint(U256::from_be_slice(value), size * 8)
+    };
+    Ok(value)
+}
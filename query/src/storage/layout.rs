@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use alloy::primitives::U256;
+use eyre::{Result, eyre};
+use serde::{Deserialize, Deserializer};
+
+/// solc 输出的 storageLayout
+///
+/// solc --storage-layout Contract.sol 或者 foundry 的 `forge inspect Contract storageLayout`
+/// 参考: https://docs.soliditylang.org/en/latest/internals/layout_in_storage.html
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageLayout {
+    pub storage: Vec<StorageItem>,
+    #[serde(default)]
+    pub types: BTreeMap<String, TypeInfo>,
+}
+
+impl StorageLayout {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| eyre!("read storage layout {} failed, {e}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    /// 根据变量名查找状态变量
+    pub fn item(&self, label: &str) -> Result<&StorageItem> {
+        self.storage
+            .iter()
+            .find(|item| item.label == label)
+            .ok_or_else(|| eyre!("state variable {label} not found in storage layout"))
+    }
+
+    pub fn type_info(&self, type_id: &str) -> Result<&TypeInfo> {
+        self.types
+            .get(type_id)
+            .ok_or_else(|| eyre!("type {type_id} not found in storage layout"))
+    }
+}
+
+/// 一个状态变量, 或者 struct 的一个成员
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageItem {
+    pub label: String,
+    /// 在 slot 中的字节偏移, 从低位(右边)开始计算
+    pub offset: usize,
+    #[serde(deserialize_with = "deserialize_u256_str")]
+    pub slot: U256,
+    #[serde(rename = "type")]
+    pub type_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// 值类型, struct 和定长数组, 连续存放, 小于 32 字节的值会打包到同一个 slot
+    Inplace,
+    /// 数据从 keccak256(key . slot) 开始
+    Mapping,
+    /// slot 中存放长度, 数据从 keccak256(slot) 开始
+    DynamicArray,
+    /// string 和 bytes, 短数据直接存放在 slot 中, 长数据从 keccak256(slot) 开始
+    Bytes,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeInfo {
+    pub encoding: Encoding,
+    pub label: String,
+    #[serde(deserialize_with = "deserialize_usize_str")]
+    pub number_of_bytes: usize,
+    /// 数组元素类型
+    pub base: Option<String>,
+    /// mapping 的 key 类型
+    pub key: Option<String>,
+    /// mapping 的 value 类型
+    pub value: Option<String>,
+    /// struct 成员
+    pub members: Option<Vec<StorageItem>>,
+}
+
+fn deserialize_u256_str<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
+}
+
+fn deserialize_usize_str<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
+}
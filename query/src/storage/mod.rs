@@ -0,0 +1,12 @@
+//! 根据 storageLayout 读取和解析合约的 storage
+mod decoder;
+mod layout;
+mod proof;
+mod slot;
+mod source;
+
+pub use decoder::StorageDecoder;
+pub use layout::{Encoding, StorageItem, StorageLayout, TypeInfo};
+pub use proof::{ProofError, ProofSource};
+pub use slot::{MappingKey, SlotPath, array_data_slot, element_position, mapping_slot};
+pub use source::{BatchSource, ProviderSource, SlotSource};
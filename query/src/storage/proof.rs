@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use alloy::consensus::TrieAccount;
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, B256, U256, keccak256};
+use alloy::providers::Provider;
+use alloy::rpc::types::EIP1186AccountProofResponse;
+use alloy_trie::proof::{ProofVerificationError, verify_proof};
+use alloy_trie::{EMPTY_ROOT_HASH, KECCAK_EMPTY, Nibbles};
+use eyre::{Result, eyre};
+use futures_util::{StreamExt, TryStreamExt, stream};
+
+use super::source::SlotSource;
+
+/// 证明校验失败
+///
+/// 作为 eyre::Report 返回, 可以通过 `downcast_ref::<ProofError>()` 区分
+#[derive(Debug)]
+pub enum ProofError {
+    /// 账户证明和区块的 stateRoot 不匹配
+    Account {
+        address: Address,
+        source: ProofVerificationError,
+    },
+    /// storage 证明和账户的 storageHash 不匹配
+    Storage {
+        slot: U256,
+        source: ProofVerificationError,
+    },
+    /// 返回的证明不是请求的 slot
+    MissingSlot(U256),
+    /// 返回的证明不是请求的账户, 另一个账户的有效证明不能当作请求账户的数据
+    WrongAccount {
+        requested: Address,
+        returned: Address,
+    },
+    /// 区块头重新计算的哈希和返回的哈希或者指定的哈希不一致, stateRoot 不可信
+    Header { expected: B256, computed: B256 },
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Account { address, source } => {
+                write!(f, "invalid account proof for {address}: {source}")
+            }
+            Self::Storage { slot, source } => {
+                write!(f, "invalid storage proof for slot {slot:#x}: {source}")
+            }
+            Self::MissingSlot(slot) => write!(f, "no storage proof returned for slot {slot:#x}"),
+            Self::WrongAccount {
+                requested,
+                returned,
+            } => write!(f, "requested proof for {requested}, got {returned}"),
+            Self::Header { expected, computed } => {
+                write!(f, "block header hashes to {computed}, expected {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProofError {}
+
+/// 通过 eth_getProof 读取 slot, 并校验 Merkle 证明
+///
+/// 账户证明使用区块头中的 stateRoot 校验, storage 证明使用账户的 storageHash 校验,
+/// 区块头通过重新计算哈希校验, 不需要信任 RPC 节点返回的数据, 只需要信任区块哈希
+/// (例如和其他节点或者轻客户端对比, 然后通过 `BlockId::hash` 指定)
+pub struct ProofSource<P> {
+    provider: P,
+    address: Address,
+    block_hash: B256,
+    state_root: B256,
+    /// 每个 eth_getProof 请求中的 slot 数量
+    keys_per_request: usize,
+    concurrency: usize,
+    cache: Mutex<HashMap<U256, U256>>,
+}
+
+impl<P: Provider> ProofSource<P> {
+    /// 读取区块头, 之后的所有证明都使用这个区块的 stateRoot 校验
+    ///
+    /// 重新计算区块头的哈希, 和返回的哈希比较, block 为区块哈希时还要和它一致,
+    /// 否则节点可以返回真实的区块哈希和伪造的 stateRoot
+    pub async fn new(provider: P, address: Address, block: BlockId) -> Result<Self> {
+        let header = provider
+            .get_block(block)
+            .await?
+            .ok_or_else(|| eyre!("block {block} not found"))?
+            .header;
+        let computed = header.inner.hash_slow();
+        let expected = match block {
+            BlockId::Hash(hash) => hash.block_hash,
+            BlockId::Number(_) => header.hash,
+        };
+        if computed != expected || computed != header.hash {
+            return Err(ProofError::Header { expected, computed }.into());
+        }
+        Ok(Self::with_state_root(
+            provider,
+            address,
+            computed,
+            header.state_root,
+        ))
+    }
+
+    /// 使用已经信任的 stateRoot, 例如从轻客户端获取, 不再请求区块头
+    pub fn with_state_root(
+        provider: P,
+        address: Address,
+        block_hash: B256,
+        state_root: B256,
+    ) -> Self {
+        Self {
+            provider,
+            address,
+            block_hash,
+            state_root,
+            keys_per_request: 50,
+            concurrency: 4,
+            cache: Mutex::default(),
+        }
+    }
+
+    /// 默认 50
+    pub fn keys_per_request(mut self, keys_per_request: usize) -> Self {
+        self.keys_per_request = keys_per_request.max(1);
+        self
+    }
+
+    /// 默认 4
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn block_hash(&self) -> B256 {
+        self.block_hash
+    }
+
+    pub fn state_root(&self) -> B256 {
+        self.state_root
+    }
+
+    /// 读取并校验一批 slot
+    async fn fetch_verified(&self, slots: Vec<U256>) -> Result<Vec<(U256, U256)>> {
+        let keys = slots.iter().map(|slot| B256::from(*slot)).collect();
+        let proof = self
+            .provider
+            .get_proof(self.address, keys)
+            .block_id(self.block_hash.into())
+            .await?;
+
+        verify_response(self.state_root, self.address, slots, &proof)
+    }
+}
+
+/// 校验 eth_getProof 返回的账户证明和 slots 的 storage 证明, 返回 slots 的值
+fn verify_response(
+    state_root: B256,
+    address: Address,
+    slots: Vec<U256>,
+    proof: &EIP1186AccountProofResponse,
+) -> Result<Vec<(U256, U256)>> {
+    let exists = verify_account(state_root, address, proof)?;
+
+    slots
+        .into_iter()
+        .map(|slot| {
+            let key = B256::from(slot);
+            let storage = proof
+                .storage_proof
+                .iter()
+                .find(|storage| storage.key.as_b256() == key)
+                .ok_or(ProofError::MissingSlot(slot))?;
+            // 不存在的账户没有 storage, 账户证明已经证明了它不存在
+            if !exists {
+                return Ok((slot, U256::ZERO));
+            }
+
+            let expected = (!storage.value.is_zero()).then(|| alloy::rlp::encode(storage.value));
+            verify_proof(
+                proof.storage_hash,
+                Nibbles::unpack(keccak256(key)),
+                expected,
+                &storage.proof,
+            )
+            .map_err(|source| ProofError::Storage { slot, source })?;
+            Ok((slot, storage.value))
+        })
+        .collect()
+}
+
+/// 校验账户证明, 返回账户是否存在
+///
+/// trie 的 key 使用请求的地址, 不能使用返回的 address
+fn verify_account(
+    state_root: B256,
+    address: Address,
+    proof: &EIP1186AccountProofResponse,
+) -> Result<bool> {
+    if proof.address != address {
+        return Err(ProofError::WrongAccount {
+            requested: address,
+            returned: proof.address,
+        }
+        .into());
+    }
+    let account = TrieAccount {
+        nonce: proof.nonce,
+        balance: proof.balance,
+        storage_root: proof.storage_hash,
+        code_hash: proof.code_hash,
+    };
+    // 不存在的账户, 有的节点返回零值哈希, 有的返回空树和空代码的哈希
+    let exists = !(account.nonce == 0
+        && account.balance.is_zero()
+        && (account.storage_root == EMPTY_ROOT_HASH || account.storage_root.is_zero())
+        && (account.code_hash == KECCAK_EMPTY || account.code_hash.is_zero()));
+    let expected = exists.then(|| alloy::rlp::encode(account));
+
+    verify_proof(
+        state_root,
+        Nibbles::unpack(keccak256(address)),
+        expected,
+        &proof.account_proof,
+    )
+    .map_err(|source| ProofError::Account { address, source })?;
+    Ok(exists)
+}
+
+impl<P: Provider> SlotSource for ProofSource<P> {
+    async fn storage_at(&self, slot: U256) -> Result<U256> {
+        if let Some(value) = self.cache.lock().unwrap().get(&slot) {
+            return Ok(*value);
+        }
+        let values = self.fetch_verified(vec![slot]).await?;
+        let value = values[0].1;
+        self.cache.lock().unwrap().extend(values);
+        Ok(value)
+    }
+
+    async fn prefetch(&self, slots: &[U256]) -> Result<()> {
+        let missing: Vec<U256> = {
+            let cache = self.cache.lock().unwrap();
+            slots
+                .iter()
+                .filter(|slot| !cache.contains_key(slot))
+                .copied()
+                .collect()
+        };
+        let chunks: Vec<Vec<U256>> = missing
+            .chunks(self.keys_per_request)
+            .map(<[U256]>::to_vec)
+            .collect();
+
+        let values: Vec<(U256, U256)> = stream::iter(chunks)
+            .map(|chunk| self.fetch_verified(chunk))
+            .buffer_unordered(self.concurrency)
+            .try_concat()
+            .await?;
+        self.cache.lock().unwrap().extend(values);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::Bytes;
+    use alloy::rpc::types::EIP1186StorageProof;
+    use alloy_trie::HashBuilder;
+    use alloy_trie::proof::ProofRetainer;
+
+    use super::*;
+
+    /// 用 leaves 构造一棵 trie, 返回 root 和 target 的证明
+    fn build_trie(mut leaves: Vec<(B256, Vec<u8>)>, targets: &[B256]) -> (B256, Vec<Vec<Bytes>>) {
+        leaves.sort_by_key(|(key, _)| *key);
+        let retainer = ProofRetainer::new(targets.iter().map(Nibbles::unpack).collect());
+        let mut builder = HashBuilder::default().with_proof_retainer(retainer);
+        for (key, value) in &leaves {
+            builder.add_leaf(Nibbles::unpack(key), value);
+        }
+        let root = builder.root();
+        let nodes = builder.take_proof_nodes();
+        let proofs = targets
+            .iter()
+            .map(|target| {
+                nodes
+                    .matching_nodes_sorted(&Nibbles::unpack(target))
+                    .into_iter()
+                    .map(|(_, node)| node)
+                    .collect()
+            })
+            .collect();
+        (root, proofs)
+    }
+
+    const ADDRESS: Address = Address::repeat_byte(0xaa);
+    const OTHER: Address = Address::repeat_byte(0xbb);
+
+    /// 两个合约账户的 state trie, slot 1, 2, 3 有值, slot 9 为空, 返回 stateRoot 和 ADDRESS, OTHER 的证明
+    fn fixture() -> (
+        B256,
+        EIP1186AccountProofResponse,
+        EIP1186AccountProofResponse,
+    ) {
+        let slots = [(1u64, 7u64), (2, 0x1234), (3, u64::MAX)];
+        let storage_leaves = slots
+            .iter()
+            .map(|(slot, value)| {
+                let key = keccak256(B256::from(U256::from(*slot)));
+                (key, alloy::rlp::encode(U256::from(*value)))
+            })
+            .collect();
+        let keys: Vec<U256> = [1u64, 2, 3, 9].map(U256::from).to_vec();
+        let hashed_keys: Vec<B256> = keys.iter().map(|key| keccak256(B256::from(*key))).collect();
+        let (storage_root, storage_proofs) = build_trie(storage_leaves, &hashed_keys);
+        let storage_proof = keys
+            .iter()
+            .zip(storage_proofs)
+            .map(|(key, proof)| EIP1186StorageProof {
+                key: B256::from(*key).into(),
+                value: slots
+                    .iter()
+                    .find(|(slot, _)| U256::from(*slot) == *key)
+                    .map_or(U256::ZERO, |(_, value)| U256::from(*value)),
+                proof,
+            })
+            .collect::<Vec<_>>();
+
+        let account = |nonce: u64, balance: u64| TrieAccount {
+            nonce,
+            balance: U256::from(balance),
+            storage_root,
+            code_hash: keccak256([nonce as u8]),
+        };
+        let accounts = [(ADDRESS, account(1, 100)), (OTHER, account(2, 200))];
+        // 再加几个 EOA, 让 trie 中有分支节点
+        let eoas = (1..=4u8).map(|i| {
+            let eoa = TrieAccount {
+                nonce: i as u64,
+                balance: U256::from(i),
+                storage_root: EMPTY_ROOT_HASH,
+                code_hash: KECCAK_EMPTY,
+            };
+            (Address::with_last_byte(i), eoa)
+        });
+        let leaves = accounts
+            .into_iter()
+            .chain(eoas)
+            .map(|(address, account)| (keccak256(address), alloy::rlp::encode(account)))
+            .collect();
+        let (state_root, account_proofs) =
+            build_trie(leaves, &[keccak256(ADDRESS), keccak256(OTHER)]);
+
+        let response = |address: Address, account: TrieAccount, proof: Vec<Bytes>| {
+            EIP1186AccountProofResponse {
+                address,
+                balance: account.balance,
+                code_hash: account.code_hash,
+                nonce: account.nonce,
+                storage_hash: account.storage_root,
+                account_proof: proof,
+                storage_proof: storage_proof.clone(),
+            }
+        };
+        let mut account_proofs = account_proofs.into_iter();
+        (
+            state_root,
+            response(ADDRESS, account(1, 100), account_proofs.next().unwrap()),
+            response(OTHER, account(2, 200), account_proofs.next().unwrap()),
+        )
+    }
+
+    fn slots() -> Vec<U256> {
+        [1u64, 2, 3, 9].map(U256::from).to_vec()
+    }
+
+    fn proof_error(result: Result<Vec<(U256, U256)>>) -> ProofError {
+        let err = result.unwrap_err();
+        err.downcast::<ProofError>()
+            .unwrap_or_else(|err| panic!("not a proof error: {err}"))
+    }
+
+    #[test]
+    fn valid_proof() {
+        let (state_root, proof, other) = fixture();
+        let values = verify_response(state_root, ADDRESS, slots(), &proof).unwrap();
+        assert_eq!(
+            values,
+            [
+                (U256::from(1), U256::from(7)),
+                (U256::from(2), U256::from(0x1234)),
+                (U256::from(3), U256::from(u64::MAX)),
+                (U256::from(9), U256::ZERO),
+            ]
+        );
+        assert!(verify_response(state_root, OTHER, slots(), &other).is_ok());
+    }
+
+    /// 另一个账户的有效证明
+    #[test]
+    fn wrong_account() {
+        let (state_root, _, other) = fixture();
+        let err = proof_error(verify_response(state_root, ADDRESS, slots(), &other));
+        assert!(matches!(
+            err,
+            ProofError::WrongAccount {
+                requested: ADDRESS,
+                returned: OTHER
+            }
+        ));
+
+        // 改掉返回的 address 也不行, 账户证明按请求的地址校验
+        let mut forged = other;
+        forged.address = ADDRESS;
+        let err = proof_error(verify_response(state_root, ADDRESS, slots(), &forged));
+        assert!(matches!(
+            err,
+            ProofError::Account {
+                address: ADDRESS,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn wrong_value() {
+        let (state_root, mut proof, _) = fixture();
+        proof.storage_proof[1].value = U256::from(0x1235);
+        let err = proof_error(verify_response(state_root, ADDRESS, slots(), &proof));
+        assert!(matches!(err, ProofError::Storage { slot, .. } if slot == U256::from(2)));
+
+        // 不存在的 slot 返回非零值
+        let (state_root, mut proof, _) = fixture();
+        proof.storage_proof[3].value = U256::from(1);
+        let err = proof_error(verify_response(state_root, ADDRESS, slots(), &proof));
+        assert!(matches!(err, ProofError::Storage { slot, .. } if slot == U256::from(9)));
+
+        // 账户余额和证明不一致
+        let (state_root, mut proof, _) = fixture();
+        proof.balance += U256::from(1);
+        let err = proof_error(verify_response(state_root, ADDRESS, slots(), &proof));
+        assert!(matches!(err, ProofError::Account { .. }));
+    }
+
+    #[test]
+    fn missing_slot() {
+        let (state_root, mut proof, _) = fixture();
+        proof.storage_proof.remove(2);
+        let err = proof_error(verify_response(state_root, ADDRESS, slots(), &proof));
+        assert!(matches!(err, ProofError::MissingSlot(slot) if slot == U256::from(3)));
+    }
+}
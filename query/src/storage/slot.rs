@@ -0,0 +1,169 @@
+use alloy::dyn_abi::DynSolValue;
+use alloy::primitives::{Address, B256, Bytes, I256, U256, keccak256};
+use eyre::{Result, eyre};
+
+/// mapping 的 key
+///
+/// 值类型的 key 填充到 32 字节, string 和 bytes 的 key 直接使用原始数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MappingKey {
+    Word(B256),
+    Packed(Bytes),
+}
+
+impl MappingKey {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Word(word) => word.as_slice(),
+            Self::Packed(bytes) => bytes,
+        }
+    }
+}
+
+impl From<Address> for MappingKey {
+    fn from(value: Address) -> Self {
+        Self::Word(value.into_word())
+    }
+}
+
+impl From<U256> for MappingKey {
+    fn from(value: U256) -> Self {
+        Self::Word(value.into())
+    }
+}
+
+impl From<I256> for MappingKey {
+    fn from(value: I256) -> Self {
+        Self::Word(value.into_raw().into())
+    }
+}
+
+impl From<u64> for MappingKey {
+    fn from(value: u64) -> Self {
+        U256::from(value).into()
+    }
+}
+
+impl From<bool> for MappingKey {
+    fn from(value: bool) -> Self {
+        U256::from(value as u8).into()
+    }
+}
+
+/// bytes32, bytesN 需要左对齐后再转换
+impl From<B256> for MappingKey {
+    fn from(value: B256) -> Self {
+        Self::Word(value)
+    }
+}
+
+impl From<&str> for MappingKey {
+    fn from(value: &str) -> Self {
+        Self::Packed(Bytes::copy_from_slice(value.as_bytes()))
+    }
+}
+
+impl From<String> for MappingKey {
+    fn from(value: String) -> Self {
+        Self::Packed(value.into_bytes().into())
+    }
+}
+
+impl From<Bytes> for MappingKey {
+    fn from(value: Bytes) -> Self {
+        Self::Packed(value)
+    }
+}
+
+impl TryFrom<&DynSolValue> for MappingKey {
+    type Error = eyre::Error;
+
+    fn try_from(value: &DynSolValue) -> Result<Self> {
+        match value {
+            DynSolValue::String(s) => Ok(s.as_str().into()),
+            DynSolValue::Bytes(b) => Ok(Bytes::copy_from_slice(b).into()),
+            _ => value
+                .as_word()
+                .map(Self::Word)
+                .ok_or_else(|| eyre!("unsupported mapping key {value:?}")),
+        }
+    }
+}
+
+/// 按照 Solidity 的规则计算状态变量的 slot
+///
+/// 例如 `allowances[owner][spender]`, allowances 在 slot 1:
+/// `SlotPath::new(U256::from(1)).key(owner).key(spender).slot()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotPath {
+    slot: U256,
+    offset: usize,
+}
+
+impl SlotPath {
+    pub fn new(slot: U256) -> Self {
+        Self { slot, offset: 0 }
+    }
+
+    /// mapping 中 key 对应的值: keccak256(key . slot)
+    pub fn key(self, key: impl Into<MappingKey>) -> Self {
+        Self::new(mapping_slot(&key.into(), self.slot))
+    }
+
+    /// 动态数组的第 index 个元素, 数据从 keccak256(slot) 开始
+    ///
+    /// element_bytes 为元素类型的 numberOfBytes, 小于等于 16 字节的元素会打包到同一个 slot
+    pub fn index(self, index: usize, element_bytes: usize) -> Self {
+        Self::new(array_data_slot(self.slot)).fixed_index(index, element_bytes)
+    }
+
+    /// 定长数组的第 index 个元素, 数据从数组所在的 slot 开始
+    pub fn fixed_index(self, index: usize, element_bytes: usize) -> Self {
+        let (slot, offset) = element_position(self.slot, index, element_bytes);
+        Self { slot, offset }
+    }
+
+    /// struct 的成员, slot 和 offset 为 storageLayout 中成员的位置
+    pub fn member(self, slot: usize, offset: usize) -> Self {
+        Self {
+            slot: self.slot.wrapping_add(U256::from(slot)),
+            offset,
+        }
+    }
+
+    pub fn slot(&self) -> U256 {
+        self.slot
+    }
+
+    /// 在 slot 中的字节偏移, 从低位开始计算
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// mapping 中 key 对应的 slot: keccak256(key . slot)
+pub fn mapping_slot(key: &MappingKey, slot: U256) -> U256 {
+    let mut preimage = key.as_bytes().to_vec();
+    preimage.extend_from_slice(&slot.to_be_bytes::<32>());
+    U256::from_be_bytes(keccak256(preimage).0)
+}
+
+/// 动态数组, 长 string/bytes 的数据起始位置 keccak256(slot)
+pub fn array_data_slot(slot: U256) -> U256 {
+    U256::from_be_bytes(keccak256(slot.to_be_bytes::<32>()).0)
+}
+
+/// 数组第 index 个元素的 slot 和 slot 内偏移
+///
+/// 小于等于 16 字节的值类型打包存放, struct 和数组的 numberOfBytes 总是 32 的倍数,
+/// 从新的 slot 开始
+pub fn element_position(start: U256, index: usize, element_bytes: usize) -> (U256, usize) {
+    if (1..=16).contains(&element_bytes) {
+        let per_slot = 32 / element_bytes;
+        let slot = start.wrapping_add(U256::from(index / per_slot));
+        (slot, (index % per_slot) * element_bytes)
+    } else {
+        let slots = element_bytes.div_ceil(32);
+        (start.wrapping_add(U256::from(index * slots)), 0)
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, U256};
+use alloy::providers::Provider;
+use alloy::rpc::client::BatchRequest;
+use eyre::Result;
+use futures_util::future::try_join_all;
+use futures_util::{StreamExt, TryStreamExt, stream};
+
+/// 读取合约 storage slot 的数据源
+pub trait SlotSource {
+    fn storage_at(&self, slot: U256) -> impl Future<Output = Result<U256>> + Send;
+
+    /// 提前读取一批 slot, 之后的 storage_at 直接使用读取的结果
+    ///
+    /// 默认不做任何事, 每次 storage_at 单独读取
+    fn prefetch(&self, slots: &[U256]) -> impl Future<Output = Result<()>> + Send {
+        let _ = slots;
+        async { Ok(()) }
+    }
+}
+
+/// 多个 [`StorageDecoder`](super::StorageDecoder) 可以共用同一个数据源和缓存
+impl<S: SlotSource + Sync> SlotSource for &S {
+    fn storage_at(&self, slot: U256) -> impl Future<Output = Result<U256>> + Send {
+        (**self).storage_at(slot)
+    }
+
+    fn prefetch(&self, slots: &[U256]) -> impl Future<Output = Result<()>> + Send {
+        (**self).prefetch(slots)
+    }
+}
+
+/// 使用 eth_getStorageAt 逐个读取 slot, 所有读取固定在同一个区块
+pub struct ProviderSource<P> {
+    provider: P,
+    address: Address,
+    block: BlockId,
+}
+
+impl<P: Provider> ProviderSource<P> {
+    pub fn new(provider: P, address: Address) -> Self {
+        Self {
+            provider,
+            address,
+            block: BlockId::latest(),
+        }
+    }
+
+    /// 设置读取的区块, 默认 latest
+    pub fn block(mut self, block: BlockId) -> Self {
+        self.block = block;
+        self
+    }
+}
+
+impl<P: Provider> SlotSource for ProviderSource<P> {
+    async fn storage_at(&self, slot: U256) -> Result<U256> {
+        Ok(self
+            .provider
+            .get_storage_at(self.address, slot)
+            .block_id(self.block)
+            .await?)
+    }
+}
+
+/// 批量读取 slot, 把需要的 slot 放到 JSON-RPC batch 中并发发送, 读取结果会被缓存
+///
+/// 所有读取固定在同一个区块, 保证大数组和 mapping 解析出来的数据一致
+pub struct BatchSource<P> {
+    provider: P,
+    address: Address,
+    block: BlockId,
+    /// 每个 batch 中的请求数量, 0 表示不使用 batch, 每个 slot 单独请求
+    batch_size: usize,
+    /// 同时发送的 batch(或者请求)数量
+    concurrency: usize,
+    cache: Mutex<HashMap<U256, U256>>,
+}
+
+impl<P: Provider> BatchSource<P> {
+    pub fn new(provider: P, address: Address, block: BlockId) -> Self {
+        Self {
+            provider,
+            address,
+            block,
+            batch_size: 100,
+            concurrency: 4,
+            cache: Mutex::default(),
+        }
+    }
+
+    /// 固定在当前最新的区块
+    pub async fn at_latest(provider: P, address: Address) -> Result<Self> {
+        let block_number = provider.get_block_number().await?;
+        Ok(Self::new(provider, address, block_number.into()))
+    }
+
+    /// 默认 100, 设置为 0 时不使用 batch, 适用于不支持 batch 请求的 RPC
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// 默认 4
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn block_id(&self) -> BlockId {
+        self.block
+    }
+
+    /// 已经读取的 slot 数量
+    pub fn cached(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    /// 把多个数据源(例如不同合约)需要的 slot 放到同一个 JSON-RPC batch 中读取,
+    /// 结果写入各自的缓存, 使用第一个数据源的 provider 发送, 不按 batch_size 拆分
+    pub async fn prefetch_many(requests: &[(&Self, Vec<U256>)]) -> Result<()> {
+        let Some((first, _)) = requests.first() else {
+            return Ok(());
+        };
+        let mut batch = BatchRequest::new(first.provider.client());
+        let mut waiters = Vec::new();
+        for (index, (source, slots)) in requests.iter().enumerate() {
+            let cache = source.cache.lock().unwrap();
+            for &slot in slots.iter().filter(|slot| !cache.contains_key(slot)) {
+                let waiter = batch
+                    .add_call::<_, U256>("eth_getStorageAt", &(source.address, slot, source.block))?
+                    .map_resp(move |value| (index, slot, value));
+                waiters.push(waiter);
+            }
+        }
+        if waiters.is_empty() {
+            return Ok(());
+        }
+        batch.send().await?;
+        for (index, slot, value) in try_join_all(waiters).await? {
+            requests[index].0.cache.lock().unwrap().insert(slot, value);
+        }
+        Ok(())
+    }
+
+    async fn fetch_one(&self, slot: U256) -> Result<(U256, U256)> {
+        let value = self
+            .provider
+            .get_storage_at(self.address, slot)
+            .block_id(self.block)
+            .await?;
+        Ok((slot, value))
+    }
+
+    async fn fetch_batch(&self, slots: Vec<U256>) -> Result<Vec<(U256, U256)>> {
+        let mut batch = BatchRequest::new(self.provider.client());
+        let waiters = slots
+            .into_iter()
+            .map(|slot| {
+                Ok(batch
+                    .add_call::<_, U256>("eth_getStorageAt", &(self.address, slot, self.block))?
+                    .map_resp(move |value| (slot, value)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        batch.send().await?;
+        Ok(try_join_all(waiters).await?)
+    }
+}
+
+impl<P: Provider> SlotSource for BatchSource<P> {
+    async fn storage_at(&self, slot: U256) -> Result<U256> {
+        if let Some(value) = self.cache.lock().unwrap().get(&slot) {
+            return Ok(*value);
+        }
+        let (_, value) = self.fetch_one(slot).await?;
+        self.cache.lock().unwrap().insert(slot, value);
+        Ok(value)
+    }
+
+    async fn prefetch(&self, slots: &[U256]) -> Result<()> {
+        let missing: Vec<U256> = {
+            let cache = self.cache.lock().unwrap();
+            slots
+                .iter()
+                .filter(|slot| !cache.contains_key(slot))
+                .copied()
+                .collect()
+        };
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let values: Vec<(U256, U256)> = if self.batch_size == 0 {
+            stream::iter(missing)
+                .map(|slot| self.fetch_one(slot))
+                .buffer_unordered(self.concurrency)
+                .try_collect()
+                .await?
+        } else {
+            let chunks: Vec<Vec<U256>> = missing
+                .chunks(self.batch_size)
+                .map(<[U256]>::to_vec)
+                .collect();
+            stream::iter(chunks)
+                .map(|chunk| self.fetch_batch(chunk))
+                .buffer_unordered(self.concurrency)
+                .try_concat()
+                .await?
+        };
+        self.cache.lock().unwrap().extend(values);
+        Ok(())
+    }
+}
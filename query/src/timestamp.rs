@@ -0,0 +1,119 @@
+//! 根据时间戳查找区块
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::Provider;
+use alloy::rpc::types::Header;
+use eyre::{Result, eyre};
+
+/// 按时间戳二分查找区块, 缓存已经读取过的区块头
+///
+/// 每一步先按当前范围内的平均出块时间估算区块号, 估算没有让范围缩小一半时改用二分,
+/// 出块时间稳定的链上通常几次请求就能找到
+pub struct BlockFinder<P> {
+    provider: P,
+    headers: Mutex<BTreeMap<u64, Header>>,
+}
+
+impl<P: Provider> BlockFinder<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            headers: Mutex::default(),
+        }
+    }
+
+    /// 第一个时间戳 >= timestamp 的区块, timestamp 晚于最新区块时为 None
+    pub async fn first_block_at_or_after(&self, timestamp: u64) -> Result<Option<u64>> {
+        let latest = self.latest().await?;
+        if latest.timestamp < timestamp {
+            return Ok(None);
+        }
+        let genesis = self.header(0).await?;
+        if genesis.timestamp >= timestamp {
+            return Ok(Some(0));
+        }
+
+        // ts(lo) < timestamp <= ts(hi), 先用缓存中最接近的区块缩小范围
+        let (mut lo, mut hi) = {
+            let headers = self.headers.lock().unwrap();
+            let lo = headers
+                .iter()
+                .rev()
+                .find(|(_, header)| header.timestamp < timestamp)
+                .map(|(number, header)| (*number, header.timestamp));
+            let hi = headers
+                .iter()
+                .find(|(_, header)| header.timestamp >= timestamp)
+                .map(|(number, header)| (*number, header.timestamp));
+            (
+                lo.unwrap_or((0, genesis.timestamp)),
+                hi.unwrap_or((latest.number, latest.timestamp)),
+            )
+        };
+
+        let mut interpolate = true;
+        while hi.0 - lo.0 > 1 {
+            let range = hi.0 - lo.0;
+            let guess = if interpolate {
+                // 按平均出块时间估算
+                let elapsed = (timestamp - lo.1) as u128;
+                let duration = (hi.1 - lo.1).max(1) as u128;
+                lo.0 + (elapsed * range as u128 / duration) as u64
+            } else {
+                lo.0 + range / 2
+            };
+            let guess = guess.clamp(lo.0 + 1, hi.0 - 1);
+
+            let header = self.header(guess).await?;
+            if header.timestamp < timestamp {
+                lo = (guess, header.timestamp);
+            } else {
+                hi = (guess, header.timestamp);
+            }
+            interpolate = hi.0 - lo.0 <= range / 2;
+        }
+        Ok(Some(hi.0))
+    }
+
+    /// 最后一个时间戳 < timestamp 的区块, timestamp 不晚于创世区块时为 None
+    pub async fn last_block_before(&self, timestamp: u64) -> Result<Option<u64>> {
+        match self.first_block_at_or_after(timestamp).await? {
+            Some(number) => Ok(number.checked_sub(1)),
+            None => Ok(Some(self.latest().await?.number)),
+        }
+    }
+
+    /// 区块头, 优先从缓存读取
+    pub async fn header(&self, number: u64) -> Result<Header> {
+        if let Some(header) = self.headers.lock().unwrap().get(&number) {
+            return Ok(header.clone());
+        }
+        self.fetch(BlockNumberOrTag::Number(number)).await
+    }
+
+    /// 已经缓存的区块头数量
+    pub fn cached(&self) -> usize {
+        self.headers.lock().unwrap().len()
+    }
+
+    /// 最新区块每次都重新读取
+    async fn latest(&self) -> Result<Header> {
+        self.fetch(BlockNumberOrTag::Latest).await
+    }
+
+    async fn fetch(&self, block: BlockNumberOrTag) -> Result<Header> {
+        let header = self
+            .provider
+            .get_block_by_number(block)
+            .await?
+            .ok_or_else(|| eyre!("block {block} not found"))?
+            .header;
+        self.headers
+            .lock()
+            .unwrap()
+            .insert(header.number, header.clone());
+        Ok(header)
+    }
+}
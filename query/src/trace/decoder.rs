@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use alloy::dyn_abi::{DynSolValue, FunctionExt, JsonAbiExt};
+use alloy::json_abi::{Error, Function, JsonAbi};
+use alloy::primitives::{Address, Bytes, LogData, Selector};
+use alloy::rpc::types::trace::geth::CallFrame;
+use alloy::sol_types::decode_revert_reason;
+use eyre::Result;
+
+use super::{DecodedCall, DecodedFunction, TraceLog};
+use crate::format::value_to_string;
+use crate::logs::{EventRegistry, read_abi_file};
+
+/// 常见标准的函数, 第一个元素是来源
+const COMMON_FUNCTIONS: &[(&str, &str)] = &[
+    ("ERC20", "function totalSupply() view returns (uint256)"),
+    (
+        "ERC20",
+        "function balanceOf(address owner) view returns (uint256)",
+    ),
+    (
+        "ERC20",
+        "function allowance(address owner, address spender) view returns (uint256)",
+    ),
+    (
+        "ERC20",
+        "function transfer(address to, uint256 value) returns (bool)",
+    ),
+    (
+        "ERC20",
+        "function approve(address spender, uint256 value) returns (bool)",
+    ),
+    (
+        "ERC20",
+        "function transferFrom(address from, address to, uint256 value) returns (bool)",
+    ),
+    ("ERC20", "function decimals() view returns (uint8)"),
+    ("ERC20", "function symbol() view returns (string)"),
+    ("ERC20", "function name() view returns (string)"),
+    (
+        "ERC721",
+        "function ownerOf(uint256 tokenId) view returns (address)",
+    ),
+    (
+        "ERC721",
+        "function safeTransferFrom(address from, address to, uint256 tokenId)",
+    ),
+    ("WETH9", "function deposit() payable"),
+    ("WETH9", "function withdraw(uint256 wad)"),
+];
+
+/// 根据 ABI 解析 callTracer 的调用树: 函数参数和返回值, revert 原因以及事件
+#[derive(Debug, Clone, Default)]
+pub struct TraceDecoder {
+    functions: HashMap<Selector, Vec<(String, Function)>>,
+    errors: HashMap<Selector, Vec<(String, Error)>>,
+    events: EventRegistry,
+    labels: HashMap<Address, String>,
+}
+
+impl TraceDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 包含常见标准的函数和事件
+    pub fn with_common() -> Self {
+        let mut decoder = Self {
+            events: EventRegistry::with_common(),
+            ..Self::default()
+        };
+        for (source, signature) in COMMON_FUNCTIONS {
+            let function = Function::parse(signature).expect("invalid bundled function");
+            decoder.add_function(*source, function);
+        }
+        decoder
+    }
+
+    pub fn add_function(&mut self, source: impl Into<String>, function: Function) -> &mut Self {
+        let entries = self.functions.entry(function.selector()).or_default();
+        entries.retain(|(_, registered)| registered.signature() != function.signature());
+        entries.insert(0, (source.into(), function));
+        self
+    }
+
+    pub fn add_error(&mut self, source: impl Into<String>, error: Error) -> &mut Self {
+        let entries = self.errors.entry(error.selector()).or_default();
+        entries.retain(|(_, registered)| registered.signature() != error.signature());
+        entries.insert(0, (source.into(), error));
+        self
+    }
+
+    /// 注册 ABI 中的函数, 自定义错误和事件, 后注册的优先匹配
+    pub fn add_abi(&mut self, source: &str, abi: &JsonAbi) -> &mut Self {
+        for function in abi.functions() {
+            self.add_function(source, function.clone());
+        }
+        for error in abi.errors() {
+            self.add_error(source, error.clone());
+        }
+        self.events.add_abi(source, abi);
+        self
+    }
+
+    /// 读取 JsonAbi 文件, 来源为文件名
+    pub fn load_abi_file(&mut self, path: impl AsRef<Path>) -> Result<&mut Self> {
+        let (source, abi) = read_abi_file(path)?;
+        Ok(self.add_abi(&source, &abi))
+    }
+
+    /// 打印调用树时用名字代替地址
+    pub fn label(&mut self, address: Address, name: impl Into<String>) -> &mut Self {
+        self.labels.insert(address, name.into());
+        self
+    }
+
+    pub fn events(&self) -> &EventRegistry {
+        &self.events
+    }
+
+    /// 解析整个调用树
+    pub fn decode(&self, frame: &CallFrame) -> DecodedCall {
+        let reverted = frame.error.is_some();
+        let function = self.decode_function(&frame.input, frame.output.as_ref(), reverted);
+        let revert_reason = reverted
+            .then(|| {
+                frame
+                    .output
+                    .as_ref()
+                    .and_then(|output| self.decode_revert(output))
+                    .or_else(|| frame.revert_reason.clone())
+            })
+            .flatten();
+        let logs = frame
+            .logs
+            .iter()
+            .map(|log| {
+                let data = LogData::new_unchecked(
+                    log.topics.clone().unwrap_or_default(),
+                    log.data.clone().unwrap_or_default(),
+                );
+                TraceLog {
+                    address: log.address,
+                    event: self.events.decode(&data).map(|decoded| decoded.to_string()),
+                    data,
+                    position: log.position,
+                }
+            })
+            .collect();
+
+        DecodedCall {
+            kind: frame.typ.clone(),
+            from: frame.from,
+            to: frame.to,
+            label: frame.to.and_then(|to| self.labels.get(&to).cloned()),
+            value: frame.value,
+            gas_used: frame.gas_used,
+            input: frame.input.clone(),
+            output: frame.output.clone(),
+            function,
+            error: frame.error.clone(),
+            revert_reason,
+            logs,
+            calls: frame.calls.iter().map(|call| self.decode(call)).collect(),
+        }
+    }
+
+    /// 根据 selector 找到能解析 input 的函数, 没有 revert 时同时解析返回值
+    fn decode_function(
+        &self,
+        input: &Bytes,
+        output: Option<&Bytes>,
+        reverted: bool,
+    ) -> Option<DecodedFunction> {
+        let selector = Selector::try_from(input.get(..4)?).ok()?;
+        self.functions
+            .get(&selector)?
+            .iter()
+            .find_map(|(source, function)| {
+                let inputs = function.abi_decode_input(&input[4..], true).ok()?;
+                let outputs = output
+                    .filter(|_| !reverted)
+                    .and_then(|output| function.abi_decode_output(output, true).ok());
+                Some(DecodedFunction {
+                    source: source.clone(),
+                    function: function.clone(),
+                    inputs,
+                    outputs,
+                })
+            })
+    }
+
+    /// 解析 revert data: 先匹配注册的自定义错误, 再尝试 Error(string) 和 Panic(uint256)
+    pub fn decode_revert(&self, data: &[u8]) -> Option<String> {
+        let custom = data
+            .get(..4)
+            .and_then(|selector| self.errors.get(&Selector::try_from(selector).ok()?))
+            .and_then(|errors| {
+                errors.iter().find_map(|(source, error)| {
+                    let values = error.abi_decode_input(&data[4..], true).ok()?;
+                    Some(format!(
+                        "{source}.{}({})",
+                        error.name,
+                        format_params(
+                            error.inputs.iter().map(|input| input.name.as_str()),
+                            &values
+                        )
+                    ))
+                })
+            });
+        custom.or_else(|| decode_revert_reason(data))
+    }
+}
+
+/// name=value, 没有名字的参数只打印值
+pub(super) fn format_params<'a>(
+    names: impl Iterator<Item = &'a str>,
+    values: &[DynSolValue],
+) -> String {
+    names
+        .zip(values)
+        .map(|(name, value)| {
+            let value = value_to_string(value);
+            if name.is_empty() {
+                value
+            } else {
+                format!("{name}={value}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,207 @@
+//! 使用 debug_traceTransaction/debug_traceCall 的 callTracer 获取调用树, 并根据 ABI 解析
+mod decoder;
+
+use std::fmt;
+
+use alloy::dyn_abi::DynSolValue;
+use alloy::eips::{BlockId, BlockNumberOrTag};
+use alloy::json_abi::Function;
+use alloy::primitives::{Address, B256, Bytes, LogData, U256};
+use alloy::providers::Provider;
+use alloy::providers::ext::DebugApi;
+use alloy::rpc::types::TransactionRequest;
+use alloy::rpc::types::trace::common::TraceResult;
+use alloy::rpc::types::trace::geth::{
+    CallConfig, CallFrame, GethDebugTracingCallOptions, GethDebugTracingOptions,
+};
+use eyre::{Result, eyre};
+
+pub use decoder::TraceDecoder;
+
+use decoder::format_params;
+
+/// callTracer, 包括子调用和事件
+fn call_tracer() -> GethDebugTracingOptions {
+    GethDebugTracingOptions::call_tracer(CallConfig::default().with_log())
+}
+
+/// 重新执行一笔交易, 返回调用树
+pub async fn trace_transaction<P: Provider>(provider: &P, tx_hash: B256) -> Result<CallFrame> {
+    let trace = provider
+        .debug_trace_transaction(tx_hash, call_tracer())
+        .await?;
+    Ok(trace.try_into_call_frame()?)
+}
+
+/// 重新执行一个区块中的所有交易, 按交易顺序返回调用树
+pub async fn trace_block<P: Provider>(
+    provider: &P,
+    block: BlockNumberOrTag,
+) -> Result<Vec<CallFrame>> {
+    let traces = provider
+        .debug_trace_block_by_number(block, call_tracer())
+        .await?;
+    traces
+        .into_iter()
+        .map(|trace| match trace {
+            TraceResult::Success { result, .. } => Ok(result.try_into_call_frame()?),
+            TraceResult::Error { error, tx_hash } => {
+                Err(eyre!("trace transaction {tx_hash:?} failed, {error}"))
+            }
+        })
+        .collect()
+}
+
+/// 在指定区块上模拟执行一个调用, 返回调用树
+pub async fn trace_call<P: Provider>(
+    provider: &P,
+    tx: TransactionRequest,
+    block: BlockId,
+) -> Result<CallFrame> {
+    let trace = provider
+        .debug_trace_call(tx, block, GethDebugTracingCallOptions::new(call_tracer()))
+        .await?;
+    Ok(trace.try_into_call_frame()?)
+}
+
+/// 解析出来的函数调用
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedFunction {
+    /// ABI 的来源, 例如 ABI 文件名
+    pub source: String,
+    pub function: Function,
+    pub inputs: Vec<DynSolValue>,
+    /// revert 或者返回值无法解析时为 None
+    pub outputs: Option<Vec<DynSolValue>>,
+}
+
+impl fmt::Display for DecodedFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = self.function.inputs.iter().map(|input| input.name.as_str());
+        write!(
+            f,
+            "{}.{}({})",
+            self.source,
+            self.function.name,
+            format_params(names, &self.inputs)
+        )?;
+        if let Some(outputs) = self.outputs.as_ref().filter(|outputs| !outputs.is_empty()) {
+            let names = self
+                .function
+                .outputs
+                .iter()
+                .map(|output| output.name.as_str());
+            write!(f, " -> ({})", format_params(names, outputs))?;
+        }
+        Ok(())
+    }
+}
+
+/// 调用中发出的事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceLog {
+    pub address: Option<Address>,
+    pub data: LogData,
+    /// 解析出来的事件, 例如 `ERC20 Transfer(from=.., to=.., value=..)`
+    pub event: Option<String>,
+    /// 事件发出时已经完成的子调用数量
+    pub position: Option<u64>,
+}
+
+/// 解析后的调用树
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedCall {
+    /// CALL, STATICCALL, DELEGATECALL, CREATE 等
+    pub kind: String,
+    pub from: Address,
+    pub to: Option<Address>,
+    /// [`TraceDecoder::label`] 设置的名字
+    pub label: Option<String>,
+    pub value: Option<U256>,
+    pub gas_used: U256,
+    pub input: Bytes,
+    pub output: Option<Bytes>,
+    pub function: Option<DecodedFunction>,
+    /// 节点返回的错误, 例如 execution reverted
+    pub error: Option<String>,
+    /// 解析出来的 revert 原因
+    pub revert_reason: Option<String>,
+    pub logs: Vec<TraceLog>,
+    pub calls: Vec<DecodedCall>,
+}
+
+impl DecodedCall {
+    pub fn reverted(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// 深度优先遍历所有调用, 包括自己
+    pub fn iter(&self) -> impl Iterator<Item = &DecodedCall> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let call = stack.pop()?;
+            stack.extend(call.calls.iter().rev());
+            Some(call)
+        })
+    }
+
+    fn fmt_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        let to = match (&self.label, self.to) {
+            (Some(label), _) => label.clone(),
+            (None, Some(to)) => to.to_string(),
+            (None, None) => "new contract".to_string(),
+        };
+        write!(f, "{indent}[{}] {to}", self.kind)?;
+        match &self.function {
+            Some(function) => write!(f, " {function}")?,
+            None if self.input.len() >= 4 => {
+                write!(f, " {}", Bytes::copy_from_slice(&self.input[..4]))?
+            }
+            None => {}
+        }
+        if let Some(value) = self.value.filter(|value| !value.is_zero()) {
+            write!(f, " value={value}")?;
+        }
+        write!(f, " gas={}", self.gas_used)?;
+        if let Some(error) = &self.error {
+            write!(f, " !! {error}")?;
+            if let Some(reason) = &self.revert_reason {
+                write!(f, ": {reason}")?;
+            }
+        }
+        writeln!(f)?;
+
+        // 按照事件发出的位置和子调用交错打印
+        let log_indent = "  ".repeat(depth + 1);
+        let fmt_log = |f: &mut fmt::Formatter<'_>, log: &TraceLog| match &log.event {
+            Some(event) => writeln!(f, "{log_indent}emit {event}"),
+            None => writeln!(f, "{log_indent}emit {:?}", log.data),
+        };
+        for (i, call) in self.calls.iter().enumerate() {
+            for log in self
+                .logs
+                .iter()
+                .filter(|log| log.position == Some(i as u64))
+            {
+                fmt_log(f, log)?;
+            }
+            call.fmt_tree(f, depth + 1)?;
+        }
+        let calls = self.calls.len() as u64;
+        for log in self
+            .logs
+            .iter()
+            .filter(|log| log.position.is_none_or(|position| position >= calls))
+        {
+            fmt_log(f, log)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DecodedCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_tree(f, 0)
+    }
+}
@@ -0,0 +1,279 @@
+//! 每个新区块重新读取合约的 storage, 输出发生变化的值
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use alloy::dyn_abi::DynSolValue;
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::Provider;
+use eyre::{Result, bail, eyre};
+use futures_util::future::try_join_all;
+use futures_util::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+
+use crate::format::value_to_string;
+use crate::storage::{BatchSource, Encoding, SlotSource, StorageDecoder, StorageLayout};
+
+/// 监听的 slot 或者状态变量
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WatchKey {
+    /// 原始 slot, 值为 bytes32
+    Slot(U256),
+    /// 按 storageLayout 解析的状态变量
+    Variable(String),
+}
+
+impl fmt::Display for WatchKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Slot(slot) => write!(f, "slot {slot:#x}"),
+            Self::Variable(label) => write!(f, "{label}"),
+        }
+    }
+}
+
+/// 一个值在新区块中发生了变化
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageChange {
+    pub block_number: u64,
+    pub address: Address,
+    pub key: WatchKey,
+    pub old: DynSolValue,
+    pub new: DynSolValue,
+}
+
+impl fmt::Display for StorageChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block {} {} {}: {} -> {}",
+            self.block_number,
+            self.address,
+            self.key,
+            value_to_string(&self.old),
+            value_to_string(&self.new)
+        )
+    }
+}
+
+struct WatchedContract {
+    address: Address,
+    slots: Vec<U256>,
+    layout: Option<StorageLayout>,
+    variables: Vec<String>,
+}
+
+/// 监听一组合约的 slot 和状态变量
+///
+/// - 每个区块中所有合约需要的 slot 放到一个 JSON-RPC batch 中读取
+/// - provider 支持订阅(WebSocket/IPC)时订阅新区块, 否则通过 HTTP 轮询
+pub struct StorageWatcher<P> {
+    provider: P,
+    contracts: Vec<WatchedContract>,
+    poll_interval: Option<Duration>,
+}
+
+impl<P: Provider + Clone> StorageWatcher<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            contracts: Vec::new(),
+            poll_interval: None,
+        }
+    }
+
+    /// 监听原始 slot
+    pub fn slots(mut self, address: Address, slots: &[U256]) -> Self {
+        self.contract(address).slots.extend_from_slice(slots);
+        self
+    }
+
+    /// 按 storageLayout 监听状态变量, 同一个合约再次设置时替换 layout
+    ///
+    /// mapping 需要指定 key, 不支持监听; 不存在的变量和 mapping 在这里直接返回错误.
+    /// 替换 layout 时之前添加的变量也按新的 layout 检查
+    pub fn variables(
+        mut self,
+        address: Address,
+        layout: StorageLayout,
+        labels: &[&str],
+    ) -> Result<Self> {
+        let existing = self
+            .contracts
+            .iter()
+            .filter(|contract| contract.address == address)
+            .flat_map(|contract| contract.variables.iter().map(String::as_str));
+        for label in existing.chain(labels.iter().copied()) {
+            let item = layout.item(label)?;
+            if layout.type_info(&item.type_id)?.encoding == Encoding::Mapping {
+                bail!("{label} is a mapping, watching mappings is not supported");
+            }
+        }
+        let contract = self.contract(address);
+        contract.layout = Some(layout);
+        contract
+            .variables
+            .extend(labels.iter().map(ToString::to_string));
+        Ok(self)
+    }
+
+    /// HTTP 轮询新区块的间隔, 默认使用 provider 的设置
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = Some(poll_interval);
+        self
+    }
+
+    /// 读取指定区块上所有监听的值
+    pub async fn read(&self, block_number: u64) -> Result<Vec<(Address, WatchKey, DynSolValue)>> {
+        let block = BlockId::number(block_number);
+        let contracts: Vec<_> = self
+            .contracts
+            .iter()
+            .map(|contract| {
+                let source = BatchSource::new(self.provider.clone(), contract.address, block);
+                (contract, source)
+            })
+            .collect();
+
+        // 所有合约的原始 slot 和状态变量的静态 slot 一起读取
+        let mut requests = Vec::new();
+        for (contract, source) in &contracts {
+            let mut slots = contract.slots.clone();
+            if let Some(layout) = &contract.layout {
+                let decoder = StorageDecoder::new(source, layout.clone());
+                for label in &contract.variables {
+                    slots.extend(decoder.slots(label)?);
+                }
+            }
+            slots.sort_unstable();
+            slots.dedup();
+            requests.push((source, slots));
+        }
+        BatchSource::prefetch_many(&requests).await?;
+
+        let values = try_join_all(
+            contracts
+                .iter()
+                .map(|(contract, source)| self.read_contract(contract, source)),
+        )
+        .await?;
+        Ok(values.into_iter().flatten().collect())
+    }
+
+    /// 以当前最新区块的值为起点, 之后每个新区块输出变化的值
+    pub async fn stream(&self) -> Result<impl Stream<Item = Result<StorageChange>> + '_> {
+        let heads = self.heads().await?;
+        let latest = self.provider.get_block_number().await?;
+        let previous: HashMap<_, _> = self
+            .read(latest)
+            .await?
+            .into_iter()
+            .map(|(address, key, value)| ((address, key), value))
+            .collect();
+
+        let changes = stream::unfold(
+            (heads, previous),
+            move |(mut heads, mut previous)| async move {
+                let changes = match heads.next().await? {
+                    Ok(block_number) => self.changes(block_number, &mut previous).await,
+                    Err(e) => Err(e),
+                };
+                Some((changes, (heads, previous)))
+            },
+        );
+        Ok(changes
+            .map_ok(|changes| stream::iter(changes).map(Ok))
+            .try_flatten())
+    }
+
+    fn contract(&mut self, address: Address) -> &mut WatchedContract {
+        let index = match self.contracts.iter().position(|c| c.address == address) {
+            Some(index) => index,
+            None => {
+                self.contracts.push(WatchedContract {
+                    address,
+                    slots: Vec::new(),
+                    layout: None,
+                    variables: Vec::new(),
+                });
+                self.contracts.len() - 1
+            }
+        };
+        &mut self.contracts[index]
+    }
+
+    /// 新区块的区块号, 订阅失败时改为轮询
+    async fn heads(&self) -> Result<BoxStream<'_, Result<u64>>> {
+        if let Ok(subscription) = self.provider.subscribe_blocks().await {
+            let heads = subscription.into_stream().map(|header| Ok(header.number));
+            return Ok(heads.boxed());
+        }
+
+        let mut poller = self.provider.watch_blocks().await?;
+        if let Some(poll_interval) = self.poll_interval {
+            poller = poller.with_poll_interval(poll_interval);
+        }
+        let heads =
+            poller
+                .into_stream()
+                .flat_map(stream::iter)
+                .then(move |block_hash| async move {
+                    let block = self
+                        .provider
+                        .get_block_by_hash(block_hash)
+                        .await?
+                        .ok_or_else(|| eyre!("block {block_hash} not found"))?;
+                    Ok(block.header.number)
+                });
+        Ok(heads.boxed())
+    }
+
+    async fn changes(
+        &self,
+        block_number: u64,
+        previous: &mut HashMap<(Address, WatchKey), DynSolValue>,
+    ) -> Result<Vec<StorageChange>> {
+        let mut changes = Vec::new();
+        for (address, key, new) in self.read(block_number).await? {
+            let old = previous.insert((address, key.clone()), new.clone());
+            if let Some(old) = old.filter(|old| *old != new) {
+                changes.push(StorageChange {
+                    block_number,
+                    address,
+                    key,
+                    old,
+                    new,
+                });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// slot 已经 prefetch, 动态数组和 string/bytes 的数据在这里单独读取
+    async fn read_contract(
+        &self,
+        contract: &WatchedContract,
+        source: &BatchSource<P>,
+    ) -> Result<Vec<(Address, WatchKey, DynSolValue)>> {
+        let mut values = Vec::new();
+        for slot in &contract.slots {
+            let word = B256::from(source.storage_at(*slot).await?);
+            values.push((
+                contract.address,
+                WatchKey::Slot(*slot),
+                DynSolValue::FixedBytes(word, 32),
+            ));
+        }
+        if let Some(layout) = &contract.layout {
+            let decoder = StorageDecoder::new(source, layout.clone());
+            for label in &contract.variables {
+                values.push((
+                    contract.address,
+                    WatchKey::Variable(label.clone()),
+                    decoder.read(label).await?,
+                ));
+            }
+        }
+        Ok(values)
+    }
+}
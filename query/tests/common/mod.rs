@@ -0,0 +1,141 @@
+//! 集成测试共用的合约和 fork 配置
+#![allow(dead_code)]
+
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{Address, Bytes, hex, keccak256},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+};
+use eyre::{OptionExt, Result};
+
+/// fork 主网使用的 RPC, 默认使用公共节点
+pub fn fork_url() -> String {
+    std::env::var("RPC_URL").unwrap_or_else(|_| "https://ethereum-rpc.publicnode.com".to_string())
+}
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.26; solc Counter.sol --via-ir --optimize --bin
+    #[sol(rpc, abi, bytecode="6080806040523460135760df908160198239f35b600080fdfe6080806040526004361015601257600080fd5b60003560e01c9081633fb5c1cb1460925781638381f58a146079575063d09de08a14603c57600080fd5b3460745760003660031901126074576000546000198114605e57600101600055005b634e487b7160e01b600052601160045260246000fd5b600080fd5b3460745760003660031901126074576020906000548152f35b34607457602036600319011260745760043560005500fea2646970667358221220e978270883b7baed10810c4079c941512e93a7ba1cd1108c781d4bc738d9090564736f6c634300081a0033")]
+    contract Counter {
+        uint256 public number;
+
+        function setNumber(uint256 newNumber) public {
+            number = newNumber;
+        }
+
+        function increment() public {
+            number++;
+        }
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.26; storage layout: src/layout/esRNT.json
+    #[sol(rpc, bytecode="608060405234801561000f575f80fd5b505f5b600b8110156100e6575f60405180606001604052808360016100349190610100565b6001600160a01b031681526020018361004e426002610119565b6100589190610130565b6001600160401b03168152602001610071846001610100565b61008390670de0b6b3a7640000610119565b90528154600181810184555f93845260209384902083516002909302018054948401516001600160401b0316600160a01b026001600160e01b03199095166001600160a01b03909316929092179390931781556040909101519082015501610012565b50610143565b634e487b7160e01b5f52601160045260245ffd5b80820180821115610113576101136100ec565b92915050565b8082028115828204841417610113576101136100ec565b81810381811115610113576101136100ec565b603e8061014f5f395ff3fe60806040525f80fdfea264697066735822122061c6be102b852df2191c9d69e810dfdc06e005d40a4ee6dd27462a263b7c273b64736f6c634300081a0033")]
+    contract esRNT {
+        struct LockInfo {
+            address user;
+            uint64 startTime;
+            uint256 amount;
+        }
+
+        LockInfo[] private _locks;
+
+        constructor() {
+            for (uint256 i = 0; i < 11; i++) {
+                _locks.push(LockInfo(address(uint160(i + 1)), uint64(block.timestamp * 2 - i), 1e18 * (i + 1)));
+            }
+        }
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    // solc v0.8.25, 和 contracts/src/bin/events_errors.rs 中的合约相同
+    #[sol(rpc, abi, bytecode = "608060405260008055348015601357600080fd5b506103e9806100236000396000f3fe608060405234801561001057600080fd5b50600436106100575760003560e01c80632baeceb71461005c5780632ccbdbca1461006657806361bc221a14610070578063c3e8b5ca1461008e578063d09de08a14610098575b600080fd5b6100646100a2565b005b61006e610103565b005b61007861013e565b60405161008591906101f9565b60405180910390f35b610096610144565b005b6100a061017f565b005b60016000808282546100b49190610243565b925050819055506000543373ffffffffffffffffffffffffffffffffffffffff167fdc69c403b972fc566a14058b3b18e1513da476de6ac475716e489fae0cbe4a2660405160405180910390a3565b6040517f23b0db14000000000000000000000000000000000000000000000000000000008152600401610135906102e3565b60405180910390fd5b60005481565b6040517fa5f9ec670000000000000000000000000000000000000000000000000000000081526004016101769061034f565b60405180910390fd5b6001600080828254610191919061036f565b925050819055506000543373ffffffffffffffffffffffffffffffffffffffff167ff6d1d8d205b41f9fb9549900a8dba5d669d68117a3a2b88c1ebc61163e8117ba60405160405180910390a3565b6000819050919050565b6101f3816101e0565b82525050565b600060208201905061020e60008301846101ea565b92915050565b7f4e487b7100000000000000000000000000000000000000000000000000000000600052601160045260246000fd5b600061024e826101e0565b9150610259836101e0565b92508282039050818112600084121682821360008512151617156102805761027f610214565b5b92915050565b600082825260208201905092915050565b7f4572726f72204100000000000000000000000000000000000000000000000000600082015250565b60006102cd600783610286565b91506102d882610297565b602082019050919050565b600060208201905081810360008301526102fc816102c0565b9050919050565b7f4572726f72204200000000000000000000000000000000000000000000000000600082015250565b6000610339600783610286565b915061034482610303565b602082019050919050565b600060208201905081810360008301526103688161032c565b9050919050565b600061037a826101e0565b9150610385836101e0565b9250828201905082811215600083121683821260008412151617156103ad576103ac610214565b5b9291505056fea2646970667358221220a878a3c1da1a1170e4496cdbc63bd5ed1587374bcd6cf6d4f1d5b88fa981795d64736f6c63430008190033")]
+    contract CounterWithError {
+        int256 public counter = 0;
+
+        event Increment(address indexed by, int256 indexed value);
+        event Decrement(address indexed by, int256 indexed value);
+
+        error ErrorA(string message);
+        error ErrorB(string message);
+
+        function increment() public {
+            counter += 1;
+            emit Increment(msg.sender, counter);
+        }
+
+        function decrement() public {
+            counter -= 1;
+            emit Decrement(msg.sender, counter);
+        }
+
+        function revertA() public pure {
+            revert ErrorA("Error A");
+        }
+
+        function revertB() public pure {
+            revert ErrorB("Error B");
+        }
+    }
+}
+
+/// 按 calldata 返回预设结果的合约, 用来代替只需要 view 函数的合约, 例如 ENS registry 和 resolver
+///
+/// 手写的 bytecode, 没有 solc 源码:
+/// - calldata 为 `0xffffffff ++ key ++ data` 时, 把 data 保存为 key 的返回值,
+///   slot key 是长度, 从 slot key + 1 开始是数据
+/// - 其他 calldata 返回 keccak256(calldata) 对应的数据, 没有设置时返回空
+///
+/// ```text
+/// 0x00 PUSH1 0 CALLDATALOAD PUSH1 0xe0 SHR PUSH4 0xffffffff EQ PUSH1 0x3c JUMPI
+///      CALLDATASIZE PUSH1 0 PUSH1 0 CALLDATACOPY CALLDATASIZE PUSH1 0 KECCAK256
+///      DUP1 SLOAD PUSH1 0                       ; key len offset
+/// 0x1d JUMPDEST DUP2 DUP2 LT ISZERO PUSH1 0x37 JUMPI
+///      DUP1 PUSH1 5 SHR DUP4 ADD PUSH1 1 ADD SLOAD DUP2 MSTORE
+///      PUSH1 0x20 ADD PUSH1 0x1d JUMP
+/// 0x37 JUMPDEST POP PUSH1 0 RETURN
+/// 0x3c JUMPDEST PUSH1 4 CALLDATALOAD PUSH1 0x24 CALLDATASIZE SUB
+///      DUP1 DUP3 SSTORE PUSH1 0                 ; key len offset
+/// 0x49 JUMPDEST DUP2 DUP2 LT ISZERO PUSH1 0x66 JUMPI
+///      DUP1 PUSH1 0x24 ADD CALLDATALOAD DUP2 PUSH1 5 SHR DUP5 ADD PUSH1 1 ADD SSTORE
+///      PUSH1 0x20 ADD PUSH1 0x49 JUMP
+/// 0x66 JUMPDEST STOP
+/// ```
+pub const MOCK_CONTRACT_BYTECODE: [u8; 116] = hex!(
+    "606880600c6000396000f3fe60003560e01c63ffffffff14603c5736600060003736600020805460005b818110156037578060051c8301600101548152602001601d565b506000f35b6004356024360380825560005b8181101560665780602401358160051c8401600101556020016049565b00"
+);
+
+pub async fn deploy_mock(provider: impl Provider) -> Result<Address> {
+    let tx = TransactionRequest::default().with_deploy_code(Bytes::from(MOCK_CONTRACT_BYTECODE));
+    let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+    receipt.contract_address.ok_or_eyre("no contract deployed")
+}
+
+/// 设置 mock 合约对 call 的返回值, 在新的区块中生效
+pub async fn mock_returns<C: SolCall>(
+    provider: impl Provider,
+    mock: Address,
+    call: &C,
+    returns: &[u8],
+) -> Result<()> {
+    let input = [
+        &[0xff; 4][..],
+        keccak256(call.abi_encode()).as_slice(),
+        returns,
+    ]
+    .concat();
+    let tx = TransactionRequest::default()
+        .to(mock)
+        .with_input(Bytes::from(input));
+    let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+    eyre::ensure!(receipt.status(), "mock setup reverted");
+    Ok(())
+}
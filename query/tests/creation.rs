@@ -0,0 +1,100 @@
+mod common;
+
+use alloy::{
+    primitives::{Address, Bytes, U256, address},
+    providers::{Provider, ProviderBuilder, WalletProvider, ext::AnvilApi},
+    rpc::types::TransactionReceipt,
+    sol,
+};
+use eyre::Result;
+use query::creation::{CreationKind, find_creation};
+
+use common::Counter;
+
+sol! {
+    #[allow(missing_docs)]
+    // Safe v1.3.0 GnosisSafeProxyFactory
+    #[sol(rpc)]
+    contract GnosisSafeProxyFactory {
+        function createProxy(address singleton, bytes memory data) external returns (address proxy);
+        function createProxyWithNonce(address _singleton, bytes memory initializer, uint256 saltNonce) external returns (address proxy);
+    }
+}
+
+const SAFE_PROXY_FACTORY: Address = address!("0xa6B71E26C5e0845f74c812102Ca7114b6a896AB2");
+const SAFE_SINGLETON: Address = address!("0xd9Db270c1B5E3Bd161E8c8503c55cEABeE709552");
+
+/// 部署交易创建的合约, 前后挖一些空块让二分查找有意义
+#[tokio::test]
+async fn deploy_transaction() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let owner = provider.default_signer_address();
+    provider.anvil_mine(Some(37), None).await?;
+    let counter = Counter::deploy_builder(provider.clone())
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    let address = counter.contract_address.unwrap();
+    provider.anvil_mine(Some(50), None).await?;
+
+    let creation = find_creation(&provider, address).await?.unwrap();
+    assert_eq!(Some(creation.block_number), counter.block_number);
+    let tx = creation.tx.unwrap();
+    assert_eq!(tx.tx_hash, counter.transaction_hash);
+    assert_eq!(tx.kind, CreationKind::Transaction);
+    assert_eq!((tx.deployer, tx.creator), (owner, owner));
+
+    // 没有 code 的地址
+    assert_eq!(find_creation(&provider, owner).await?, None);
+    assert_eq!(
+        find_creation(&provider, Address::with_last_byte(0xad)).await?,
+        None
+    );
+
+    Ok(())
+}
+
+/// 检查工厂合约创建的 proxy
+async fn check_factory_creation(
+    provider: impl Provider,
+    proxy: Address,
+    receipt: TransactionReceipt,
+    kind: CreationKind,
+) -> Result<()> {
+    assert!(receipt.status());
+    provider.anvil_mine(Some(5), None).await?;
+
+    let creation = find_creation(&provider, proxy).await?.unwrap();
+    assert_eq!(Some(creation.block_number), receipt.block_number);
+    let tx = creation.tx.unwrap();
+    assert_eq!(tx.tx_hash, receipt.transaction_hash);
+    assert_eq!(tx.kind, kind);
+    assert_eq!(
+        (tx.deployer, tx.creator),
+        (receipt.from, SAFE_PROXY_FACTORY)
+    );
+    Ok(())
+}
+
+/// Safe v1.3.0 的 factory 用 CREATE 和 CREATE2 部署 proxy
+#[tokio::test]
+#[ignore = "forks mainnet, set RPC_URL and run with --ignored"]
+async fn factory_create() -> Result<()> {
+    let provider = ProviderBuilder::new()
+        .on_anvil_with_wallet_and_config(|anvil| anvil.fork(common::fork_url()))?;
+    let factory = GnosisSafeProxyFactory::new(SAFE_PROXY_FACTORY, provider.clone());
+
+    // 先模拟得到 proxy 地址, 再发送交易
+    let create = factory.createProxy(SAFE_SINGLETON, Bytes::new());
+    let proxy = create.call().await?.proxy;
+    let receipt = create.send().await?.get_receipt().await?;
+    check_factory_creation(provider.clone(), proxy, receipt, CreationKind::Create).await?;
+
+    let create = factory.createProxyWithNonce(SAFE_SINGLETON, Bytes::new(), U256::from(42));
+    let proxy = create.call().await?.proxy;
+    let receipt = create.send().await?.get_receipt().await?;
+    check_factory_creation(provider, proxy, receipt, CreationKind::Create2).await?;
+
+    Ok(())
+}
@@ -0,0 +1,219 @@
+mod common;
+
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, B256, Bytes, address, b256},
+    providers::{Provider, ProviderBuilder},
+    sol_types::{SolCall, SolValue},
+};
+use eyre::Result;
+use query::ens::{
+    EnsResolver, IEnsRegistry, IEnsResolver, contenthash_uri, labelhash, namehash, reverse_name,
+};
+
+use common::{deploy_mock, mock_returns};
+
+const ALICE: Address = address!("0x00000000000000000000000000000000000a11ce");
+const BOB: Address = address!("0x0000000000000000000000000000000000000b0b");
+const CID: &str = "QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4";
+
+/// EIP-137 中的例子
+#[test]
+fn namehash_examples() {
+    assert_eq!(namehash(""), B256::ZERO);
+    assert_eq!(
+        namehash("eth"),
+        b256!("0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae")
+    );
+    assert_eq!(
+        namehash("foo.eth"),
+        b256!("0xde9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f")
+    );
+    assert_eq!(namehash("Foo.ETH"), namehash("foo.eth"));
+    assert_eq!(namehash("foo.eth."), namehash("foo.eth"));
+    assert_eq!(
+        labelhash("eth"),
+        b256!("0x4f5b812789fc606be1b3b16908db13fc7a9adf7ca72641f84d75b47069d3d7f0")
+    );
+    assert_eq!(
+        reverse_name(ALICE),
+        "00000000000000000000000000000000000a11ce.addr.reverse"
+    );
+}
+
+#[test]
+fn contenthash_uris() {
+    let mut ipfs = vec![0xe3, 0x01, 0x01, 0x70];
+    ipfs.extend(bs58::decode(CID).into_vec().unwrap());
+    assert_eq!(contenthash_uri(&ipfs), Some(format!("ipfs://{CID}")));
+
+    let mut swarm = vec![0xe4, 0x01, 0x01, 0xfa, 0x01, 0x1b, 0x20];
+    swarm.extend([0xab; 32]);
+    assert_eq!(
+        contenthash_uri(&swarm),
+        Some(format!("bzz://{}", "ab".repeat(32)))
+    );
+    assert_eq!(contenthash_uri(&ipfs[..ipfs.len() - 1]), None);
+    assert_eq!(contenthash_uri(&[]), None);
+}
+
+/// 普通的 anvil 上没有 ENS, 用 mock 合约作为 registry 和 resolver,
+/// 返回值和主网的 ENSRegistry/PublicResolver 的 ABI 相同
+#[tokio::test]
+async fn resolve_records() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let registry = deploy_mock(&provider).await?;
+    let resolver = deploy_mock(&provider).await?;
+    let ens = EnsResolver::new(provider.clone()).registry(registry);
+
+    let name = "alice.eth";
+    let node = namehash(name);
+    let mut contenthash = vec![0xe3, 0x01, 0x01, 0x70];
+    contenthash.extend(bs58::decode(CID).into_vec()?);
+
+    // 没有注册的名称, registry 返回 0 地址
+    let unknown = IEnsRegistry::resolverCall {
+        node: namehash("unknown.eth"),
+    };
+    mock_returns(&provider, registry, &unknown, &Address::ZERO.abi_encode()).await?;
+    assert_eq!(ens.resolver("unknown.eth").await?, None);
+    assert_eq!(ens.address("unknown.eth").await?, None);
+    assert!(ens.resolve_address("unknown.eth").await.is_err());
+
+    // 先以 0 地址作为 resolver, 再设置 resolver
+    let resolver_call = IEnsRegistry::resolverCall { node };
+    mock_returns(
+        &provider,
+        registry,
+        &resolver_call,
+        &Address::ZERO.abi_encode(),
+    )
+    .await?;
+    let before = provider.get_block_number().await?;
+    mock_returns(&provider, registry, &resolver_call, &resolver.abi_encode()).await?;
+    mock_returns(
+        &provider,
+        resolver,
+        &IEnsResolver::addrCall { node },
+        &ALICE.abi_encode(),
+    )
+    .await?;
+    let text = |key: &str| IEnsResolver::textCall {
+        node,
+        key: key.to_string(),
+    };
+    mock_returns(
+        &provider,
+        resolver,
+        &text("url"),
+        &IEnsResolver::textCall::abi_encode_returns(&("https://alloy.rs".to_string(),)),
+    )
+    .await?;
+    mock_returns(
+        &provider,
+        resolver,
+        &text("avatar"),
+        &IEnsResolver::textCall::abi_encode_returns(&(String::new(),)),
+    )
+    .await?;
+    mock_returns(
+        &provider,
+        resolver,
+        &IEnsResolver::contenthashCall { node },
+        &IEnsResolver::contenthashCall::abi_encode_returns(&(Bytes::from(contenthash.clone()),)),
+    )
+    .await?;
+
+    // 大小写不同的名称有相同的 node
+    assert_eq!(ens.resolver("Alice.ETH").await?, Some(resolver));
+    assert_eq!(ens.address(name).await?, Some(ALICE));
+    assert_eq!(
+        ens.text(name, "url").await?.as_deref(),
+        Some("https://alloy.rs")
+    );
+    assert_eq!(ens.text(name, "avatar").await?, None);
+    let hash = ens.contenthash(name).await?.unwrap();
+    assert_eq!(hash, contenthash);
+    assert_eq!(contenthash_uri(&hash), Some(format!("ipfs://{CID}")));
+    assert_eq!(ens.resolve_address(name).await?, ALICE);
+    assert_eq!(ens.resolve_address(&BOB.to_string()).await?, BOB);
+
+    // 设置记录之前的区块
+    let historical = EnsResolver::new(provider.clone())
+        .registry(registry)
+        .block(BlockId::number(before));
+    assert_eq!(historical.resolver(name).await?, None);
+    assert_eq!(historical.text(name, "url").await?, None);
+    assert!(historical.resolve_address(name).await.is_err());
+    Ok(())
+}
+
+/// 反向解析的名称需要正向解析回同一个地址
+#[tokio::test]
+async fn reverse_lookup() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let registry = deploy_mock(&provider).await?;
+    let resolver = deploy_mock(&provider).await?;
+    let ens = EnsResolver::new(provider.clone()).registry(registry);
+
+    let name = "alice.eth";
+    for node in [
+        namehash(name),
+        namehash(&reverse_name(ALICE)),
+        namehash(&reverse_name(BOB)),
+    ] {
+        mock_returns(
+            &provider,
+            registry,
+            &IEnsRegistry::resolverCall { node },
+            &resolver.abi_encode(),
+        )
+        .await?;
+    }
+    let set_addr = async |address: Address| {
+        mock_returns(
+            &provider,
+            resolver,
+            &IEnsResolver::addrCall {
+                node: namehash(name),
+            },
+            &address.abi_encode(),
+        )
+        .await
+    };
+    set_addr(ALICE).await?;
+    // BOB 的反向记录也声称是 alice.eth
+    for address in [ALICE, BOB] {
+        let call = IEnsResolver::nameCall {
+            node: namehash(&reverse_name(address)),
+        };
+        mock_returns(
+            &provider,
+            resolver,
+            &call,
+            &IEnsResolver::nameCall::abi_encode_returns(&(name.to_string(),)),
+        )
+        .await?;
+    }
+
+    assert_eq!(ens.lookup_address(ALICE).await?.as_deref(), Some(name));
+    assert_eq!(ens.lookup_address(BOB).await?, None);
+    let block = provider.get_block_number().await?;
+
+    // addr 改成 BOB 之后结果反过来, 之前的区块不变
+    set_addr(BOB).await?;
+    assert_eq!(ens.lookup_address(ALICE).await?, None);
+    assert_eq!(ens.lookup_address(BOB).await?.as_deref(), Some(name));
+    assert_eq!(ens.resolve_address(name).await?, BOB);
+
+    let historical = EnsResolver::new(provider.clone())
+        .registry(registry)
+        .block(BlockId::number(block));
+    assert_eq!(
+        historical.lookup_address(ALICE).await?.as_deref(),
+        Some(name)
+    );
+    assert_eq!(historical.lookup_address(BOB).await?, None);
+    assert_eq!(historical.resolve_address(name).await?, ALICE);
+    Ok(())
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use alloy::providers::{Provider, ProviderBuilder};
+use eyre::Result;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use query::export::{Dataset, ExportSummary, Exporter, Format};
+
+use common::CounterWithError;
+
+/// 文件中的行数, CSV 不包括表头
+fn count_rows(path: &Path, format: Format) -> Result<u64> {
+    Ok(match format {
+        Format::Csv => csv::Reader::from_path(path)?.records().count() as u64,
+        Format::Jsonl => BufReader::new(File::open(path)?).lines().count() as u64,
+        Format::Parquet => SerializedFileReader::new(File::open(path)?)?
+            .metadata()
+            .file_metadata()
+            .num_rows() as u64,
+    })
+}
+
+fn file_names(paths: &[PathBuf]) -> Vec<String> {
+    let mut names: Vec<_> = paths
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+/// 每个数据集中这些分段的文件名
+fn chunk_names(format: Format, datasets: &[Dataset], chunks: &[(u64, u64)]) -> Vec<String> {
+    let mut names: Vec<_> = datasets
+        .iter()
+        .flat_map(|dataset| {
+            chunks.iter().map(move |(from, to)| {
+                format!("{dataset}_{from:010}_{to:010}.{}", format.extension())
+            })
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// 本次写入的文件中的行数和 summary 中的一致
+fn assert_rows(summary: &ExportSummary, format: Format, expected: &[(Dataset, u64)]) -> Result<()> {
+    assert_eq!(summary.rows, expected.iter().copied().collect());
+    for (dataset, rows) in expected {
+        let written = summary
+            .files
+            .iter()
+            .filter(|path| path.parent().unwrap().ends_with(dataset.name()))
+            .map(|path| count_rows(path, format))
+            .sum::<Result<u64>>()?;
+        assert_eq!(written, *rows, "{dataset}");
+    }
+    Ok(())
+}
+
+/// 导出, 删除部分分段后重新导出, 检查跳过, 合并和重新写入的文件
+async fn export_and_resume(format: Format) -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    // 区块 1 部署, 区块 2..=6 各有一笔 increment 交易和一个 Increment 事件
+    let counter = CounterWithError::deploy(provider.clone()).await?;
+    for _ in 0..5 {
+        counter.increment().send().await?.get_receipt().await?;
+    }
+    assert_eq!(provider.get_block_number().await?, 6);
+
+    let out_dir = std::env::temp_dir().join(format!(
+        "query_export_{}_{}",
+        format.extension(),
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let exporter = Exporter::new(provider.clone(), &out_dir, format)
+        .datasets(&Dataset::ALL)
+        .chunk_blocks(3);
+    let all = Dataset::ALL;
+    let block_datasets = [Dataset::Transactions, Dataset::Receipts];
+
+    // 第一次只导出到区块 4, 分段 [3, 5] 只有一部分
+    let summary = exporter.export(0, 4).await?;
+    assert_eq!(
+        file_names(&summary.files),
+        chunk_names(format, &all, &[(0, 2), (3, 4)])
+    );
+    assert!(summary.skipped.is_empty());
+    assert!(summary.replaced.is_empty());
+    assert_rows(
+        &summary,
+        format,
+        &[
+            (Dataset::Logs, 3),
+            (Dataset::Blocks, 5),
+            (Dataset::Transactions, 4),
+            (Dataset::Receipts, 4),
+        ],
+    )?;
+
+    std::fs::remove_file(exporter.chunk_path(Dataset::Logs, 0, 2))?;
+    std::fs::remove_file(exporter.chunk_path(Dataset::Blocks, 3, 4))?;
+
+    // 从区块 4 继续: 其他数据集合并 [3, 4] 的旧文件, blocks 的旧文件已经删除, 只写入 [4, 5]
+    let summary = exporter.export(4, 6).await?;
+    let mut files = chunk_names(format, &all, &[(6, 6)]);
+    files.extend(chunk_names(
+        format,
+        &[Dataset::Logs, Dataset::Transactions, Dataset::Receipts],
+        &[(3, 5)],
+    ));
+    files.extend(chunk_names(format, &[Dataset::Blocks], &[(4, 5)]));
+    files.sort();
+    assert_eq!(file_names(&summary.files), files);
+    assert!(summary.skipped.is_empty());
+    assert_eq!(
+        file_names(&summary.replaced),
+        chunk_names(
+            format,
+            &[Dataset::Logs, Dataset::Transactions, Dataset::Receipts],
+            &[(3, 4)]
+        )
+    );
+    assert_rows(
+        &summary,
+        format,
+        &[
+            (Dataset::Logs, 4),
+            (Dataset::Blocks, 3),
+            (Dataset::Transactions, 4),
+            (Dataset::Receipts, 4),
+        ],
+    )?;
+
+    // 从头导出: 重新写入删除的 logs 分段, blocks 的 [4, 5] 合并为 [3, 5], 其他的跳过
+    let summary = exporter.export(0, 6).await?;
+    let mut files = chunk_names(format, &[Dataset::Logs], &[(0, 2)]);
+    files.extend(chunk_names(format, &[Dataset::Blocks], &[(3, 5)]));
+    files.sort();
+    assert_eq!(file_names(&summary.files), files);
+    let mut skipped = chunk_names(format, &all, &[(6, 6)]);
+    skipped.extend(chunk_names(format, &block_datasets, &[(0, 2), (3, 5)]));
+    skipped.extend(chunk_names(format, &[Dataset::Blocks], &[(0, 2)]));
+    skipped.extend(chunk_names(format, &[Dataset::Logs], &[(3, 5)]));
+    skipped.sort();
+    assert_eq!(file_names(&summary.skipped), skipped);
+    assert_eq!(
+        file_names(&summary.replaced),
+        chunk_names(format, &[Dataset::Blocks], &[(4, 5)])
+    );
+    assert_rows(
+        &summary,
+        format,
+        &[(Dataset::Logs, 1), (Dataset::Blocks, 3)],
+    )?;
+
+    // 最终每个数据集都是完整的 3 个分段, 没有遗留的 .tmp 文件
+    for (dataset, rows) in [
+        (Dataset::Logs, 5),
+        (Dataset::Blocks, 7),
+        (Dataset::Transactions, 6),
+        (Dataset::Receipts, 6),
+    ] {
+        let files: Vec<_> = std::fs::read_dir(out_dir.join(dataset.name()))?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<_>>()?;
+        assert_eq!(
+            file_names(&files),
+            chunk_names(format, &[dataset], &[(0, 2), (3, 5), (6, 6)])
+        );
+        let total = files
+            .iter()
+            .map(|path| count_rows(path, format))
+            .sum::<Result<u64>>()?;
+        assert_eq!(total, rows, "{dataset}");
+    }
+
+    std::fs::remove_dir_all(&out_dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn resume_csv() -> Result<()> {
+    export_and_resume(Format::Csv).await
+}
+
+#[tokio::test]
+async fn resume_jsonl() -> Result<()> {
+    export_and_resume(Format::Jsonl).await
+}
+
+#[tokio::test]
+async fn resume_parquet() -> Result<()> {
+    export_and_resume(Format::Parquet).await
+}
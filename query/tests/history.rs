@@ -0,0 +1,112 @@
+mod common;
+
+use alloy::{
+    primitives::{Address, B256, I256, U256, address, b256, utils::parse_ether},
+    providers::{Provider, ProviderBuilder, WalletProvider, ext::AnvilApi},
+    sol,
+};
+use eyre::Result;
+use query::history::{HistoryFetcher, IWETH9, TransferKind};
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface ILido {
+        function submit(address referral) external payable returns (uint256);
+        function transfer(address to, uint256 value) external returns (bool);
+        function balanceOf(address owner) external view returns (uint256);
+    }
+}
+
+const WETH: Address = address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+/// stETH 按 shares 记账, 余额随 totalPooledEther 变化, 不发出 Transfer
+const STETH: Address = address!("0xae7ab96520DE3A18E5e111B5EaAb095312D7fE84");
+/// Lido 中 keccak256("lido.Lido.beaconBalance"), 信标链上的余额, 是 totalPooledEther 的一部分
+const CL_BALANCE_POSITION: B256 =
+    b256!("0xa66d35f054e68143c18f32c990ed5cb972bb68a68f500cd2dd3a16bbf3686483");
+
+/// fork 主网, 在 WETH 和 stETH 上产生一些转账, 修改 Lido 的信标链余额模拟 rebase,
+/// 再根据 logs 重建余额
+#[tokio::test]
+#[ignore = "forks mainnet, set RPC_URL and run with --ignored"]
+async fn weth_and_steth() -> Result<()> {
+    let provider = ProviderBuilder::new()
+        .on_anvil_with_wallet_and_config(|anvil| anvil.fork(common::fork_url()))?;
+    let account = provider.default_signer_address();
+    let other = Address::with_last_byte(0xbb);
+    let fork_block = provider.get_block_number().await?;
+
+    // deposit 2, 转出 0.5, withdraw 0.25
+    let weth = IWETH9::new(WETH, provider.clone());
+    let steth = ILido::new(STETH, provider.clone());
+    let txs = [
+        weth.deposit().value(parse_ether("2")?).send().await?,
+        weth.transfer(other, parse_ether("0.5")?).send().await?,
+        weth.withdraw(parse_ether("0.25")?).send().await?,
+        steth
+            .submit(Address::ZERO)
+            .value(parse_ether("1")?)
+            .send()
+            .await?,
+        steth.transfer(other, parse_ether("0.3")?).send().await?,
+    ];
+    for tx in txs {
+        assert!(tx.get_receipt().await?.status());
+    }
+
+    // 信标链余额增加 1000 ETH, 所有 stETH 持有者的余额按 shares 比例增加
+    let before_rebase = steth.balanceOf(account).call().await?._0;
+    let cl_balance = provider
+        .get_storage_at(STETH, CL_BALANCE_POSITION.into())
+        .await?;
+    provider
+        .anvil_set_storage_at(
+            STETH,
+            CL_BALANCE_POSITION.into(),
+            (cl_balance + parse_ether("1000")?).into(),
+        )
+        .await?;
+    provider.anvil_mine(Some(1), None).await?;
+    let rebase =
+        I256::from_raw(steth.balanceOf(account).call().await?._0) - I256::from_raw(before_rebase);
+    assert!(rebase.is_positive());
+    let latest = provider.get_block_number().await?;
+
+    // 不指定 token, Deposit/Withdrawal 只在已知的 WETH9 上查询
+    let history = HistoryFetcher::new(provider, account)
+        .fetch(fork_block + 1, latest)
+        .await?;
+    assert_eq!(history.tokens.len(), 2);
+
+    let weth = history.tokens.iter().find(|t| t.token == WETH).unwrap();
+    let kinds: Vec<_> = weth.transfers.iter().map(|t| t.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TransferKind::Deposit,
+            TransferKind::Transfer,
+            TransferKind::Withdrawal
+        ]
+    );
+    let start = I256::from_raw(weth.start_balance.unwrap());
+    let expected =
+        ["2", "1.5", "1.25"].map(|amount| start + I256::from_raw(parse_ether(amount).unwrap()));
+    assert_eq!(weth.balances, expected);
+    assert_eq!(weth.mismatch(), None);
+
+    let steth = history.tokens.iter().find(|t| t.token == STETH).unwrap();
+    // submit 时从 0 地址 mint
+    assert_eq!(steth.transfers.len(), 2);
+    assert_eq!(steth.transfers[0].from, Address::ZERO);
+    assert_eq!(steth.transfers[1].to, other);
+    // 差值是 rebase 增加的余额, 加上 shares 取整造成的几 wei 误差
+    let mismatch = steth.mismatch().unwrap();
+    assert!((mismatch - rebase).abs() <= I256::try_from(2)?);
+    assert_eq!(
+        I256::from_raw(steth.end_balance.unwrap()) - steth.computed_balance(),
+        mismatch
+    );
+    assert!(steth.end_balance.unwrap() > U256::ZERO);
+
+    Ok(())
+}
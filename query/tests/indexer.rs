@@ -0,0 +1,134 @@
+mod common;
+
+use alloy::{
+    json_abi::Event,
+    providers::{Provider, ProviderBuilder, ext::AnvilApi},
+    rpc::types::Filter,
+};
+use eyre::Result;
+use query::indexer::{IndexEvent, IndexFilter, Indexer, IndexerDb};
+
+use common::CounterWithError;
+
+fn increment_filter(
+    counter: &CounterWithError::CounterWithErrorInstance<(), impl Provider>,
+) -> Result<IndexFilter> {
+    Ok(IndexFilter::new(
+        "increments",
+        Event::parse("event Increment(address indexed by, int256 indexed value)")?,
+        Filter::new().address(*counter.address()),
+    ))
+}
+
+fn increment_values(db: &IndexerDb) -> Result<Vec<String>> {
+    Ok(db
+        .logs("increments")?
+        .into_iter()
+        .filter_map(|log| Some(log.decoded?["value"].as_str()?.to_string()))
+        .collect())
+}
+
+/// 重启后从 checkpoint 继续, 以及通过 snapshot/revert 制造的 reorg
+#[tokio::test]
+async fn checkpoint_and_reorg() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let counter = CounterWithError::deploy(provider.clone()).await?;
+    let filter = increment_filter(&counter)?;
+
+    let db_path = std::env::temp_dir().join(format!("query_indexer_{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&db_path);
+    let new_indexer = || -> Result<_> {
+        Ok(
+            Indexer::new(provider.clone(), IndexerDb::open(&db_path)?, 0)
+                .filter(filter.clone())
+                .batch_size(2),
+        )
+    };
+
+    counter.increment().send().await?.get_receipt().await?;
+    // 之后回退到这里, 制造一次 reorg
+    let snapshot = provider.anvil_snapshot().await?;
+    counter.increment().send().await?.get_receipt().await?;
+    counter.increment().send().await?.get_receipt().await?;
+
+    let mut indexer = new_indexer()?;
+    let events = indexer.sync().await?;
+    assert!(
+        events
+            .iter()
+            .all(|event| matches!(event, IndexEvent::Indexed { .. }))
+    );
+    assert_eq!(increment_values(indexer.db())?, ["1", "2", "3"]);
+
+    // 模拟重启, 从数据库中的 checkpoint 继续, 没有新的区块需要处理
+    let checkpoint = indexer.db().checkpoint()?;
+    drop(indexer);
+    let mut indexer = new_indexer()?;
+    assert_eq!(indexer.db().checkpoint()?, checkpoint);
+    assert!(indexer.sync().await?.is_empty());
+
+    // 回退到 snapshot, 在新的分叉上 increment, 并多出几个块超过原来的高度
+    // 先出一个空块: 同一个 nonce 的 increment 交易相同, 时间戳也相同时区块 3 的 hash 不变
+    provider.anvil_revert(snapshot).await?;
+    provider.anvil_mine(Some(1), None).await?;
+    counter.increment().send().await?.get_receipt().await?;
+    provider.anvil_mine(Some(2), None).await?;
+
+    let events = indexer.sync().await?;
+    assert_eq!(
+        events.first(),
+        Some(&IndexEvent::Reorged {
+            ancestor: Some(2),
+            removed_logs: 2,
+        })
+    );
+    // 孤块中的 2, 3 被删除, 新分叉上的 increment 重新得到 2
+    assert_eq!(increment_values(indexer.db())?, ["1", "2"]);
+
+    drop(indexer);
+    std::fs::remove_file(&db_path)?;
+    Ok(())
+}
+
+/// reorg 超过 reorg_depth 时返回错误, 不会逐个检查所有已处理的区块
+#[tokio::test]
+async fn reorg_deeper_than_depth() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let counter = CounterWithError::deploy(provider.clone()).await?;
+    let snapshot = provider.anvil_snapshot().await?;
+    for _ in 0..3 {
+        counter.increment().send().await?.get_receipt().await?;
+    }
+    provider.anvil_mine(Some(4), None).await?;
+
+    let mut indexer = Indexer::new(provider.clone(), IndexerDb::open_in_memory()?, 0)
+        .filter(increment_filter(&counter)?)
+        .batch_size(2)
+        .reorg_depth(2);
+    indexer.sync().await?;
+    assert_eq!(increment_values(indexer.db())?, ["1", "2", "3"]);
+
+    provider.anvil_revert(snapshot).await?;
+    provider.anvil_mine(Some(10), None).await?;
+    let error = indexer.sync().await.unwrap_err();
+    assert!(
+        error.to_string().contains("reorg deeper than 2 blocks"),
+        "{error}"
+    );
+
+    Ok(())
+}
+
+/// anonymous 事件没有 topic0, 不能按事件签名过滤
+#[test]
+fn anonymous_event_filter() -> Result<()> {
+    let event = Event::parse("event Ping(uint256 indexed value) anonymous")?;
+    let filter = IndexFilter::new("pings", event, Filter::new());
+    assert!(filter.filter.topics[0].is_empty());
+
+    let event = Event::parse("event Ping(uint256 indexed value)")?;
+    let selector = event.selector();
+    let filter = IndexFilter::new("pings", event, Filter::new());
+    assert!(filter.filter.topics[0].matches(&selector));
+    Ok(())
+}
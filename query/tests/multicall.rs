@@ -0,0 +1,125 @@
+mod common;
+
+use alloy::{
+    primitives::U256,
+    providers::{Provider, ProviderBuilder, WalletProvider, ext::AnvilApi},
+    sol,
+};
+use eyre::Result;
+use query::multicall::{CallError, MULTICALL3_ADDRESS, Multicall};
+
+use common::Counter;
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface IWETH9 {
+        function balanceOf(address owner) external view returns (uint256);
+    }
+}
+
+/// 部署 3 个 Counter, number 分别为 10, 20, 30
+async fn counters(
+    provider: impl Provider + Clone,
+) -> Result<Vec<Counter::CounterInstance<(), impl Provider + Clone>>> {
+    let mut counters = Vec::new();
+    for i in 1..=3u64 {
+        let counter = Counter::deploy(provider.clone()).await?;
+        counter
+            .setNumber(U256::from(i * 10))
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        counters.push(counter);
+    }
+    Ok(counters)
+}
+
+#[tokio::test]
+async fn aggregate_reads() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let owner = provider.default_signer_address();
+    let counters = counters(provider.clone()).await?;
+    assert!(provider.get_code_at(MULTICALL3_ADDRESS).await?.is_empty());
+
+    let mut multicall = Multicall::new(provider.clone());
+    let numbers = multicall.extend(counters.iter().map(|counter| counter.number()));
+    // Counter 没有 balanceOf, 调用会 revert
+    let reverted = multicall.add(IWETH9::new(*counters[0].address(), &provider).balanceOf(owner));
+    // 调用 EOA 成功但是没有返回数据, 解码失败
+    let no_code = multicall.add(IWETH9::new(owner, &provider).balanceOf(owner));
+    let results = multicall.call().await?;
+    assert_eq!(results.len(), 5);
+    // 自动部署了 Multicall3
+    assert!(!provider.get_code_at(MULTICALL3_ADDRESS).await?.is_empty());
+
+    let numbers = results
+        .get_all(&numbers)
+        .into_iter()
+        .map(|number| Ok(number?.number))
+        .collect::<Result<Vec<_>, CallError>>()?;
+    assert_eq!(numbers, [U256::from(10), U256::from(20), U256::from(30)]);
+    assert!(matches!(results.get(reverted), Err(CallError::Reverted(_))));
+    assert!(matches!(
+        results.get(no_code),
+        Err(CallError::Decode { .. })
+    ));
+
+    Ok(())
+}
+
+/// 句柄只能用在同一个 Multicall 发送之前添加的调用
+#[tokio::test]
+async fn invalid_handles() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let counters = counters(provider.clone()).await?;
+
+    let mut first = Multicall::new(provider.clone());
+    first.add(counters[0].number());
+    let results = first.call().await?;
+    let late = first.add(counters[1].number());
+    assert!(matches!(
+        results.get(late),
+        Err(CallError::MissingResult { index: 1, len: 1 })
+    ));
+    assert_eq!(first.call().await?.get(late)?.number, U256::from(20));
+
+    let mut second = Multicall::new(provider.clone());
+    let foreign = second.add(counters[2].number());
+    assert!(matches!(
+        results.get(foreign),
+        Err(CallError::ForeignHandle { index: 0 })
+    ));
+
+    Ok(())
+}
+
+/// anvil_setCode 只修改当前状态, 之后挖出的区块才包含 Multicall3
+#[tokio::test]
+async fn pinned_block() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let counters = counters(provider.clone()).await?;
+    let before_deploy = provider.get_block_number().await?;
+    // latest 区块读取的是当前状态, 会看到 anvil_setCode 的修改
+    provider.evm_mine(None).await?;
+
+    let mut multicall = Multicall::new(provider.clone()).block(before_deploy.into());
+    let number = multicall.add(counters[0].number());
+    let err = multicall.call().await.unwrap_err();
+    assert!(err.to_string().contains("anvil_setCode"), "{err}");
+
+    // 自动部署之后挖出的区块
+    let increment = async || -> Result<u64> {
+        let receipt = counters[0].increment().send().await?.get_receipt().await?;
+        Ok(receipt.block_number.unwrap())
+    };
+    let block = increment().await?;
+    increment().await?;
+    let multicall = multicall.block(block.into());
+    assert_eq!(multicall.call().await?.get(number)?.number, U256::from(11));
+    let multicall = multicall.block(block.saturating_add(1).into());
+    assert_eq!(multicall.call().await?.get(number)?.number, U256::from(12));
+
+    Ok(())
+}
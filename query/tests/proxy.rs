@@ -0,0 +1,179 @@
+mod common;
+
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{Address, Bytes, U256, address, hex},
+    providers::{Provider, ProviderBuilder, WalletProvider},
+    rpc::types::TransactionRequest,
+    sol,
+};
+use eyre::{OptionExt, Result};
+use query::proxy::{ProxyKind, ProxyResolver, minimal_proxy_implementation};
+
+use common::Counter;
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract EigenPodManager {
+        function createPod() external returns (address);
+        function getPod(address podOwner) external view returns (address);
+        function eigenPodBeacon() external view returns (address);
+    }
+
+    #[allow(missing_docs)]
+    // OpenZeppelin v4 ProxyAdmin
+    #[sol(rpc)]
+    contract ProxyAdmin {
+        function getProxyImplementation(address proxy) external view returns (address);
+    }
+
+    #[allow(missing_docs)]
+    // Safe v1.3.0 GnosisSafeProxyFactory
+    #[sol(rpc)]
+    contract GnosisSafeProxyFactory {
+        function createProxyWithNonce(address _singleton, bytes memory initializer, uint256 saltNonce) external returns (address proxy);
+    }
+}
+
+/// EigenLayer EigenPodManager, OpenZeppelin TransparentUpgradeableProxy
+const EIGEN_POD_MANAGER: Address = address!("0x91E677b07F7AF907ec9a428aafA9fc14a0d3A338");
+const SAFE_PROXY_FACTORY: Address = address!("0xa6B71E26C5e0845f74c812102Ca7114b6a896AB2");
+const SAFE_SINGLETON: Address = address!("0xd9Db270c1B5E3Bd161E8c8503c55cEABeE709552");
+
+/// 部署 EIP-1167 clone, creation code 来自 EIP-1167 (和 OpenZeppelin Clones 相同)
+async fn clone(provider: impl Provider, implementation: Address) -> Result<Address> {
+    let code = [
+        &hex!("3d602d80600a3d3981f3363d3d373d3d3d363d73")[..],
+        implementation.as_slice(),
+        &hex!("5af43d82803e903d91602b57fd5bf3"),
+    ]
+    .concat();
+    let tx = TransactionRequest::default().with_deploy_code(Bytes::from(code));
+    let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+    receipt.contract_address.ok_or_eyre("no contract deployed")
+}
+
+/// EIP-1167 中的示例 runtime code
+#[test]
+fn minimal_proxy_runtime() {
+    let code = hex!(
+        "363d3d373d3d3d363d73bebebebebebebebebebebebebebebebebebebebe5af43d82803e903d91602b57fd5bf3"
+    );
+    assert_eq!(
+        minimal_proxy_implementation(&code),
+        Some(address!("0xbebebebebebebebebebebebebebebebebebebebe"))
+    );
+    assert_eq!(minimal_proxy_implementation(&code[1..]), None);
+}
+
+#[tokio::test]
+async fn minimal_proxy() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let counter = *Counter::deploy(provider.clone()).await?.address();
+    let minimal = clone(provider.clone(), counter).await?;
+
+    let resolver = ProxyResolver::new(provider.clone());
+    // 不是代理
+    let resolution = resolver.resolve(counter).await?;
+    assert!(resolution.hops.is_empty());
+    assert_eq!(resolution.implementation(), counter);
+
+    let resolution = resolver.resolve(minimal).await?;
+    assert_eq!(resolution.addresses(), [minimal, counter]);
+    assert_eq!(resolution.hops[0].kind, ProxyKind::Eip1167);
+
+    // 通过代理调用 Counter, 状态存放在代理合约中
+    let through_proxy = Counter::new(minimal, provider.clone());
+    through_proxy
+        .setNumber(U256::from(42))
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    assert_eq!(through_proxy.number().call().await?.number, U256::from(42));
+    assert_eq!(
+        Counter::new(counter, provider)
+            .number()
+            .call()
+            .await?
+            .number,
+        U256::ZERO
+    );
+
+    Ok(())
+}
+
+/// 主网上的 TransparentUpgradeableProxy 和 BeaconProxy
+#[tokio::test]
+#[ignore = "forks mainnet, set RPC_URL and run with --ignored"]
+async fn eip1967_proxies() -> Result<()> {
+    let provider = ProviderBuilder::new()
+        .on_anvil_with_wallet_and_config(|anvil| anvil.fork(common::fork_url()))?;
+    let resolver = ProxyResolver::new(provider.clone());
+
+    let hop = resolver
+        .detect(EIGEN_POD_MANAGER)
+        .await?
+        .ok_or_eyre("not a proxy")?;
+    assert_eq!(hop.kind, ProxyKind::Eip1967);
+    // admin 是 ProxyAdmin, 通过它读取的实现地址和 implementation slot 一致
+    let admin = hop.admin.ok_or_eyre("no admin")?;
+    let implementation = ProxyAdmin::new(admin, provider.clone())
+        .getProxyImplementation(EIGEN_POD_MANAGER)
+        .call()
+        .await?
+        ._0;
+    assert_eq!(hop.implementation, implementation);
+    assert!(resolver.detect(implementation).await?.is_none());
+
+    // createPod 为调用者部署一个 BeaconProxy
+    let manager = EigenPodManager::new(EIGEN_POD_MANAGER, provider.clone());
+    manager.createPod().send().await?.get_receipt().await?;
+    let pod = manager
+        .getPod(provider.default_signer_address())
+        .call()
+        .await?
+        ._0;
+    let beacon = manager.eigenPodBeacon().call().await?._0;
+    let hop = resolver.detect(pod).await?.ok_or_eyre("not a proxy")?;
+    assert_eq!(hop.kind, ProxyKind::Eip1967Beacon);
+    assert_eq!(hop.beacon, Some(beacon));
+    assert!(resolver.detect(hop.implementation).await?.is_none());
+
+    // 代理的代理: clone -> EigenPodManager -> 实现合约
+    let nested = clone(provider.clone(), EIGEN_POD_MANAGER).await?;
+    let resolution = resolver.resolve(nested).await?;
+    assert_eq!(
+        resolution.addresses(),
+        [nested, EIGEN_POD_MANAGER, implementation]
+    );
+    assert!(
+        ProxyResolver::new(provider)
+            .max_depth(1)
+            .resolve(nested)
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
+/// 通过 Safe v1.3.0 的 factory 部署 Safe proxy
+#[tokio::test]
+#[ignore = "forks mainnet, set RPC_URL and run with --ignored"]
+async fn safe_proxy() -> Result<()> {
+    let provider = ProviderBuilder::new()
+        .on_anvil_with_wallet_and_config(|anvil| anvil.fork(common::fork_url()))?;
+    let factory = GnosisSafeProxyFactory::new(SAFE_PROXY_FACTORY, provider.clone());
+    let create = factory.createProxyWithNonce(SAFE_SINGLETON, Bytes::new(), U256::from(42));
+    // CREATE2 的地址是确定的, 先模拟得到地址
+    let proxy = create.call().await?.proxy;
+    create.send().await?.get_receipt().await?;
+
+    let resolution = ProxyResolver::new(provider).resolve(proxy).await?;
+    assert_eq!(resolution.addresses(), [proxy, SAFE_SINGLETON]);
+    assert_eq!(resolution.hops[0].kind, ProxyKind::Safe);
+
+    Ok(())
+}
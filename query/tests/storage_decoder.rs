@@ -0,0 +1,245 @@
+use alloy::{
+    dyn_abi::DynSolValue,
+    primitives::{Address, B256, I256, U256, keccak256},
+    providers::{ProviderBuilder, ext::AnvilApi},
+    sol_types::SolValue,
+};
+use eyre::Result;
+use query::storage::{BatchSource, MappingKey, StorageDecoder, StorageLayout};
+
+const LONG_NAME: &str = "a string that is longer than thirty-one bytes";
+
+/// 动态数组, 长 string/bytes 的数据起始位置 keccak256(slot)
+fn data_slot(slot: U256) -> U256 {
+    U256::from_be_bytes(keccak256(slot.to_be_bytes::<32>()).0)
+}
+
+/// 值类型 key 的 mapping: keccak256(abi.encode(key, slot))
+fn entry_slot(key: B256, slot: U256) -> U256 {
+    U256::from_be_bytes(keccak256((key, slot).abi_encode()).0)
+}
+
+/// string 和 bytes 在 storage 中的编码
+///
+/// 小于 32 字节时数据左对齐, 最低字节为 len * 2; 否则 slot 中存放 len * 2 + 1,
+/// 数据从 keccak256(slot) 开始
+fn encode_bytes(slot: U256, data: &[u8]) -> Vec<(U256, U256)> {
+    let word = |chunk: &[u8]| {
+        let mut word = [0u8; 32];
+        word[..chunk.len()].copy_from_slice(chunk);
+        word
+    };
+    if data.len() < 32 {
+        let mut short = word(data);
+        short[31] = data.len() as u8 * 2;
+        return vec![(slot, U256::from_be_bytes(short))];
+    }
+
+    let mut words = vec![(slot, U256::from(data.len() * 2 + 1))];
+    for (i, chunk) in data.chunks(32).enumerate() {
+        words.push((
+            data_slot(slot) + U256::from(i),
+            U256::from_be_bytes(word(chunk)),
+        ));
+    }
+    words
+}
+
+fn uint(value: u64, bits: usize) -> DynSolValue {
+    DynSolValue::Uint(U256::from(value), bits)
+}
+
+/// 按 solc 的规则解析各种编码的状态变量
+///
+/// src/layout/StorageTypes.json 按 solc 的 storageLayout 格式手写, 对应的合约:
+///
+/// ```solidity
+/// contract StorageTypes {
+///     struct Info { address owner; uint64 nonce; bool active; uint256 amount; string name; }
+///
+///     uint8 small;
+///     bool flag;
+///     address owner;
+///     int16 delta;
+///     string shortName;
+///     string longName;
+///     bytes shortData;
+///     bytes longData;
+///     Info info;
+///     uint64[3] fixedSmall;
+///     uint256[2] fixedWords;
+///     uint128[] dynamic;
+///     uint32[][] nested;
+///     mapping(address => mapping(uint256 => Info)) infos;
+/// }
+/// ```
+///
+/// 用 anvil_setStorageAt 按 Solidity 文档中的编码写入数据, 再用 StorageDecoder 读取
+#[tokio::test]
+async fn decode_all_encodings() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let contract = Address::with_last_byte(0x42);
+    let alice = Address::with_last_byte(0xaa);
+    let bob = Address::with_last_byte(0xbb);
+    let address_word = |address: Address| U256::from_be_slice(address.as_slice());
+    let slot = |slot: u64| U256::from(slot);
+    let one_ether = U256::from(10).pow(U256::from(18));
+    let long_data = vec![0xcd; 32];
+
+    // nested 的两个内层数组, 长度存放在外层数组的元素中
+    let inner = [data_slot(slot(12)), data_slot(slot(12)) + U256::from(1)];
+    let mut words = vec![
+        // small, flag, owner, delta 打包在 slot 0, 负数占满 int16 的 2 个字节
+        (
+            slot(0),
+            U256::from(0xfe)
+                | U256::from(1) << 8
+                | address_word(alice) << 16
+                | U256::from(0xfffd) << 176,
+        ),
+        // info 的 owner, nonce, active 打包在 struct 的第一个 slot
+        (
+            slot(5),
+            address_word(bob) | U256::from(9) << 160 | U256::from(1) << 224,
+        ),
+        (slot(6), one_ether),
+        // fixedSmall 的 3 个 uint64 在同一个 slot
+        (
+            slot(8),
+            U256::from(1) | U256::from(2) << 64 | U256::from(3) << 128,
+        ),
+        (slot(9), U256::from(10)),
+        (slot(10), U256::from(20)),
+        // dynamic = [5, 6, 7], 每个 slot 两个 uint128
+        (slot(11), U256::from(3)),
+        (data_slot(slot(11)), U256::from(5) | U256::from(6) << 128),
+        (data_slot(slot(11)) + U256::from(1), U256::from(7)),
+        // nested = [[1, 2], [3]]
+        (slot(12), U256::from(2)),
+        (inner[0], U256::from(2)),
+        (inner[1], U256::from(1)),
+        (data_slot(inner[0]), U256::from(1) | U256::from(2) << 32),
+        (data_slot(inner[1]), U256::from(3)),
+    ];
+    words.extend(encode_bytes(slot(1), b"alloy"));
+    words.extend(encode_bytes(slot(2), LONG_NAME.as_bytes()));
+    words.extend(encode_bytes(slot(3), &[0x01, 0x02]));
+    // 正好 32 字节的 bytes 按长数据存放
+    words.extend(encode_bytes(slot(4), &long_data));
+    words.extend(encode_bytes(slot(7), b"info"));
+
+    // infos[alice][7]
+    let entry = entry_slot(
+        U256::from(7).into(),
+        entry_slot(alice.into_word(), slot(13)),
+    );
+    words.push((entry, address_word(alice) | U256::from(1) << 160));
+    words.push((entry + U256::from(1), U256::from(5)));
+    words.extend(encode_bytes(entry + U256::from(2), LONG_NAME.as_bytes()));
+
+    for (key, value) in words {
+        provider
+            .anvil_set_storage_at(contract, key, value.into())
+            .await?;
+    }
+
+    let layout = StorageLayout::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/layout/StorageTypes.json"
+    ))?;
+    let source = BatchSource::at_latest(provider.clone(), contract).await?;
+    let decoder = StorageDecoder::new(source, layout);
+
+    assert_eq!(decoder.read("small").await?, uint(0xfe, 8));
+    assert_eq!(decoder.read("flag").await?, DynSolValue::Bool(true));
+    assert_eq!(decoder.read("owner").await?, DynSolValue::Address(alice));
+    assert_eq!(
+        decoder.read("delta").await?,
+        DynSolValue::Int(I256::try_from(-3)?, 16)
+    );
+
+    assert_eq!(
+        decoder.read("shortName").await?,
+        DynSolValue::String("alloy".to_string())
+    );
+    assert_eq!(
+        decoder.read("longName").await?,
+        DynSolValue::String(LONG_NAME.to_string())
+    );
+    assert_eq!(
+        decoder.read("shortData").await?,
+        DynSolValue::Bytes(vec![0x01, 0x02])
+    );
+    assert_eq!(
+        decoder.read("longData").await?,
+        DynSolValue::Bytes(long_data)
+    );
+
+    assert_eq!(decoder.slots("info")?, vec![slot(5), slot(6), slot(7)]);
+    assert_eq!(
+        decoder.read("info").await?,
+        DynSolValue::Tuple(vec![
+            DynSolValue::Address(bob),
+            uint(9, 64),
+            DynSolValue::Bool(true),
+            DynSolValue::Uint(one_ether, 256),
+            DynSolValue::String("info".to_string()),
+        ])
+    );
+
+    assert_eq!(
+        decoder.read("fixedSmall").await?,
+        DynSolValue::FixedArray(vec![uint(1, 64), uint(2, 64), uint(3, 64)])
+    );
+    assert_eq!(
+        decoder.read("fixedWords").await?,
+        DynSolValue::FixedArray(vec![uint(10, 256), uint(20, 256)])
+    );
+    assert_eq!(
+        decoder.read("dynamic").await?,
+        DynSolValue::Array(vec![uint(5, 128), uint(6, 128), uint(7, 128)])
+    );
+    assert_eq!(
+        decoder.read("nested").await?,
+        DynSolValue::Array(vec![
+            DynSolValue::Array(vec![uint(1, 32), uint(2, 32)]),
+            DynSolValue::Array(vec![uint(3, 32)]),
+        ])
+    );
+
+    let keys = |owner: Address, id: u64| [MappingKey::from(owner), MappingKey::from(id)];
+    assert_eq!(
+        decoder.read_mapping("infos", &keys(alice, 7)).await?,
+        DynSolValue::Tuple(vec![
+            DynSolValue::Address(alice),
+            uint(1, 64),
+            DynSolValue::Bool(false),
+            uint(5, 256),
+            DynSolValue::String(LONG_NAME.to_string()),
+        ])
+    );
+    // 没有写入的 key 是各个成员的零值
+    assert_eq!(
+        decoder.read_mapping("infos", &keys(bob, 7)).await?,
+        DynSolValue::Tuple(vec![
+            DynSolValue::Address(Address::ZERO),
+            uint(0, 64),
+            DynSolValue::Bool(false),
+            uint(0, 256),
+            DynSolValue::String(String::new()),
+        ])
+    );
+    // 只给一个 key 得到的是内层 mapping, 不能直接读取; key 太多时也会报错
+    assert!(
+        decoder
+            .read_mapping("infos", &[MappingKey::from(alice)])
+            .await
+            .is_err()
+    );
+    let mut too_many = keys(alice, 7).to_vec();
+    too_many.push(MappingKey::from(1u64));
+    assert!(decoder.read_mapping("infos", &too_many).await.is_err());
+    assert!(decoder.read("infos").await.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,263 @@
+mod common;
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{Address, B256, Bytes, U256, address, b256, keccak256, utils::parse_ether},
+    providers::{Provider, ProviderBuilder, WalletProvider, ext::AnvilApi},
+    sol_types::SolValue,
+};
+use eyre::Result;
+use query::history::IWETH9;
+use query::storage::{SlotPath, StorageLayout};
+
+use common::esRNT;
+
+const WETH: Address = address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+/// 按 solc 输出的 storageLayout 计算 struct 数组元素和成员的位置,
+/// 和 esRNT 构造函数写入的数据对比
+#[tokio::test]
+async fn struct_array_slots() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let contract = esRNT::deploy(provider.clone()).await?;
+    let address = *contract.address();
+    let deployed_at = provider
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .await?
+        .unwrap()
+        .header
+        .timestamp;
+
+    let layout = StorageLayout::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/layout/esRNT.json"
+    ))?;
+    let locks = layout.item("_locks")?;
+    let base = layout.type_info(&locks.type_id)?.base.clone().unwrap();
+    let lock_info = layout.type_info(&base)?;
+    let member = |index: usize, label: &str| {
+        let member = lock_info
+            .members
+            .iter()
+            .flatten()
+            .find(|member| member.label == label)
+            .unwrap();
+        SlotPath::new(locks.slot)
+            .index(index, lock_info.number_of_bytes)
+            .member(member.slot.to(), member.offset)
+    };
+
+    let len = provider.get_storage_at(address, locks.slot).await?;
+    assert_eq!(len, U256::from(11));
+
+    for i in 0..11 {
+        let user = member(i, "user");
+        let word = provider.get_storage_at(address, user.slot()).await?;
+        assert_eq!(user.offset(), 0);
+        assert_eq!(
+            Address::from_word(word.into()),
+            Address::with_last_byte(i as u8 + 1)
+        );
+
+        // startTime 和 user 打包在同一个 slot
+        let start_time = member(i, "startTime");
+        assert_eq!(start_time.slot(), user.slot());
+        let word = provider.get_storage_at(address, start_time.slot()).await?;
+        let value = (word >> (start_time.offset() * 8)) & U256::from(u64::MAX);
+        assert_eq!(value, U256::from(deployed_at * 2 - i as u64));
+
+        let amount = member(i, "amount");
+        assert_eq!(amount.slot(), user.slot() + U256::from(1));
+        let value = provider.get_storage_at(address, amount.slot()).await?;
+        assert_eq!(value, parse_ether("1")? * U256::from(i + 1));
+    }
+
+    Ok(())
+}
+
+/// 各种 key 的 mapping, 嵌套 mapping 和 value 为 struct 的 mapping
+///
+/// src/layout/Mappings.json 按 solc 的 storageLayout 格式手写, 对应的合约:
+///
+/// ```solidity
+/// contract Mappings {
+///     struct Position { address owner; uint96 shares; uint256 debt; }
+///
+///     mapping(address => uint256) balances;
+///     mapping(string => uint256) names;
+///     mapping(bytes => bytes32) hashes;
+///     mapping(address => mapping(address => uint256)) allowances;
+///     mapping(uint256 => Position) positions;
+/// }
+/// ```
+///
+/// 按 Solidity 文档中的公式计算 slot, 用 anvil_setStorageAt 写入, 再按 SlotPath 读取
+#[tokio::test]
+async fn mapping_key_slots() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let contract = Address::with_last_byte(0x42);
+    let alice = Address::with_last_byte(0xaa);
+    let bob = Address::with_last_byte(0xbb);
+
+    let layout = StorageLayout::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/layout/Mappings.json"
+    ))?;
+    let slot = |label: &str| layout.item(label).unwrap().slot;
+    // 值类型的 key 填充到 32 字节: keccak256(abi.encode(key, slot))
+    let word_key =
+        |key: B256, slot: U256| U256::from_be_bytes(keccak256((key, slot).abi_encode()).0);
+    // string 和 bytes 的 key 不填充: keccak256(abi.encodePacked(key, slot))
+    let packed_key = |key: &[u8], slot: U256| {
+        U256::from_be_bytes(keccak256([key, &slot.to_be_bytes::<32>()].concat()).0)
+    };
+
+    let long_name = "a string key longer than thirty-two bytes";
+    let long_bytes = Bytes::from(vec![0xab; 40]);
+    let hash = b256!("0x1111111111111111111111111111111111111111111111111111111111111111");
+
+    let mut cases = vec![
+        (
+            SlotPath::new(slot("balances")).key(alice),
+            word_key(alice.into_word(), slot("balances")),
+            U256::from(100),
+        ),
+        (
+            SlotPath::new(slot("balances")).key(bob),
+            word_key(bob.into_word(), slot("balances")),
+            U256::from(101),
+        ),
+        (
+            SlotPath::new(slot("names")).key("alice"),
+            packed_key(b"alice", slot("names")),
+            U256::from(1),
+        ),
+        (
+            SlotPath::new(slot("names")).key(""),
+            packed_key(b"", slot("names")),
+            U256::from(2),
+        ),
+        (
+            SlotPath::new(slot("names")).key(long_name),
+            packed_key(long_name.as_bytes(), slot("names")),
+            U256::from(3),
+        ),
+        (
+            SlotPath::new(slot("hashes")).key(Bytes::from_static(&[0x00, 0xff])),
+            packed_key(&[0x00, 0xff], slot("hashes")),
+            hash.into(),
+        ),
+        (
+            SlotPath::new(slot("hashes")).key(long_bytes.clone()),
+            packed_key(&long_bytes, slot("hashes")),
+            U256::from(4),
+        ),
+        // allowances[alice][bob] 和 allowances[bob][alice] 是不同的 slot
+        (
+            SlotPath::new(slot("allowances")).key(alice).key(bob),
+            word_key(
+                bob.into_word(),
+                word_key(alice.into_word(), slot("allowances")),
+            ),
+            U256::from(200),
+        ),
+        (
+            SlotPath::new(slot("allowances")).key(bob).key(alice),
+            word_key(
+                alice.into_word(),
+                word_key(bob.into_word(), slot("allowances")),
+            ),
+            U256::from(300),
+        ),
+    ];
+
+    // positions[7], owner 和 shares 打包在 struct 的第一个 slot, debt 在第二个 slot
+    let position = layout.type_info(
+        layout
+            .type_info(&layout.item("positions")?.type_id)?
+            .value
+            .as_deref()
+            .unwrap(),
+    )?;
+    let member = |label: &str| {
+        let member = position
+            .members
+            .iter()
+            .flatten()
+            .find(|member| member.label == label)
+            .unwrap();
+        SlotPath::new(slot("positions"))
+            .key(U256::from(7))
+            .member(member.slot.to(), member.offset)
+    };
+    let start = word_key(U256::from(7).into(), slot("positions"));
+    let shares = U256::from(5) << 160;
+    cases.push((
+        member("owner"),
+        start,
+        shares | U256::from_be_slice(alice.as_slice()),
+    ));
+    cases.push((member("debt"), start + U256::from(1), U256::from(42)));
+
+    for (path, expected, value) in &cases {
+        assert_eq!(path.slot(), *expected);
+        provider
+            .anvil_set_storage_at(contract, *expected, (*value).into())
+            .await?;
+    }
+    for (path, _, value) in &cases {
+        assert_eq!(
+            provider.get_storage_at(contract, path.slot()).await?,
+            *value
+        );
+    }
+
+    let shares = member("shares");
+    assert_eq!(shares.slot(), member("owner").slot());
+    assert_eq!(shares.offset(), 20);
+    let word = provider.get_storage_at(contract, shares.slot()).await?;
+    assert_eq!(
+        (word >> (shares.offset() * 8)) & U256::from(u128::MAX >> 32),
+        U256::from(5)
+    );
+    assert_eq!(Address::from_word(word.into()), alice);
+
+    Ok(())
+}
+
+/// mainnet 上 WETH9 的 mapping 和嵌套 mapping
+#[tokio::test]
+#[ignore = "forks mainnet, set RPC_URL and run with --ignored"]
+async fn mapping_slots() -> Result<()> {
+    let provider = ProviderBuilder::new()
+        .on_anvil_with_wallet_and_config(|anvil| anvil.fork(common::fork_url()))?;
+    let owner = provider.default_signer_address();
+    let spender = Address::with_last_byte(0xbb);
+
+    let weth = IWETH9::new(WETH, provider.clone());
+    // 开发账户在主网上可能已经有 WETH
+    let before = weth.balanceOf(owner).call().await?._0;
+    weth.deposit()
+        .value(parse_ether("1")?)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    weth.approve(spender, U256::from(200))
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+
+    let balance = SlotPath::new(U256::from(3)).key(owner);
+    let value = provider.get_storage_at(WETH, balance.slot()).await?;
+    assert_eq!(value, before + parse_ether("1")?);
+    assert_eq!(value, weth.balanceOf(owner).call().await?._0);
+
+    let allowance = SlotPath::new(U256::from(4)).key(owner).key(spender);
+    let value = provider.get_storage_at(WETH, allowance.slot()).await?;
+    assert_eq!(value, U256::from(200));
+    assert_eq!(value, weth.allowance(owner, spender).call().await?._0);
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use alloy::{
+    eips::BlockNumberOrTag,
+    providers::{Provider, ProviderBuilder, ext::AnvilApi},
+};
+use eyre::Result;
+use query::timestamp::BlockFinder;
+
+/// 出块间隔不规则的链, 包括时间戳相同的连续区块
+#[tokio::test]
+async fn irregular_block_times() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil();
+    let genesis = provider
+        .get_block_by_number(BlockNumberOrTag::Number(0))
+        .await?
+        .unwrap()
+        .header
+        .timestamp;
+
+    // 区块 1..=9 的时间戳相对创世区块的偏移, 区块 2..=4 和 7, 8 的时间戳相同
+    let offsets = [10, 12, 12, 12, 100, 101, 1000, 1000, 1005];
+    for offset in offsets {
+        provider
+            .anvil_set_next_block_timestamp(genesis + offset)
+            .await?;
+        provider.evm_mine(None).await?;
+    }
+    // 之后是 50 个间隔至少 12 秒的区块
+    provider.anvil_mine(Some(50), Some(12)).await?;
+
+    let mut timestamps = vec![genesis];
+    for number in 1..=provider.get_block_number().await? {
+        let header = provider
+            .get_block_by_number(BlockNumberOrTag::Number(number))
+            .await?
+            .unwrap()
+            .header;
+        timestamps.push(header.timestamp);
+    }
+    assert_eq!(timestamps.len(), offsets.len() + 51);
+    let latest = timestamps.len() as u64 - 1;
+
+    let finder = BlockFinder::new(provider.clone());
+    let at = |offset: u64| genesis + offset;
+
+    // 边界: 创世区块和最新区块之后
+    assert_eq!(finder.first_block_at_or_after(genesis).await?, Some(0));
+    assert_eq!(finder.first_block_at_or_after(genesis - 1).await?, Some(0));
+    assert_eq!(finder.last_block_before(genesis).await?, None);
+    assert_eq!(finder.first_block_at_or_after(at(1)).await?, Some(1));
+    assert_eq!(finder.last_block_before(at(1)).await?, Some(0));
+    let last = timestamps[latest as usize];
+    assert_eq!(finder.first_block_at_or_after(last).await?, Some(latest));
+    assert_eq!(finder.first_block_at_or_after(last + 1).await?, None);
+    assert_eq!(finder.last_block_before(last + 1).await?, Some(latest));
+    assert_eq!(finder.last_block_before(last).await?, Some(latest - 1));
+
+    // 时间戳相同的区块, 返回第一个
+    assert_eq!(finder.first_block_at_or_after(at(12)).await?, Some(2));
+    assert_eq!(finder.last_block_before(at(12)).await?, Some(1));
+    assert_eq!(finder.first_block_at_or_after(at(13)).await?, Some(5));
+    assert_eq!(finder.last_block_before(at(13)).await?, Some(4));
+    assert_eq!(finder.first_block_at_or_after(at(1000)).await?, Some(7));
+    assert_eq!(finder.last_block_before(at(1001)).await?, Some(8));
+
+    // 间隔之中的时间戳
+    assert_eq!(finder.first_block_at_or_after(at(500)).await?, Some(7));
+    assert_eq!(finder.last_block_before(at(500)).await?, Some(6));
+
+    // 每个区块的时间戳, 以及前后 1 秒, 和逐个比较的结果一致
+    for timestamp in timestamps.iter().flat_map(|t| [t - 1, *t, t + 1]) {
+        let first = timestamps
+            .iter()
+            .position(|t| *t >= timestamp)
+            .map(|n| n as u64);
+        let before = timestamps
+            .iter()
+            .rposition(|t| *t < timestamp)
+            .map(|n| n as u64);
+        assert_eq!(
+            finder.first_block_at_or_after(timestamp).await?,
+            first,
+            "{timestamp}"
+        );
+        assert_eq!(
+            finder.last_block_before(timestamp).await?,
+            before,
+            "{timestamp}"
+        );
+    }
+
+    Ok(())
+}
+
+/// 查找附近的时间戳时, 用缓存中的区块缩小范围
+#[tokio::test]
+async fn nearby_lookup_uses_cache() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil();
+    let genesis = provider
+        .get_block_by_number(BlockNumberOrTag::Number(0))
+        .await?
+        .unwrap()
+        .header
+        .timestamp;
+    // 前 100 个区块间隔 2 秒, 之后 100 个区块间隔 30 秒
+    for i in 1..=200u64 {
+        let offset = if i <= 100 {
+            2 * i
+        } else {
+            200 + 30 * (i - 100)
+        };
+        provider
+            .anvil_set_next_block_timestamp(genesis + offset)
+            .await?;
+        provider.evm_mine(None).await?;
+    }
+
+    let finder = BlockFinder::new(provider.clone());
+    assert_eq!(
+        finder.first_block_at_or_after(genesis + 1501).await?,
+        Some(144)
+    );
+    let first = finder.cached();
+
+    assert_eq!(
+        finder.first_block_at_or_after(genesis + 1535).await?,
+        Some(145)
+    );
+    let second = finder.cached() - first;
+    assert!(
+        second < first,
+        "first lookup {first}, second lookup {second}"
+    );
+
+    // 已经读取过的区块不需要再次请求
+    let cached = finder.cached();
+    assert_eq!(
+        finder.first_block_at_or_after(genesis + 1501).await?,
+        Some(144)
+    );
+    assert_eq!(finder.cached(), cached);
+
+    Ok(())
+}
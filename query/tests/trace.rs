@@ -0,0 +1,172 @@
+mod common;
+
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, U256},
+    providers::{Provider, ProviderBuilder},
+    sol,
+    sol_types::SolCall,
+};
+use eyre::Result;
+use query::multicall::{MULTICALL3_ADDRESS, ensure_multicall3};
+use query::trace::{TraceDecoder, trace_call, trace_transaction};
+
+use common::{Counter, CounterWithError};
+
+sol! {
+    #[allow(missing_docs)]
+    // Multicall3 中用到的部分, 合约由 ensure_multicall3 部署
+    #[sol(rpc, abi)]
+    contract Multicall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+    }
+}
+
+fn call3(target: Address, call: impl SolCall) -> Multicall3::Call3 {
+    Multicall3::Call3 {
+        target,
+        allowFailure: false,
+        callData: call.abi_encode().into(),
+    }
+}
+
+/// 部署 Counter, CounterWithError 和 Multicall3, 返回注册了它们的 ABI 和标签的 decoder
+async fn setup(provider: impl Provider + Clone) -> Result<(Address, Address, TraceDecoder)> {
+    assert!(ensure_multicall3(&provider).await?);
+    let counter = *Counter::deploy(provider.clone()).await?.address();
+    let with_error = *CounterWithError::deploy(provider.clone()).await?.address();
+
+    let mut decoder = TraceDecoder::with_common();
+    decoder
+        .add_abi("Counter", &Counter::abi::contract())
+        .add_abi("CounterWithError", &CounterWithError::abi::contract())
+        .add_abi("Multicall3", &Multicall3::abi::contract())
+        .label(counter, "Counter")
+        .label(with_error, "CounterWithError")
+        .label(MULTICALL3_ADDRESS, "Multicall3");
+    Ok((counter, with_error, decoder))
+}
+
+/// Multicall3 依次调用两个合约, 交易的调用树
+#[tokio::test]
+async fn transaction_call_tree() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let (counter, with_error, decoder) = setup(provider.clone()).await?;
+
+    let multicall = Multicall3::new(MULTICALL3_ADDRESS, provider.clone());
+    let receipt = multicall
+        .aggregate3(vec![
+            call3(
+                counter,
+                Counter::setNumberCall {
+                    newNumber: U256::from(42),
+                },
+            ),
+            call3(with_error, CounterWithError::incrementCall {}),
+            call3(counter, Counter::numberCall {}),
+        ])
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+    let call = decoder.decode(&trace_transaction(&provider, receipt.transaction_hash).await?);
+    print!("{call}");
+
+    let aggregate3 = call.function.as_ref().expect("aggregate3 decoded");
+    assert_eq!(aggregate3.function.name, "aggregate3");
+    assert_eq!(call.label.as_deref(), Some("Multicall3"));
+    let names = call
+        .calls
+        .iter()
+        .map(|call| call.function.as_ref().map(|f| f.function.name.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [Some("setNumber"), Some("increment"), Some("number")]
+    );
+    assert!(call.calls.iter().all(|call| call.kind == "CALL"));
+    assert_eq!(
+        call.calls[2].function.as_ref().unwrap().outputs.as_deref(),
+        Some(&[U256::from(42).into()][..])
+    );
+
+    // Increment 事件在 CounterWithError 的调用中发出
+    assert!(call.logs.is_empty());
+    let logs = &call.calls[1].logs;
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].address, Some(with_error));
+    assert!(
+        logs[0]
+            .event
+            .as_deref()
+            .unwrap()
+            .starts_with("CounterWithError Increment(")
+    );
+    assert_eq!(call.iter().count(), 4);
+
+    Ok(())
+}
+
+/// 模拟调用, 自定义错误
+#[tokio::test]
+async fn custom_error() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let (_, with_error, decoder) = setup(provider.clone()).await?;
+
+    let tx = CounterWithError::new(with_error, provider.clone())
+        .revertA()
+        .into_transaction_request();
+    let call = decoder.decode(&trace_call(&provider, tx, BlockId::latest()).await?);
+    print!("{call}");
+    assert!(call.reverted());
+    assert!(call.calls.is_empty());
+    assert_eq!(
+        call.revert_reason.as_deref(),
+        Some("CounterWithError.ErrorA(message=Error A)")
+    );
+
+    Ok(())
+}
+
+/// 子调用 Panic(0x11) 溢出, Multicall3 以 Error(string) revert
+#[tokio::test]
+async fn nested_revert() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let (counter, _, decoder) = setup(provider.clone()).await?;
+    Counter::new(counter, provider.clone())
+        .setNumber(U256::MAX)
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+
+    let tx = Multicall3::new(MULTICALL3_ADDRESS, provider.clone())
+        .aggregate3(vec![call3(counter, Counter::incrementCall {})])
+        .into_transaction_request();
+    let call = decoder.decode(&trace_call(&provider, tx, BlockId::latest()).await?);
+    print!("{call}");
+    assert!(call.reverted());
+    assert!(
+        call.revert_reason
+            .as_deref()
+            .unwrap()
+            .contains("Multicall3: call failed")
+    );
+    let inner = &call.calls[0];
+    assert!(inner.reverted());
+    assert_eq!(inner.label.as_deref(), Some("Counter"));
+    assert!(inner.revert_reason.as_deref().unwrap().contains("overflow"));
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use alloy::{
+    dyn_abi::DynSolValue,
+    primitives::{Address, B256, U256},
+    providers::{Provider, ProviderBuilder},
+};
+use eyre::Result;
+use query::storage::StorageLayout;
+use query::watch::{StorageWatcher, WatchKey};
+
+use common::Counter;
+
+/// WETH9 的 balanceOf, mapping(address => uint256) 在 slot 3
+const WETH9_LAYOUT: &str = r#"{
+  "storage": [
+    {
+      "astId": 1,
+      "contract": "WETH9.sol:WETH9",
+      "label": "balanceOf",
+      "offset": 0,
+      "slot": "3",
+      "type": "t_mapping(t_address,t_uint256)"
+    }
+  ],
+  "types": {
+    "t_address": {
+      "encoding": "inplace",
+      "label": "address",
+      "numberOfBytes": "20"
+    },
+    "t_mapping(t_address,t_uint256)": {
+      "encoding": "mapping",
+      "key": "t_address",
+      "label": "mapping(address => uint256)",
+      "numberOfBytes": "32",
+      "value": "t_uint256"
+    },
+    "t_uint256": {
+      "encoding": "inplace",
+      "label": "uint256",
+      "numberOfBytes": "32"
+    }
+  }
+}"#;
+
+fn counter_layout() -> Result<StorageLayout> {
+    StorageLayout::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/layout/Counter.json"
+    ))
+}
+
+/// 不存在的变量和 mapping 在添加时就返回错误
+#[test]
+fn reject_labels() -> Result<()> {
+    // 不会发送请求
+    let provider = ProviderBuilder::new().on_http("http://localhost:8545".parse()?);
+    let address = Address::with_last_byte(1);
+
+    let err = StorageWatcher::new(provider.clone())
+        .variables(address, counter_layout()?, &["number", "count"])
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("count"), "{err}");
+
+    let err = StorageWatcher::new(provider.clone())
+        .variables(
+            address,
+            StorageLayout::from_json(WETH9_LAYOUT)?,
+            &["balanceOf"],
+        )
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("mapping"), "{err}");
+
+    assert!(
+        StorageWatcher::new(provider.clone())
+            .variables(address, counter_layout()?, &["number"])
+            .is_ok()
+    );
+
+    // 替换 layout 后之前的变量不存在
+    let esrnt = StorageLayout::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/layout/esRNT.json"
+    ))?;
+    let err = StorageWatcher::new(provider)
+        .variables(address, esrnt, &["_locks"])?
+        .variables(address, counter_layout()?, &["number"])
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("_locks"), "{err}");
+    Ok(())
+}
+
+/// 两个合约的 slot 和状态变量在同一个区块读取
+#[tokio::test]
+async fn read_contracts() -> Result<()> {
+    let provider = ProviderBuilder::new().on_anvil_with_wallet();
+    let mut addresses = Vec::new();
+    for number in [7u64, 9] {
+        let counter = Counter::deploy(provider.clone()).await?;
+        counter
+            .setNumber(U256::from(number))
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        addresses.push(*counter.address());
+    }
+
+    let watcher = StorageWatcher::new(provider.clone())
+        .slots(addresses[0], &[U256::ZERO])
+        .variables(addresses[1], counter_layout()?, &["number"])?;
+    let values = watcher.read(provider.get_block_number().await?).await?;
+    assert_eq!(
+        values,
+        [
+            (
+                addresses[0],
+                WatchKey::Slot(U256::ZERO),
+                DynSolValue::FixedBytes(B256::from(U256::from(7)), 32)
+            ),
+            (
+                addresses[1],
+                WatchKey::Variable("number".to_string()),
+                DynSolValue::Uint(U256::from(9), 256)
+            ),
+        ]
+    );
+    Ok(())
+}